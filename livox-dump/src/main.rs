@@ -0,0 +1,226 @@
+use std::error::Error;
+use std::net::Ipv4Addr;
+use std::path::PathBuf;
+use std::time::{SystemTime, UNIX_EPOCH};
+use clap::Parser;
+use serde_json::{json, Value};
+use tokio::net::UdpSocket;
+use tokio::select;
+
+use livox_rs::Livox;
+use livox_rs::capture::{CaptureReader, Datagram, DatagramKind};
+use livox_rs::model::{ControlFrame, PointCloudFrame};
+
+/// Print every Livox frame seen on the network or in a capture.
+///
+/// When listening, only broadcasts (port 55000) and the data port are visible:
+/// command traffic goes between the device and the host program's own sockets,
+/// read a capture of it instead.
+#[derive(Parser, Debug)]
+#[command(version)]
+struct Args {
+    /// Read packets from a pcap/pcapng capture instead of listening.
+    #[arg(short, long, value_name = "FILE")]
+    read: Option<PathBuf>,
+    /// Host data port point cloud packets are sent to. Learned from the handshake in captures.
+    #[arg(short, long)]
+    data_port: Option<u16>,
+    /// Print one JSON object per line.
+    #[arg(long)]
+    json: bool,
+    /// Do not print point cloud packets.
+    #[arg(long)]
+    no_points: bool,
+}
+
+#[tokio::main]
+async fn main() -> Result<(), Box<dyn Error>> {
+    let subscriber = tracing_subscriber::fmt().with_writer(std::io::stderr).finish();
+    tracing::subscriber::set_global_default(subscriber)?;
+    let args = Args::parse();
+
+    if let Some(path) = &args.read {
+        let mut reader = CaptureReader::open(path)?;
+        if let Some(port) = args.data_port {
+            reader = reader.with_data_port(port);
+        }
+        while let Some(datagram) = reader.next_datagram() {
+            print_datagram(&datagram?, &args);
+        }
+        return Ok(());
+    }
+
+    let broadcast_socket = UdpSocket::bind((Ipv4Addr::UNSPECIFIED, Livox::BROADCAST_LISTEN_PORT)).await?;
+    let data_socket = match args.data_port {
+        Some(port) => Some(UdpSocket::bind((Ipv4Addr::UNSPECIFIED, port)).await?),
+        None => None,
+    };
+    let mut broadcast_buf = [0u8; 2048];
+    let mut data_buf = [0u8; 2048];
+
+    loop {
+        let (kind, size, src, socket) = select! {
+            received = broadcast_socket.recv_from(&mut broadcast_buf) => {
+                let (size, src) = received?;
+                (DatagramKind::Control, size, src, &broadcast_socket)
+            }
+            received = async { data_socket.as_ref().unwrap().recv_from(&mut data_buf).await }, if data_socket.is_some() => {
+                let (size, src) = received?;
+                (DatagramKind::PointCloud, size, src, data_socket.as_ref().unwrap())
+            }
+        };
+        let payload = match kind {
+            DatagramKind::Control => &broadcast_buf[..size],
+            DatagramKind::PointCloud => &data_buf[..size],
+        };
+        let datagram = Datagram {
+            timestamp: SystemTime::now().duration_since(UNIX_EPOCH)?,
+            src,
+            dst: socket.local_addr()?,
+            kind,
+            payload: payload.to_vec(),
+        };
+        print_datagram(&datagram, &args);
+    }
+}
+
+fn print_datagram(datagram: &Datagram, args: &Args) {
+    let line = match datagram.kind {
+        DatagramKind::Control => describe_control(datagram, args.json),
+        DatagramKind::PointCloud if args.no_points => return,
+        DatagramKind::PointCloud => describe_point_cloud(datagram, args.json),
+    };
+    println!("{}", line);
+}
+
+fn describe_control(datagram: &Datagram, as_json: bool) -> String {
+    let Datagram { timestamp, src, dst, payload, .. } = datagram;
+    let header = |at: usize| payload.get(at).copied();
+    let cmd_type = match header(4) {
+        Some(0x00) => "CMD",
+        Some(0x01) => "ACK",
+        Some(0x02) => "MSG",
+        _ => "???",
+    };
+    let checksums = ControlFrame::checksums(payload);
+    let frame = ControlFrame::parse_unchecked(payload);
+
+    if as_json {
+        let mut value = json!({
+            "time": timestamp.as_secs_f64(),
+            "src": src.to_string(),
+            "dst": dst.to_string(),
+            "kind": "control",
+            "cmd_type": cmd_type,
+            "cmd_set": header(9),
+            "cmd_id": header(10),
+        });
+        match &checksums {
+            Ok(checksums) => value["checksums"] = json!(checksums),
+            Err(err) => value["checksum_error"] = json!(format!("{:?}", err)),
+        }
+        match &frame {
+            Ok(frame) => {
+                value["version"] = json!(frame.version);
+                value["seq_num"] = json!(frame.seq_num);
+                value["data"] = json!(frame.data);
+            }
+            Err(err) => value["parse_error"] = json!(format!("{:?}", err)),
+        }
+        return value.to_string();
+    }
+
+    let checksums = match checksums {
+        Ok(c) => format!("crc16={} crc32={}", ok(c.crc16_ok), ok(c.crc32_ok)),
+        Err(err) => format!("{:?}", err),
+    };
+    let body = match frame {
+        Ok(frame) => format!("v{} seq={} {:?}", frame.version, frame.seq_num, frame.data),
+        Err(err) => format!("undecodable: {:?}", err),
+    };
+    format!("{:.6} {} -> {} {} set={} id={} {} {}",
+            timestamp.as_secs_f64(), src, dst, cmd_type,
+            hex(header(9)), hex(header(10)), checksums, body)
+}
+
+fn describe_point_cloud(datagram: &Datagram, as_json: bool) -> String {
+    let Datagram { timestamp, src, dst, payload, .. } = datagram;
+    let frame = match PointCloudFrame::parse(payload) {
+        Ok(frame) => frame,
+        Err(err) if as_json => return json!({
+            "time": timestamp.as_secs_f64(),
+            "src": src.to_string(),
+            "dst": dst.to_string(),
+            "kind": "point_cloud",
+            "error": format!("{:?}", err),
+        }).to_string(),
+        Err(err) => return format!("{:.6} {} -> {} DATA undecodable: {:?}",
+                                   timestamp.as_secs_f64(), src, dst, err),
+    };
+
+    // (range in m, reflectivity) of every point with a return
+    let points = frame.data.points();
+    let returns = points.iter()
+        .filter(|p| !p.is_zero())
+        .map(|p| (p.to_point().coords.cast::<f64>().norm(), p.reflectivity))
+        .collect::<Vec<_>>();
+    let total = points.len();
+    let min_range = returns.iter().map(|(r, _)| *r).reduce(f64::min).unwrap_or(0.0);
+    let max_range = returns.iter().map(|(r, _)| *r).reduce(f64::max).unwrap_or(0.0);
+    let mean_reflectivity = if returns.is_empty() { 0.0 } else {
+        returns.iter().map(|(_, r)| *r as f64).sum::<f64>() / returns.len() as f64
+    };
+    let status = &frame.status_code;
+
+    if as_json {
+        return json!({
+            "time": timestamp.as_secs_f64(),
+            "src": src.to_string(),
+            "dst": dst.to_string(),
+            "kind": "point_cloud",
+            "version": frame.version,
+            "slot_id": frame.slot_id,
+            "lidar_id": frame.lidar_id,
+            "data_type": payload[9],
+            "timestamp_type": frame.timestamp_type,
+            "timestamp": frame.timestamp,
+            "status": status_json(&frame),
+            "points": total,
+            "returns": returns.len(),
+            "min_range": min_range,
+            "max_range": max_range,
+            "mean_reflectivity": mean_reflectivity,
+        }).to_string();
+    }
+
+    format!("{:.6} {} -> {} DATA lidar={} type={} ts_type={} ts={} points={}/{} range={:.2}..{:.2}m refl={:.1} system={} sync={}",
+            timestamp.as_secs_f64(), src, dst, frame.lidar_id, payload[9], frame.timestamp_type, frame.timestamp,
+            returns.len(), total, min_range, max_range, mean_reflectivity,
+            status.system_status, status.time_sync_status)
+}
+
+fn status_json(frame: &PointCloudFrame) -> Value {
+    let status = &frame.status_code;
+    json!({
+        "temp_status": status.temp_status,
+        "volt_status": status.volt_status,
+        "motor_status": status.motor_status,
+        "dirty_warn": status.dirty_warn,
+        "firmware_status": status.firmware_status,
+        "pps_status": status.pps_status,
+        "device_status": status.device_status,
+        "fan_status": status.fan_status,
+        "self_heating": status.self_heating,
+        "ptp_status": status.ptp_status,
+        "time_sync_status": status.time_sync_status,
+        "system_status": status.system_status,
+    })
+}
+
+fn ok(valid: bool) -> &'static str {
+    if valid { "ok" } else { "BAD" }
+}
+
+fn hex(byte: Option<u8>) -> String {
+    byte.map_or("--".to_string(), |b| format!("{:#04x}", b))
+}
@@ -189,6 +189,10 @@ pub fn derive_response_fn(input: TokenStream) -> TokenStream {
     (quote! {
         impl Response for #name {
             type Enum = Enum;
+
+            fn ret_code(&self) -> u8 {
+                self.ret_code
+            }
         }
 
         impl From<#name> for Enum {
@@ -0,0 +1,106 @@
+use criterion::{BatchSize, Criterion, criterion_group, criterion_main};
+use nalgebra::{Point3, Vector3};
+
+use livox_rs::capture::{CapturedFrame, CapturedPacket, CaptureReader};
+use livox_rs::cloud::PointCloud;
+use livox_rs::filter::outlier::{RadiusOutlierRemoval, StatisticalOutlierRemoval};
+use livox_rs::filter::voxel::{VoxelGrid, VoxelPolicy};
+use livox_rs::model::data_type::LivoxPoint;
+use livox_rs::point::{BatchPoint, PointBatch};
+
+/// Points per second of a Mid-70.
+const RATE: u64 = 100_000;
+
+/// Capture of a Mid-70 to benchmark on, read with [`CaptureReader`] which learns the data port
+/// from the handshake in it. Without one, the benchmarks run on [`synthetic_cloud`].
+const CAPTURE_VAR: &str = "LIVOX_BENCH_CAPTURE";
+
+/// The first `seconds` of points of the capture in [`CAPTURE_VAR`], else synthetic ones.
+fn mid70_cloud(seconds: u64) -> PointCloud {
+    match std::env::var_os(CAPTURE_VAR) {
+        Some(path) => {
+            let cloud = CaptureReader::open(&path).expect("Failed to open the capture")
+                .filter_map(|packet| match packet {
+                    Ok(CapturedPacket { frame: CapturedFrame::PointCloud(frame), .. }) => Some(PointBatch::from_frame(&frame)),
+                    _ => None,
+                })
+                .flat_map(|batch| batch.iter().collect::<Vec<_>>())
+                .take((seconds * RATE) as usize)
+                .collect::<PointCloud>();
+            assert!(!cloud.is_empty(), "No point cloud data in {:?}", path);
+            cloud
+        }
+        None => synthetic_cloud(seconds),
+    }
+}
+
+/// Synthetic data, not recorded: a Mid-70 like rosette scan over a 70.4 degree circular field of view,
+/// standing in a 6 metre wide corridor 1 metre above the floor, with range noise.
+fn synthetic_cloud(seconds: u64) -> PointCloud {
+    let half_fov = 35.2f64.to_radians();
+    let mut seed = 7u64;
+    let mut noise = || {
+        seed = seed.wrapping_mul(6364136223846793005).wrapping_add(1442695040888963407);
+        (seed >> 40) as f64 / (1u64 << 24) as f64 - 0.5
+    };
+
+    // outward normals and distances of the corridor planes
+    let planes = [
+        (Vector3::new(0.0, 0.0, -1.0), 1.0), // floor
+        (Vector3::new(0.0, 0.0, 1.0), 2.0),
+        (Vector3::new(0.0, 1.0, 0.0), 3.0),
+        (Vector3::new(0.0, -1.0, 0.0), 3.0),
+        (Vector3::new(1.0, 0.0, 0.0), 40.0),
+    ];
+
+    (0..seconds * RATE).filter_map(|i| {
+        let t = i as f64 / RATE as f64;
+        // two prisms turning in opposite directions
+        let (a, b) = (2.0 * std::f64::consts::PI * 120.0 * t, -2.0 * std::f64::consts::PI * 77.0 * t);
+        let (dy, dz) = ((a.cos() + b.cos()) / 2.0 * half_fov, (a.sin() + b.sin()) / 2.0 * half_fov);
+        let direction = Vector3::new(1.0, dy.tan(), dz.tan()).normalize();
+
+        let range = planes.iter()
+            .filter_map(|(normal, distance): &(Vector3<f64>, f64)| {
+                let d = normal.dot(&direction);
+                (d > 1e-9).then(|| distance / d)
+            })
+            .fold(f64::INFINITY, f64::min);
+        // 2cm range noise and a few stray returns
+        let range = if i % 500 == 0 { range * (0.2 + noise().abs()) } else { range + noise() * 0.04 };
+        (range < 90.0).then(|| {
+            let p: Point3<f64> = Point3::from(direction * range);
+            BatchPoint {
+                point: LivoxPoint { x: p.x as f32, y: p.y as f32, z: p.z as f32, reflectivity: (i % 200) as u8, tag: 0 },
+                timestamp: i * 10_000,
+                return_index: 0,
+            }
+        })
+    }).collect()
+}
+
+fn bench_voxel(c: &mut Criterion) {
+    let cloud = mid70_cloud(3);
+    c.bench_function("voxel centroid 5cm, 300k points", |b| {
+        b.iter(|| VoxelGrid::new(0.05).unwrap().downsample(&cloud))
+    });
+    c.bench_function("voxel first point 5cm, 300k points", |b| {
+        b.iter(|| VoxelGrid::new(0.05).unwrap().with_policy(VoxelPolicy::FirstPoint).downsample(&cloud))
+    });
+}
+
+fn bench_outlier(c: &mut Criterion) {
+    let cloud = mid70_cloud(1);
+    let mut group = c.benchmark_group("outlier removal, 100k points");
+    group.sample_size(10);
+    group.bench_function("statistical k=16", |b| {
+        b.iter_batched(|| cloud.clone(), |mut cloud| StatisticalOutlierRemoval::default().apply(&mut cloud), BatchSize::LargeInput)
+    });
+    group.bench_function("radius 10cm", |b| {
+        b.iter_batched(|| cloud.clone(), |mut cloud| RadiusOutlierRemoval { radius: 0.1, min_neighbors: 4 }.apply(&mut cloud), BatchSize::LargeInput)
+    });
+    group.finish();
+}
+
+criterion_group!(benches, bench_voxel, bench_outlier);
+criterion_main!(benches);
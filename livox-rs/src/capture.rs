@@ -0,0 +1,581 @@
+use std::collections::HashMap;
+use std::fs::File;
+use std::io::{BufReader, BufWriter, Cursor, Read, Write};
+use std::net::{Ipv4Addr, SocketAddr, SocketAddrV4};
+use std::path::Path;
+use std::time::Duration;
+use async_stream::stream;
+use nalgebra::SMatrix;
+use pcap_file::DataLink;
+use pcap_file::pcap::{PcapHeader, PcapPacket, PcapReader, PcapWriter};
+use pcap_file::pcapng::{Block, PcapNgReader};
+use tokio::sync::mpsc;
+use tokio::time::{Instant, sleep_until};
+use tracing::{debug, warn};
+
+use crate::{Livox, LivoxError, LivoxResult, RawPacket};
+use crate::clock_align::ClockEstimator;
+use crate::point::PointBatch;
+use crate::model::{ControlFrame, FrameData, PointCloudFrame};
+use crate::model::deku_data_type::{general, RequestData};
+use crate::result_util::ToLivoxResult;
+
+/// Port the LiDAR sends (and receives) control commands on.
+pub const COMMAND_PORT: u16 = 65000;
+
+/// A Livox frame decoded from a capture file.
+#[derive(Debug)]
+pub enum CapturedFrame {
+    /// Broadcast, command, acknowledge or message on port 55000/65000.
+    Control(ControlFrame),
+    /// Point cloud data on the data port.
+    PointCloud(PointCloudFrame),
+}
+
+/// Kind of Livox traffic a datagram carries, judged by its ports.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum DatagramKind {
+    Control,
+    PointCloud,
+}
+
+/// A raw UDP datagram on a Livox port, as seen in a capture.
+#[derive(Debug)]
+pub struct Datagram {
+    /// Capture timestamp, since UNIX epoch.
+    pub timestamp: Duration,
+    pub src: SocketAddr,
+    pub dst: SocketAddr,
+    pub kind: DatagramKind,
+    /// The UDP payload, after IP reassembly.
+    pub payload: Vec<u8>,
+}
+
+/// A UDP datagram carrying a Livox frame, as seen in a capture.
+#[derive(Debug)]
+pub struct CapturedPacket {
+    /// Capture timestamp, since UNIX epoch.
+    pub timestamp: Duration,
+    pub src: SocketAddr,
+    pub dst: SocketAddr,
+    /// The UDP payload, after IP reassembly.
+    pub payload: Vec<u8>,
+    pub frame: CapturedFrame,
+}
+
+/// How [`CaptureReader::stream`] paces the packets it yields.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Pacing {
+    /// Yield packets as fast as they are read.
+    AsFastAsPossible,
+    /// Reproduce the inter-packet delays recorded in the capture.
+    Realtime,
+}
+
+enum Source {
+    Pcap(PcapReader<Box<dyn Read + Send>>),
+    PcapNg(PcapNgReader<Box<dyn Read + Send>>),
+}
+
+#[derive(Hash, PartialEq, Eq, Clone, Copy)]
+struct FragmentKey {
+    src: Ipv4Addr,
+    dst: Ipv4Addr,
+    id: u16,
+}
+
+#[derive(Default)]
+struct Fragments {
+    parts: Vec<(usize, Vec<u8>)>,
+    total_len: Option<usize>,
+}
+
+/// Reads Livox traffic from a pcap or pcapng capture.
+///
+/// UDP datagrams on [`Livox::BROADCAST_LISTEN_PORT`] and [`COMMAND_PORT`] are decoded with
+/// [`ControlFrame::parse`], datagrams sent to the data port with [`PointCloudFrame::parse`].
+/// The data port is learned from the handshake in the capture, unless set with
+/// [`CaptureReader::with_data_port`].
+/// Point cloud frames get a host timestamp aligned to capture times, per source LiDAR.
+pub struct CaptureReader {
+    source: Source,
+    fragments: HashMap<FragmentKey, Fragments>,
+    data_port: Option<u16>,
+    data_port_fixed: bool,
+    clocks: HashMap<SocketAddr, ClockEstimator>,
+    /// Set once reading the capture failed, the reader can not resume past a corrupt record.
+    failed: bool,
+}
+
+impl CaptureReader {
+    /// Pending fragmented datagrams kept before the reassembly buffer is flushed.
+    const MAX_PENDING_FRAGMENTS: usize = 256;
+
+    /// Open a capture file, detecting pcap or pcapng by its magic number.
+    pub fn open(path: impl AsRef<Path>) -> LivoxResult<Self> {
+        let file = File::open(path).err_reason("While opening capture file")?;
+        Self::new(BufReader::new(file))
+    }
+
+    /// Read a capture from any reader, detecting pcap or pcapng by its magic number.
+    pub fn new(mut reader: impl Read + Send + 'static) -> LivoxResult<Self> {
+        let mut magic = [0u8; 4];
+        reader.read_exact(&mut magic).err_reason("While reading capture magic number")?;
+        let reader: Box<dyn Read + Send> = Box::new(Cursor::new(magic).chain(reader));
+
+        let source = match magic {
+            [0x0a, 0x0d, 0x0d, 0x0a] => Source::PcapNg(PcapNgReader::new(reader)
+                .err_reason("While reading pcapng section header")?),
+            _ => Source::Pcap(PcapReader::new(reader)
+                .err_reason("While reading pcap header")?),
+        };
+
+        Ok(CaptureReader {
+            source,
+            fragments: HashMap::new(),
+            data_port: None,
+            data_port_fixed: false,
+            clocks: HashMap::new(),
+            failed: false,
+        })
+    }
+
+    /// Use a fixed host data port instead of learning it from the handshake.
+    pub fn with_data_port(mut self, port: u16) -> Self {
+        self.data_port = Some(port);
+        self.data_port_fixed = true;
+        self
+    }
+
+    /// Host data port currently used to recognize point cloud packets.
+    pub fn data_port(&self) -> Option<u16> {
+        self.data_port
+    }
+
+    /// Read the next link layer frame as `(timestamp, link type, data)`.
+    fn next_link_frame(&mut self) -> Option<LivoxResult<(Duration, DataLink, Vec<u8>)>> {
+        match &mut self.source {
+            Source::Pcap(reader) => {
+                let datalink = reader.header().datalink;
+                reader.next_packet().map(|packet| packet
+                    .map(|packet| (packet.timestamp, datalink, packet.data.into_owned()))
+                    .err_reason("While reading pcap packet"))
+            }
+            Source::PcapNg(reader) => loop {
+                let block = match reader.next_block()? {
+                    Ok(block) => block.into_owned(),
+                    Err(err) => return Some(Err(err).err_reason("While reading pcapng block")),
+                };
+                match block {
+                    Block::EnhancedPacket(packet) => {
+                        let datalink = match reader.packet_interface(&packet) {
+                            Some(interface) => interface.linktype,
+                            None => {
+                                warn!("Packet refers to unknown interface {}", packet.interface_id);
+                                continue;
+                            }
+                        };
+                        return Some(Ok((packet.timestamp, datalink, packet.data.into_owned())));
+                    }
+                    Block::SimplePacket(packet) => {
+                        let datalink = match reader.interfaces().first() {
+                            Some(interface) => interface.linktype,
+                            None => continue,
+                        };
+                        return Some(Ok((Duration::ZERO, datalink, packet.data.into_owned())));
+                    }
+                    _ => continue,
+                }
+            },
+        }
+    }
+
+    /// Reassemble IPv4 fragments, returning the full IP payload once complete.
+    fn reassemble(&mut self, key: FragmentKey, offset: usize, more: bool, data: &[u8]) -> Option<Vec<u8>> {
+        if self.fragments.len() >= Self::MAX_PENDING_FRAGMENTS {
+            warn!("Too many incomplete IP fragments, dropping {} of them", self.fragments.len());
+            self.fragments.clear();
+        }
+        let entry = self.fragments.entry(key).or_default();
+        entry.parts.push((offset, data.to_vec()));
+        if !more { entry.total_len = Some(offset + data.len()); }
+
+        let total_len = entry.total_len?;
+        entry.parts.sort_by_key(|(offset, _)| *offset);
+        let mut covered = 0;
+        for (offset, part) in &entry.parts {
+            if *offset > covered { return None; }
+            covered = covered.max(offset + part.len());
+        }
+        if covered < total_len { return None; }
+
+        let entry = self.fragments.remove(&key)?;
+        let mut payload = vec![0u8; total_len];
+        // a corrupt capture may have parts past the end
+        for (offset, part) in entry.parts.into_iter().filter(|(offset, _)| *offset < total_len) {
+            let end = (offset + part.len()).min(total_len);
+            payload[offset..end].copy_from_slice(&part[..end - offset]);
+        }
+        Some(payload)
+    }
+
+    /// Decode a link layer frame into a UDP datagram `(src, dst, payload)`.
+    fn udp_datagram(&mut self, datalink: DataLink, frame: &[u8]) -> Option<(SocketAddr, SocketAddr, Vec<u8>)> {
+        let ip = link_payload(datalink, frame)?;
+        if ip.len() < 20 || ip[0] >> 4 != 4 { return None; }
+        let header_len = (ip[0] & 0x0f) as usize * 4;
+        let total_len = (u16::from_be_bytes([ip[2], ip[3]]) as usize).min(ip.len());
+        if ip[9] != 17 /*UDP*/ || header_len < 20 || total_len < header_len { return None; }
+
+        let src_ip = Ipv4Addr::new(ip[12], ip[13], ip[14], ip[15]);
+        let dst_ip = Ipv4Addr::new(ip[16], ip[17], ip[18], ip[19]);
+        let flags_offset = u16::from_be_bytes([ip[6], ip[7]]);
+        let more = flags_offset & 0x2000 != 0;
+        let offset = (flags_offset & 0x1fff) as usize * 8;
+        let data = &ip[header_len..total_len];
+
+        let udp = if more || offset != 0 {
+            let key = FragmentKey { src: src_ip, dst: dst_ip, id: u16::from_be_bytes([ip[4], ip[5]]) };
+            self.reassemble(key, offset, more, data)?
+        } else {
+            data.to_vec()
+        };
+
+        if udp.len() < 8 { return None; }
+        let src_port = u16::from_be_bytes([udp[0], udp[1]]);
+        let dst_port = u16::from_be_bytes([udp[2], udp[3]]);
+        let udp_len = (u16::from_be_bytes([udp[4], udp[5]]) as usize).clamp(8, udp.len());
+        Some((SocketAddrV4::new(src_ip, src_port).into(),
+              SocketAddrV4::new(dst_ip, dst_port).into(),
+              udp[8..udp_len].to_vec()))
+    }
+
+    /// Classify a UDP datagram by its ports, learning the data port from handshakes.
+    fn classify(&mut self, src: &SocketAddr, dst: &SocketAddr, payload: &[u8]) -> Option<DatagramKind> {
+        let control_ports = [Livox::BROADCAST_LISTEN_PORT, COMMAND_PORT];
+        if control_ports.contains(&src.port()) || control_ports.contains(&dst.port()) {
+            if let Ok(ControlFrame { data: FrameData::Request(RequestData::General(
+                general::request::Enum::Handshake(handshake))), .. }) = ControlFrame::parse_unchecked(payload) {
+                if !self.data_port_fixed && self.data_port != Some(handshake.data_port) {
+                    debug!("Learned data port {} from handshake", handshake.data_port);
+                    self.data_port = Some(handshake.data_port);
+                }
+            }
+            Some(DatagramKind::Control)
+        } else if self.data_port == Some(dst.port()) {
+            Some(DatagramKind::PointCloud)
+        } else {
+            None
+        }
+    }
+
+    /// Read the next UDP datagram carrying Livox traffic, without decoding it.
+    ///
+    /// A read error, e.g. a truncated capture, is returned once and ends the capture.
+    pub fn next_datagram(&mut self) -> Option<LivoxResult<Datagram>> {
+        if self.failed { return None; }
+        loop {
+            let (timestamp, datalink, frame) = match self.next_link_frame()? {
+                Ok(frame) => frame,
+                Err(err) => {
+                    self.failed = true;
+                    return Some(Err(err));
+                }
+            };
+            let Some((src, dst, payload)) = self.udp_datagram(datalink, &frame) else { continue; };
+            if let Some(kind) = self.classify(&src, &dst, &payload) {
+                return Some(Ok(Datagram { timestamp, src, dst, kind, payload }));
+            }
+        }
+    }
+
+    /// Get a async stream of captured packets, read on a blocking thread.
+    ///
+    /// Packets that fail to parse are yielded as errors and the replay goes on,
+    /// like the live streams; the stream ends after an error reading the capture.
+    pub fn stream(self, pacing: Pacing) -> impl tokio_stream::Stream<Item=LivoxResult<CapturedPacket>> {
+        let (tx, mut rx) = mpsc::channel(1024);
+        tokio::task::spawn_blocking(move || {
+            for packet in self {
+                if tx.blocking_send(packet).is_err() { break; }
+            }
+        });
+
+        stream! {
+            let mut origin: Option<(Instant, Duration)> = None;
+            while let Some(packet) = rx.recv().await {
+                let packet = match packet {
+                    Ok(packet) => packet,
+                    Err(err) => {
+                        yield Err(err);
+                        continue;
+                    }
+                };
+                if pacing == Pacing::Realtime {
+                    let (start, first) = *origin.get_or_insert((Instant::now(), packet.timestamp));
+                    sleep_until(start + packet.timestamp.saturating_sub(first)).await;
+                }
+                yield Ok(packet);
+            }
+        }
+    }
+
+    /// Get a async stream of point batches of the captured LiDAR data,
+    /// like [`LivoxClient::point_stream`](crate::LivoxClient::point_stream).
+    pub fn point_stream(self, pacing: Pacing) -> impl tokio_stream::Stream<Item=LivoxResult<PointBatch>> {
+        use tokio_stream::StreamExt;
+
+        self.stream(pacing).filter_map(|packet| match packet {
+            Ok(CapturedPacket { frame: CapturedFrame::PointCloud(frame), .. }) => Some(Ok(PointBatch::from_frame(&frame))),
+            Ok(_) => None,
+            Err(err) => Some(Err(err)),
+        })
+    }
+
+    /// Get a async stream of homogeneous matrix of the captured LiDAR data,
+    /// like [`LivoxClient::homogeneous_matrix_stream`](crate::LivoxClient::homogeneous_matrix_stream).
+    pub fn homogeneous_matrix_stream(self, pacing: Pacing) -> impl tokio_stream::Stream<Item=LivoxResult<SMatrix<f32, 4, 96>>> {
+        use tokio_stream::StreamExt;
+
+        self.point_stream(pacing).map(|batch| batch?.to_homogeneous_mm()
+            .ok_or(LivoxError::ParseError(crate::model::ParseError::WrongPointCloudSize)))
+    }
+}
+
+impl Iterator for CaptureReader {
+    type Item = LivoxResult<CapturedPacket>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        let Datagram { timestamp, src, dst, kind, payload } = match self.next_datagram()? {
+            Ok(datagram) => datagram,
+            Err(err) => return Some(Err(err)),
+        };
+        let frame = match kind {
+            DatagramKind::Control => ControlFrame::parse(&payload).map(CapturedFrame::Control),
+            DatagramKind::PointCloud => PointCloudFrame::parse(&payload).map(|mut frame| {
+                let estimator = self.clocks.entry(src).or_default();
+                frame.align_host_time(estimator, timestamp.as_nanos() as u64);
+                CapturedFrame::PointCloud(frame)
+            }),
+        };
+        Some(frame.map(|frame| CapturedPacket { timestamp, src, dst, payload, frame }).map_err(LivoxError::ParseError))
+    }
+}
+
+/// Writes received point cloud packets to a pcap file of raw IPv4 packets, which
+/// [`CaptureReader`] reads back given the data port, see [`CaptureReader::with_data_port`].
+pub struct CaptureWriter<W: Write> {
+    writer: PcapWriter<W>,
+    dst: SocketAddrV4,
+    id: u16,
+}
+
+impl CaptureWriter<BufWriter<File>> {
+    pub fn create(path: impl AsRef<Path>, dst: SocketAddrV4) -> LivoxResult<Self> {
+        let file = File::create(path).err_reason("While creating capture file")?;
+        Self::new(BufWriter::new(file), dst)
+    }
+}
+
+impl<W: Write> CaptureWriter<W> {
+    /// `dst` is the host address packets were received on.
+    pub fn new(writer: W, dst: SocketAddrV4) -> LivoxResult<Self> {
+        let header = PcapHeader { datalink: DataLink::RAW, ..PcapHeader::default() };
+        let writer = PcapWriter::with_header(writer, header).err_reason("While writing pcap header")?;
+        Ok(CaptureWriter { writer, dst, id: 0 })
+    }
+
+    pub fn write_packet(&mut self, packet: &RawPacket) -> LivoxResult<()> {
+        let src = match packet.src {
+            SocketAddr::V4(src) => src,
+            SocketAddr::V6(src) => SocketAddrV4::new(Ipv4Addr::UNSPECIFIED, src.port()),
+        };
+        let total_len = (20 + 8 + packet.payload.len()) as u16;
+        let mut ip = Vec::with_capacity(total_len as usize);
+        ip.extend_from_slice(&[0x45, 0]);
+        ip.extend_from_slice(&total_len.to_be_bytes());
+        ip.extend_from_slice(&self.id.to_be_bytes());
+        ip.extend_from_slice(&[0, 0, 64, 17, 0, 0]);
+        ip.extend_from_slice(&src.ip().octets());
+        ip.extend_from_slice(&self.dst.ip().octets());
+        let checksum = !ip.chunks(2).fold(0u32, |sum, word| {
+            let sum = sum + u16::from_be_bytes([word[0], word[1]]) as u32;
+            (sum & 0xffff) + (sum >> 16)
+        }) as u16;
+        ip[10..12].copy_from_slice(&checksum.to_be_bytes());
+        // UDP checksum is optional over IPv4
+        ip.extend_from_slice(&src.port().to_be_bytes());
+        ip.extend_from_slice(&self.dst.port().to_be_bytes());
+        ip.extend_from_slice(&(total_len - 20).to_be_bytes());
+        ip.extend_from_slice(&[0, 0]);
+        ip.extend_from_slice(&packet.payload);
+        self.id = self.id.wrapping_add(1);
+
+        let timestamp = Duration::from_nanos(packet.arrival);
+        self.writer.write_packet(&PcapPacket::new(timestamp, ip.len() as u32, &ip))
+            .map(|_| ()).err_reason("While writing pcap packet")
+    }
+
+    pub fn into_inner(self) -> W {
+        self.writer.into_writer()
+    }
+}
+
+/// Strip the link layer header, returning the IPv4 packet if there is one.
+fn link_payload(datalink: DataLink, frame: &[u8]) -> Option<&[u8]> {
+    const ETHERTYPE_IPV4: u16 = 0x0800;
+    const ETHERTYPE_VLAN: u16 = 0x8100;
+    let ether_type = |at: usize| frame.get(at..at + 2).map(|b| u16::from_be_bytes([b[0], b[1]]));
+
+    match datalink {
+        DataLink::ETHERNET => {
+            let mut offset = 12;
+            while ether_type(offset)? == ETHERTYPE_VLAN { offset += 4; }
+            (ether_type(offset)? == ETHERTYPE_IPV4).then(|| &frame[offset + 2..])
+        }
+        DataLink::LINUX_SLL => (ether_type(14)? == ETHERTYPE_IPV4).then(|| &frame[16..]),
+        DataLink::LINUX_SLL2 => (ether_type(0)? == ETHERTYPE_IPV4).then(|| frame.get(20..)).flatten(),
+        DataLink::RAW | DataLink::IPV4 => Some(frame),
+        DataLink::NULL | DataLink::LOOP => frame.get(4..),
+        _ => None,
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use std::io::Cursor;
+    use std::net::Ipv4Addr;
+    use std::time::Duration;
+    use pcap_file::pcap::{PcapPacket, PcapWriter};
+
+    use super::*;
+
+    fn ethernet_udp(src: (Ipv4Addr, u16), dst: (Ipv4Addr, u16), id: u16, frag: u16, ip_payload: &[u8]) -> Vec<u8> {
+        let mut frame = vec![0u8; 12];
+        frame.extend_from_slice(&0x0800u16.to_be_bytes());
+        frame.extend_from_slice(&[0x45, 0]);
+        frame.extend_from_slice(&(20 + ip_payload.len() as u16).to_be_bytes());
+        frame.extend_from_slice(&id.to_be_bytes());
+        frame.extend_from_slice(&frag.to_be_bytes());
+        frame.extend_from_slice(&[64, 17, 0, 0]);
+        frame.extend_from_slice(&src.0.octets());
+        frame.extend_from_slice(&dst.0.octets());
+        frame.extend_from_slice(ip_payload);
+        frame
+    }
+
+    fn udp(src_port: u16, dst_port: u16, payload: &[u8]) -> Vec<u8> {
+        let mut udp = Vec::new();
+        udp.extend_from_slice(&src_port.to_be_bytes());
+        udp.extend_from_slice(&dst_port.to_be_bytes());
+        udp.extend_from_slice(&(8 + payload.len() as u16).to_be_bytes());
+        udp.extend_from_slice(&[0, 0]);
+        udp.extend_from_slice(payload);
+        udp
+    }
+
+    fn capture(frames: &[Vec<u8>]) -> Vec<u8> {
+        let mut writer = PcapWriter::new(Vec::new()).unwrap();
+        for (i, frame) in frames.iter().enumerate() {
+            writer.write_packet(&PcapPacket::new(Duration::from_millis(i as u64), frame.len() as u32, frame)).unwrap();
+        }
+        writer.into_writer()
+    }
+
+    #[test]
+    fn test_broadcast_from_pcap() {
+        let lidar = Ipv4Addr::new(192, 168, 1, 100);
+        let broadcast = include_bytes!("../bin/broadcast.dump");
+        let data = capture(&[ethernet_udp((lidar, 65000), (Ipv4Addr::BROADCAST, 55000), 1, 0,
+                                          &udp(65000, 55000, broadcast))]);
+
+        let packets = CaptureReader::new(Cursor::new(data)).unwrap().collect::<Vec<_>>();
+        assert_eq!(packets.len(), 1);
+        let packet = packets.into_iter().next().unwrap().unwrap();
+        assert_eq!(packet.src, SocketAddr::from((lidar, 65000)));
+        assert_eq!(packet.payload, broadcast);
+        assert!(matches!(packet.frame, CapturedFrame::Control(ControlFrame { data: FrameData::Message(_), .. })));
+    }
+
+    #[test]
+    fn test_truncated_capture_ends() {
+        let lidar = Ipv4Addr::new(192, 168, 1, 100);
+        let broadcast = include_bytes!("../bin/broadcast.dump");
+        let frame = ethernet_udp((lidar, 65000), (Ipv4Addr::BROADCAST, 55000), 1, 0, &udp(65000, 55000, broadcast));
+        let mut data = capture(&[frame.clone(), frame]);
+        data.truncate(data.len() - 10);
+
+        let packets = CaptureReader::new(Cursor::new(data)).unwrap().take(10).collect::<Vec<_>>();
+        assert_eq!(packets.len(), 2);
+        assert!(packets[0].is_ok());
+        assert!(packets[1].is_err());
+    }
+
+    #[tokio::test]
+    async fn test_stream_skips_bad_packet() {
+        use tokio_stream::StreamExt;
+
+        let lidar = Ipv4Addr::new(192, 168, 1, 100);
+        let broadcast = include_bytes!("../bin/broadcast.dump");
+        let mut corrupt = broadcast.to_vec();
+        *corrupt.last_mut().unwrap() ^= 0xff;
+        let frame = |payload: &[u8]| ethernet_udp((lidar, 65000), (Ipv4Addr::BROADCAST, 55000), 1, 0,
+                                                  &udp(65000, 55000, payload));
+        let data = capture(&[frame(broadcast), frame(&corrupt), frame(broadcast)]);
+
+        let reader = CaptureReader::new(Cursor::new(data)).unwrap();
+        let packets = reader.stream(Pacing::AsFastAsPossible).collect::<Vec<_>>().await;
+        assert_eq!(packets.len(), 3);
+        assert!(packets[0].is_ok());
+        assert!(matches!(packets[1], Err(LivoxError::ParseError(_))));
+        assert!(packets[2].is_ok());
+    }
+
+    #[test]
+    fn test_write_capture() {
+        let host = SocketAddrV4::new(Ipv4Addr::new(192, 168, 1, 50), 56001);
+        let mut writer = CaptureWriter::new(Vec::new(), host).unwrap();
+        let packet = RawPacket {
+            arrival: 1_500_000_000,
+            src: SocketAddr::from((Ipv4Addr::new(192, 168, 1, 100), 65001)),
+            payload: bytes::Bytes::from_static(&[5, 0, 1, 0, 1, 2, 3]),
+        };
+        writer.write_packet(&packet).unwrap();
+        writer.write_packet(&packet).unwrap();
+
+        let mut reader = CaptureReader::new(Cursor::new(writer.into_inner())).unwrap().with_data_port(56001);
+        let datagram = reader.next_datagram().unwrap().unwrap();
+        assert_eq!((datagram.src, datagram.dst), (packet.src, SocketAddr::V4(host)));
+        assert_eq!(datagram.kind, DatagramKind::PointCloud);
+        assert_eq!(datagram.payload, &packet.payload[..]);
+        assert_eq!(datagram.timestamp, Duration::from_millis(1500));
+        assert!(reader.next_datagram().unwrap().is_ok());
+        assert!(reader.next_datagram().is_none());
+    }
+
+    #[test]
+    fn test_fragment_reassembly() {
+        let lidar = Ipv4Addr::new(192, 168, 1, 100);
+        let host = Ipv4Addr::new(192, 168, 1, 50);
+        let ack = include_bytes!("../bin/heartbeat_response.dump");
+        let datagram = udp(65000, 50001, ack);
+        let (first, second) = datagram.split_at(16);
+        let data = capture(&[
+            ethernet_udp((lidar, 65000), (host, 50001), 7, 0x2000, first),
+            ethernet_udp((lidar, 65000), (host, 50001), 7, 2, second),
+        ]);
+
+        let packets = CaptureReader::new(Cursor::new(data)).unwrap().collect::<Vec<_>>();
+        assert_eq!(packets.len(), 1);
+        let packet = packets.into_iter().next().unwrap().unwrap();
+        assert_eq!(packet.payload, ack);
+        assert_eq!(packet.timestamp, Duration::from_millis(1));
+
+        // a part past the end of the last one must not panic
+        let mut reader = CaptureReader::new(Cursor::new(capture(&[]))).unwrap();
+        let key = FragmentKey { src: lidar, dst: host, id: 8 };
+        assert_eq!(reader.reassemble(key, 0, true, &[1; 400]), None);
+        assert_eq!(reader.reassemble(key, 304, true, &[2; 8]), None);
+        assert_eq!(reader.reassemble(key, 96, false, &[3; 56]), Some([[1; 96].as_slice(), &[3; 56]].concat()));
+    }
+}
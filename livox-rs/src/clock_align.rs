@@ -0,0 +1,198 @@
+use std::collections::VecDeque;
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
+use tracing::info;
+
+/// Maps device timestamps to host system time from packet arrival times,
+/// for LiDARs without PTP/GPS synchronisation.
+///
+/// Every packet gives a sample `offset = arrival - device`, which is the true offset plus
+/// a non-negative transport latency. The minimum offset of each window is kept, and a line
+/// fitted through the minima of recent windows gives offset and drift between the two clocks.
+/// A device timestamp going backwards or jumping by more than the reset threshold
+/// (e.g. the device got synchronised) restarts the estimation.
+#[derive(Debug, Clone)]
+pub struct ClockEstimator {
+    window_ns: u64,
+    max_windows: usize,
+    reset_threshold_ns: i64,
+    windows: VecDeque<Sample>,
+    current: Option<Sample>,
+    current_start: u64,
+    last_device: Option<u64>,
+    model: Option<Model>,
+}
+
+#[derive(Debug, Clone, Copy)]
+struct Sample {
+    device: u64,
+    offset: i64,
+}
+
+/// `host = device + offset + drift * (device - reference)`
+#[derive(Debug, Clone, Copy)]
+struct Model {
+    reference: u64,
+    offset: f64,
+    drift: f64,
+}
+
+impl Default for ClockEstimator {
+    fn default() -> Self {
+        Self::new(Duration::from_secs(1), 30)
+    }
+}
+
+impl ClockEstimator {
+    /// Keep the minimum offset of every `window` of device time, fitting over the last `windows` of them.
+    pub fn new(window: Duration, windows: usize) -> Self {
+        ClockEstimator {
+            window_ns: window.as_nanos() as u64,
+            max_windows: windows.max(1),
+            reset_threshold_ns: Duration::from_secs(1).as_nanos() as i64,
+            windows: VecDeque::new(),
+            current: None,
+            current_start: 0,
+            last_device: None,
+            model: None,
+        }
+    }
+
+    /// Restart estimation when a sample is this far from the prediction. Defaults to 1 second.
+    pub fn with_reset_threshold(mut self, threshold: Duration) -> Self {
+        self.reset_threshold_ns = threshold.as_nanos() as i64;
+        self
+    }
+
+    /// Forget every sample.
+    pub fn reset(&mut self) {
+        self.windows.clear();
+        self.current = None;
+        self.last_device = None;
+        self.model = None;
+    }
+
+    /// Feed a device timestamp and the host time it arrived at, both in nanoseconds,
+    /// returning the host time estimated for `device`.
+    pub fn update(&mut self, device: u64, host: u64) -> u64 {
+        let offset = host as i64 - device as i64;
+        let jumped = self.last_device.is_some_and(|last| device < last)
+            || self.to_host(device).is_some_and(|h| (host as i64 - h as i64).abs() > self.reset_threshold_ns);
+        if jumped {
+            info!("Device clock jumped, restarting clock estimation");
+            self.reset();
+        }
+        self.last_device = Some(device);
+
+        match &mut self.current {
+            Some(current) if device - self.current_start < self.window_ns => {
+                if offset < current.offset {
+                    *current = Sample { device, offset };
+                }
+            }
+            _ => {
+                if let Some(finished) = self.current.take() {
+                    self.windows.push_back(finished);
+                    if self.windows.len() > self.max_windows {
+                        self.windows.pop_front();
+                    }
+                }
+                self.current = Some(Sample { device, offset });
+                self.current_start = device;
+            }
+        }
+        self.fit();
+        self.to_host(device).unwrap()
+    }
+
+    /// Feed a device timestamp that arrived just now.
+    pub fn update_now(&mut self, device: u64) -> u64 {
+        self.update(device, now_ns())
+    }
+
+    /// Host time of a device timestamp, in nanoseconds, `None` before the first sample.
+    pub fn to_host(&self, device: u64) -> Option<u64> {
+        self.model.map(|m| {
+            let offset = m.offset + m.drift * (device as f64 - m.reference as f64);
+            (device as i64 + offset.round() as i64) as u64
+        })
+    }
+
+    /// Current offset `host - device`, in nanoseconds.
+    pub fn offset_ns(&self) -> Option<i64> {
+        let device = self.last_device?;
+        Some(self.to_host(device)? as i64 - device as i64)
+    }
+
+    /// Drift of the device clock against the host clock, in parts per million.
+    pub fn drift_ppm(&self) -> Option<f64> {
+        self.model.map(|m| m.drift * 1e6)
+    }
+
+    /// Least squares line through the minima of every window.
+    fn fit(&mut self) {
+        let samples = self.windows.iter().chain(self.current.iter()).copied().collect::<Vec<_>>();
+        let Some(first) = samples.first() else {
+            self.model = None;
+            return;
+        };
+        let reference = first.device;
+        let n = samples.len() as f64;
+        let xs = samples.iter().map(|s| (s.device - reference) as f64);
+        let ys = samples.iter().map(|s| s.offset as f64);
+        let mean_x = xs.clone().sum::<f64>() / n;
+        let mean_y = ys.clone().sum::<f64>() / n;
+        let sxx = xs.clone().map(|x| (x - mean_x).powi(2)).sum::<f64>();
+        let sxy = xs.zip(ys).map(|(x, y)| (x - mean_x) * (y - mean_y)).sum::<f64>();
+
+        // Drift is only meaningful once minima span a few windows.
+        let drift = if samples.len() >= 3 && sxx > 0.0 { sxy / sxx } else { 0.0 };
+        self.model = Some(Model { reference, offset: mean_y - drift * mean_x, drift });
+    }
+}
+
+/// Host system time in nanoseconds since UNIX epoch.
+pub fn now_ns() -> u64 {
+    SystemTime::now().duration_since(UNIX_EPOCH).unwrap_or_default().as_nanos() as u64
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn test_offset_and_drift() {
+        const OFFSET: i64 = 1_660_000_000_000_000_000;
+        const DRIFT: f64 = 50e-6;
+
+        let mut estimator = ClockEstimator::default();
+        let mut seed = 12345u64;
+        let mut latency = || {
+            seed = seed.wrapping_mul(6364136223846793005).wrapping_add(1442695040888963407);
+            // 50us to ~5ms, with the occasional fast packet
+            if seed >> 60 == 0 { 50_000 } else { 50_000 + (seed >> 33) % 5_000_000 }
+        };
+
+        let true_host = |device: u64| (device as i64 + OFFSET + (device as f64 * DRIFT) as i64) as u64;
+        for i in 0..20_000u64 {
+            // 20 seconds of packets, every millisecond
+            let device = 5_000_000_000 + i * 1_000_000;
+            estimator.update(device, true_host(device) + latency());
+        }
+
+        let device = 5_000_000_000 + 20_000 * 1_000_000;
+        let error = estimator.to_host(device).unwrap() as i64 - true_host(device) as i64;
+        assert!(error.abs() < 200_000, "error {}ns", error);
+        assert!((estimator.drift_ppm().unwrap() - 50.0).abs() < 10.0, "drift {:?}", estimator.drift_ppm());
+    }
+
+    #[test]
+    fn test_reset_on_jump() {
+        let mut estimator = ClockEstimator::default();
+        estimator.update(10_000_000_000, 100_000_000_000);
+        assert_eq!(estimator.offset_ns(), Some(90_000_000_000));
+
+        // device restarted
+        estimator.update(1_000_000, 101_000_000_000);
+        assert_eq!(estimator.offset_ns(), Some(100_999_000_000));
+    }
+}
@@ -0,0 +1,136 @@
+use std::time::Duration;
+use async_stream::stream;
+use nalgebra::{Matrix4xX, Point3};
+use tokio_stream::{Stream, StreamExt};
+
+use crate::LivoxResult;
+use crate::point::{BatchPoint, PointBatch};
+
+pub mod kdtree;
+
+/// Points accumulated from several packets, e.g. to integrate the non-repetitive scan of a Mid-70.
+#[derive(Debug, Clone, PartialEq, Default)]
+pub struct PointCloud {
+    pub points: Vec<BatchPoint>,
+}
+
+impl PointCloud {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn with_capacity(capacity: usize) -> Self {
+        PointCloud { points: Vec::with_capacity(capacity) }
+    }
+
+    pub fn len(&self) -> usize {
+        self.points.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.points.is_empty()
+    }
+
+    pub fn push_batch(&mut self, batch: &PointBatch) {
+        self.points.extend(batch.iter());
+    }
+
+    /// Keep the points for which `keep` returns true.
+    pub fn retain(&mut self, keep: impl FnMut(&BatchPoint) -> bool) {
+        self.points.retain(keep);
+    }
+
+    /// Coordinates in metres.
+    pub fn positions(&self) -> Vec<Point3<f32>> {
+        self.points.iter().map(|p| p.point.to_point()).collect()
+    }
+
+    /// Sensor timestamps of the first and last points, in nanoseconds.
+    pub fn time_span(&self) -> Option<(u64, u64)> {
+        let first = self.points.iter().map(|p| p.timestamp).min()?;
+        let last = self.points.iter().map(|p| p.timestamp).max()?;
+        Some((first, last))
+    }
+
+    /// Homogeneous coordinates as columns, in metres.
+    pub fn to_homogeneous(&self) -> Matrix4xX<f32> {
+        Matrix4xX::from_fn(self.len(), |r, c| {
+            let p = &self.points[c].point;
+            [p.x, p.y, p.z, 1.0][r]
+        })
+    }
+
+    /// Group the batches of a stream into clouds spanning `period` of sensor time,
+    /// see [`CloudAccumulator`]. Errors are passed through without interrupting the accumulation.
+    pub fn accumulate(stream: impl Stream<Item=LivoxResult<PointBatch>>, period: Duration)
+                      -> impl Stream<Item=LivoxResult<PointCloud>> {
+        stream! {
+            tokio::pin!(stream);
+            let mut accumulator = CloudAccumulator::new(period);
+            while let Some(batch) = stream.next().await {
+                match batch {
+                    Ok(batch) => if let Some(cloud) = accumulator.push(&batch) {
+                        yield Ok(cloud);
+                    },
+                    Err(err) => yield Err(err),
+                }
+            }
+            if let Some(cloud) = accumulator.flush() {
+                yield Ok(cloud);
+            }
+        }
+    }
+}
+
+/// Groups batches into clouds spanning a period of sensor time, starting at the first batch of each.
+#[derive(Debug, Clone)]
+pub struct CloudAccumulator {
+    period_ns: u64,
+    start: Option<u64>,
+    cloud: PointCloud,
+}
+
+impl CloudAccumulator {
+    pub fn new(period: Duration) -> Self {
+        CloudAccumulator { period_ns: period.as_nanos() as u64, start: None, cloud: PointCloud::new() }
+    }
+
+    /// Add a batch, returning the previous cloud when the batch is past its period.
+    pub fn push(&mut self, batch: &PointBatch) -> Option<PointCloud> {
+        let timestamp = batch.packet.timestamp;
+        // a timestamp going backwards also ends the cloud
+        let finished = self.start
+            .is_some_and(|start| timestamp < start || timestamp - start >= self.period_ns)
+            .then(|| {
+                self.start = None;
+                std::mem::take(&mut self.cloud)
+            });
+        self.start.get_or_insert(timestamp);
+        self.cloud.push_batch(batch);
+        finished
+    }
+
+    /// Whether no batch was added since the last cloud.
+    pub fn is_empty(&self) -> bool {
+        self.start.is_none()
+    }
+
+    /// Take the current cloud, `None` if it has no points.
+    pub fn flush(&mut self) -> Option<PointCloud> {
+        self.start = None;
+        let cloud = std::mem::take(&mut self.cloud);
+        (!cloud.is_empty()).then_some(cloud)
+    }
+}
+
+impl FromIterator<BatchPoint> for PointCloud {
+    fn from_iter<T: IntoIterator<Item=BatchPoint>>(iter: T) -> Self {
+        PointCloud { points: iter.into_iter().collect() }
+    }
+}
+
+impl Extend<BatchPoint> for PointCloud {
+    fn extend<T: IntoIterator<Item=BatchPoint>>(&mut self, iter: T) {
+        self.points.extend(iter);
+    }
+}
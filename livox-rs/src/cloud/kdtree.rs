@@ -0,0 +1,136 @@
+use nalgebra::Point3;
+
+/// Static k-d tree over a set of points, for neighbour queries on accumulated clouds.
+///
+/// The tree is implicit: indices are permuted so that every subrange has its splitting
+/// point in the middle, splitting along x, y and z in turn.
+#[derive(Debug, Clone)]
+pub struct KdTree {
+    points: Vec<Point3<f32>>,
+    indices: Vec<usize>,
+}
+
+impl KdTree {
+    pub fn new(points: Vec<Point3<f32>>) -> Self {
+        let mut indices = (0..points.len()).collect::<Vec<_>>();
+        build(&points, &mut indices, 0);
+        KdTree { points, indices }
+    }
+
+    pub fn len(&self) -> usize {
+        self.points.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.points.is_empty()
+    }
+
+    pub fn point(&self, i: usize) -> &Point3<f32> {
+        &self.points[i]
+    }
+
+    /// The `k` points nearest to `query` as `(index, squared distance)`, nearest first.
+    pub fn nearest(&self, query: &Point3<f32>, k: usize) -> Vec<(usize, f32)> {
+        let mut found = Vec::with_capacity(k + 1);
+        if k > 0 {
+            self.search_nearest(query, k, 0, self.indices.len(), 0, &mut found);
+        }
+        found
+    }
+
+    /// Indices of the points within `radius` of `query`.
+    pub fn within(&self, query: &Point3<f32>, radius: f32) -> Vec<usize> {
+        let mut found = Vec::new();
+        self.search_within(query, radius * radius, 0, self.indices.len(), 0, &mut |i| {
+            found.push(i);
+            true
+        });
+        found
+    }
+
+    /// Number of points within `radius` of `query`, counting no further than `limit`.
+    pub fn count_within(&self, query: &Point3<f32>, radius: f32, limit: usize) -> usize {
+        let mut count = 0;
+        self.search_within(query, radius * radius, 0, self.indices.len(), 0, &mut |_| {
+            count += 1;
+            count < limit
+        });
+        count
+    }
+
+    fn search_nearest(&self, query: &Point3<f32>, k: usize, lo: usize, hi: usize, depth: usize,
+                      found: &mut Vec<(usize, f32)>) {
+        if lo >= hi { return; }
+        let mid = (lo + hi) / 2;
+        let index = self.indices[mid];
+        let point = &self.points[index];
+
+        let distance = (point - query).norm_squared();
+        if found.len() < k || distance < found[k - 1].1 {
+            let at = found.partition_point(|&(_, d)| d <= distance);
+            found.insert(at, (index, distance));
+            found.truncate(k);
+        }
+
+        let axis = depth % 3;
+        let delta = query[axis] - point[axis];
+        let (near, far) = if delta < 0.0 { ((lo, mid), (mid + 1, hi)) } else { ((mid + 1, hi), (lo, mid)) };
+        self.search_nearest(query, k, near.0, near.1, depth + 1, found);
+        if found.len() < k || delta * delta < found[k - 1].1 {
+            self.search_nearest(query, k, far.0, far.1, depth + 1, found);
+        }
+    }
+
+    /// Calls `visit` on every point in range until it returns false, returning false if stopped.
+    fn search_within(&self, query: &Point3<f32>, radius_sq: f32, lo: usize, hi: usize, depth: usize,
+                     visit: &mut impl FnMut(usize) -> bool) -> bool {
+        if lo >= hi { return true; }
+        let mid = (lo + hi) / 2;
+        let index = self.indices[mid];
+        let point = &self.points[index];
+
+        if (point - query).norm_squared() <= radius_sq && !visit(index) {
+            return false;
+        }
+        let delta = query[depth % 3] - point[depth % 3];
+        let reach = delta * delta <= radius_sq;
+        ((delta > 0.0 && !reach) || self.search_within(query, radius_sq, lo, mid, depth + 1, visit))
+            && ((delta < 0.0 && !reach) || self.search_within(query, radius_sq, mid + 1, hi, depth + 1, visit))
+    }
+}
+
+fn build(points: &[Point3<f32>], indices: &mut [usize], depth: usize) {
+    if indices.len() <= 1 { return; }
+    let axis = depth % 3;
+    let mid = indices.len() / 2;
+    indices.select_nth_unstable_by(mid, |&a, &b| points[a][axis].total_cmp(&points[b][axis]));
+    let (left, right) = indices.split_at_mut(mid);
+    build(points, left, depth + 1);
+    build(points, &mut right[1..], depth + 1);
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn test_queries() {
+        let mut seed = 42u64;
+        let mut random = || {
+            seed = seed.wrapping_mul(6364136223846793005).wrapping_add(1442695040888963407);
+            (seed >> 40) as f32 / (1u64 << 24) as f32 * 10.0
+        };
+        let points = (0..2000).map(|_| Point3::new(random(), random(), random())).collect::<Vec<_>>();
+        let tree = KdTree::new(points.clone());
+
+        let query = Point3::new(5.0, 5.0, 5.0);
+        let mut brute = points.iter().enumerate().map(|(i, p)| (i, (p - query).norm_squared())).collect::<Vec<_>>();
+        brute.sort_by(|a, b| a.1.total_cmp(&b.1));
+        let nearest = tree.nearest(&query, 8);
+        assert_eq!(nearest.iter().map(|n| n.0).collect::<Vec<_>>(), brute[..8].iter().map(|n| n.0).collect::<Vec<_>>());
+
+        let inside = brute.iter().filter(|(_, d)| *d <= 1.5 * 1.5).count();
+        assert_eq!(tree.within(&query, 1.5).len(), inside);
+        assert_eq!(tree.count_within(&query, 1.5, 3), 3.min(inside));
+    }
+}
@@ -0,0 +1,139 @@
+use std::time::Duration;
+use tracing::info;
+
+use crate::point::PointBatch;
+use crate::projection::{Camera, DepthImage, Projection};
+
+pub mod densify;
+
+/// Accumulates projected points into a depth map over a sliding time window.
+///
+/// Every pixel keeps the nearest return received within the window, and is emptied once
+/// its return is older than the window. Timestamps are in nanoseconds, from any clock
+/// as long as inserts and samples use the same one, e.g. host-aligned timestamps of the points.
+/// A timestamp going back by more than the window (e.g. the device rebooted or lost its
+/// synchronisation) empties the map, or every later return would count as expired.
+#[derive(Debug, Clone)]
+pub struct DepthAccumulator {
+    width: u32,
+    height: u32,
+    window_ns: u64,
+    depth: Vec<f32>,
+    time: Vec<u64>,
+    latest: u64,
+}
+
+impl DepthAccumulator {
+    pub fn new(width: u32, height: u32, window: Duration) -> Self {
+        let len = width as usize * height as usize;
+        DepthAccumulator {
+            width,
+            height,
+            window_ns: window.as_nanos() as u64,
+            depth: vec![0.0; len],
+            time: vec![0; len],
+            latest: 0,
+        }
+    }
+
+    /// An accumulator of the size of the camera image.
+    pub fn for_camera(camera: &Camera, window: Duration) -> Self {
+        Self::new(camera.width(), camera.height(), window)
+    }
+
+    pub fn window(&self) -> Duration {
+        Duration::from_nanos(self.window_ns)
+    }
+
+    pub fn set_window(&mut self, window: Duration) {
+        self.window_ns = window.as_nanos() as u64;
+    }
+
+    /// Most recent timestamp inserted.
+    pub fn latest(&self) -> u64 {
+        self.latest
+    }
+
+    fn expired(&self, i: usize, now: u64) -> bool {
+        self.depth[i] == 0.0 || now.saturating_sub(self.time[i]) > self.window_ns
+    }
+
+    /// Insert a projected point seen at `timestamp`.
+    pub fn insert(&mut self, projection: &Projection, timestamp: u64) {
+        let (x, y) = projection.pixel_index();
+        if x >= self.width || y >= self.height { return; }
+        if self.latest.saturating_sub(timestamp) > self.window_ns {
+            info!("Clock went back by {}ms, clearing the depth map", (self.latest - timestamp) / 1_000_000);
+            self.clear();
+        }
+        let i = (y * self.width + x) as usize;
+        self.latest = self.latest.max(timestamp);
+        if projection.depth < self.depth[i] || self.expired(i, timestamp) {
+            self.depth[i] = projection.depth;
+            self.time[i] = timestamp;
+        }
+    }
+
+    /// Project and insert the visible points of a batch, with their host-aligned timestamps,
+    /// or sensor timestamps when the batch has none.
+    pub fn insert_batch(&mut self, camera: &Camera, batch: &PointBatch) {
+        for (i, projection) in camera.project_batch(batch) {
+            self.insert(&projection, batch.host_time(i).unwrap_or(batch.timestamp[i]));
+        }
+    }
+
+    /// The depth map at `now`, without the returns older than the window.
+    pub fn sample_at(&self, now: u64) -> DepthImage {
+        let mut image = DepthImage::new(self.width, self.height);
+        for (i, depth) in image.depth.iter_mut().enumerate() {
+            if !self.expired(i, now) {
+                *depth = self.depth[i];
+            }
+        }
+        image
+    }
+
+    /// The depth map at the time of the latest insert.
+    pub fn sample(&self) -> DepthImage {
+        self.sample_at(self.latest)
+    }
+
+    pub fn clear(&mut self) {
+        self.depth.fill(0.0);
+        self.latest = 0;
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use nalgebra::Point2;
+    use super::*;
+
+    #[test]
+    fn test_accumulate() {
+        const MS: u64 = 1_000_000;
+        let mut accumulator = DepthAccumulator::new(4, 4, Duration::from_millis(100));
+        let at = |x: f32, depth: f32| Projection { pixel: Point2::new(x, 1.5), depth };
+
+        accumulator.insert(&at(1.5, 10.0), 0);
+        accumulator.insert(&at(1.5, 12.0), 10 * MS);
+        accumulator.insert(&at(2.5, 3.0), 50 * MS);
+        // nearest return is kept
+        assert_eq!(accumulator.sample().get(1, 1), Some(10.0));
+        assert_eq!(accumulator.sample().get(2, 1), Some(3.0));
+
+        // the return at 10m ages out, the farther one replaces it from then on
+        assert_eq!(accumulator.sample_at(120 * MS).get(1, 1), None);
+        accumulator.insert(&at(1.5, 12.0), 120 * MS);
+        assert_eq!(accumulator.sample().get(1, 1), Some(12.0));
+        assert_eq!(accumulator.sample().get(2, 1), Some(3.0));
+        assert_eq!(accumulator.sample_at(200 * MS).pixels().count(), 1);
+
+        // a clock going back within the window keeps the map, further back it starts over
+        accumulator.insert(&at(0.5, 5.0), 60 * MS);
+        assert_eq!(accumulator.sample().pixels().count(), 3);
+        accumulator.insert(&at(0.5, 5.0), 5 * MS);
+        assert_eq!(accumulator.latest(), 5 * MS);
+        assert_eq!(accumulator.sample().pixels().count(), 1);
+    }
+}
@@ -0,0 +1,179 @@
+use std::num::NonZeroUsize;
+use std::thread;
+
+use crate::projection::DepthImage;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+#[cfg_attr(feature = "serde", serde(rename_all = "snake_case"))]
+pub enum DensifyMethod {
+    /// Fill empty pixels with the depth of the nearest valid pixel.
+    Dilation,
+    /// Fill empty pixels with the weighted average of the valid pixels around,
+    /// only averaging pixels on the nearest surface so that edges stay sharp.
+    #[default]
+    EdgeAware,
+}
+
+/// Fills the empty pixels of a sparse depth map, e.g. from a [`DepthAccumulator`](super::DepthAccumulator),
+/// from the valid pixels within `radius`.
+#[derive(Debug, Clone, Copy, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+#[cfg_attr(feature = "serde", serde(default, deny_unknown_fields))]
+pub struct Densify {
+    pub method: DensifyMethod,
+    /// Search radius, in pixels.
+    pub radius: u32,
+    /// Relative depth difference from the nearest surface above which a pixel is
+    /// considered across a discontinuity, for [`DensifyMethod::EdgeAware`].
+    pub max_depth_jump: f32,
+    /// Worker threads, `0` for one per CPU.
+    pub threads: usize,
+}
+
+impl Default for Densify {
+    fn default() -> Self {
+        Densify { method: DensifyMethod::default(), radius: 6, max_depth_jump: 0.05, threads: 0 }
+    }
+}
+
+/// Densified depth map with the confidence of every pixel.
+#[derive(Debug, Clone, PartialEq)]
+pub struct DenseDepth {
+    pub depth: DepthImage,
+    /// `1` for measured pixels, decreasing with the distance to the valid pixels
+    /// and the disagreement between them, `0` for pixels left empty. Row major.
+    pub confidence: Vec<f32>,
+}
+
+impl Densify {
+    pub fn apply(&self, sparse: &DepthImage) -> DenseDepth {
+        let width = sparse.width as usize;
+        if sparse.depth.is_empty() {
+            return DenseDepth { depth: sparse.clone(), confidence: vec![] };
+        }
+        let valid = sparse.pixels().collect::<Vec<_>>();
+        let mut out = vec![(0.0f32, 0.0f32); sparse.depth.len()];
+
+        let threads = match self.threads {
+            0 => thread::available_parallelism().map_or(1, NonZeroUsize::get),
+            n => n,
+        };
+        let rows_per_band = (sparse.height as usize).div_ceil(threads).max(1);
+        thread::scope(|scope| {
+            for (band_index, band) in out.chunks_mut(width * rows_per_band).enumerate() {
+                let valid = &valid;
+                scope.spawn(move || self.fill_band(sparse, valid, band_index * rows_per_band, band));
+            }
+        });
+
+        let (depth, confidence) = out.into_iter().unzip();
+        DenseDepth { depth: DepthImage { width: sparse.width, height: sparse.height, depth }, confidence }
+    }
+
+    /// Fill rows starting at `y0` from the valid pixels, sorted by row, whose window reaches them.
+    fn fill_band(&self, sparse: &DepthImage, valid: &[(u32, u32, f32)], y0: usize, band: &mut [(f32, f32)]) {
+        let width = sparse.width as usize;
+        let rows = band.len() / width;
+        let r = self.radius as i64;
+        let (first, last) = (y0 as i64 - r, (y0 + rows) as i64 + r);
+        let start = valid.partition_point(|p| (p.1 as i64) < first);
+        let end = valid.partition_point(|p| (p.1 as i64) < last);
+        let valid = &valid[start..end];
+
+        // every valid pixel within the radius of a band pixel, with its squared distance
+        let neighbours = |visit: &mut dyn FnMut(usize, i64, f32)| {
+            for &(x, y, depth) in valid {
+                for dy in -r..=r {
+                    let row = y as i64 + dy - y0 as i64;
+                    if row < 0 || row >= rows as i64 { continue; }
+                    for dx in -r..=r {
+                        let column = x as i64 + dx;
+                        let distance = dx * dx + dy * dy;
+                        if column < 0 || column >= width as i64 || distance > r * r { continue; }
+                        visit(row as usize * width + column as usize, distance, depth);
+                    }
+                }
+            }
+        };
+
+        // nearest valid pixel, the nearest surface on ties
+        let mut nearest = vec![(i64::MAX, 0.0f32); band.len()];
+        neighbours(&mut |i, distance, depth| {
+            let cell = &mut nearest[i];
+            if distance < cell.0 || (distance == cell.0 && depth < cell.1) {
+                *cell = (distance, depth);
+            }
+        });
+        let falloff = |distance: i64| 1.0 - (distance as f32).sqrt() / (r + 1) as f32;
+
+        match self.method {
+            DensifyMethod::Dilation => {
+                for (cell, (distance, depth)) in band.iter_mut().zip(nearest) {
+                    if distance != i64::MAX {
+                        *cell = (depth, falloff(distance));
+                    }
+                }
+            }
+            DensifyMethod::EdgeAware => {
+                let mut foreground = vec![f32::INFINITY; band.len()];
+                neighbours(&mut |i, _, depth| foreground[i] = foreground[i].min(depth));
+
+                // (weighted depth, weight on the nearest surface, total weight)
+                let mut sums = vec![(0.0f32, 0.0f32, 0.0f32); band.len()];
+                neighbours(&mut |i, distance, depth| {
+                    let weight = falloff(distance);
+                    let sum = &mut sums[i];
+                    if depth <= foreground[i] * (1.0 + self.max_depth_jump) {
+                        sum.0 += weight * depth;
+                        sum.1 += weight;
+                    }
+                    sum.2 += weight;
+                });
+
+                for (i, cell) in band.iter_mut().enumerate() {
+                    let (distance, depth) = nearest[i];
+                    let (weighted, surface, total) = sums[i];
+                    *cell = match distance {
+                        i64::MAX => (0.0, 0.0),
+                        0 => (depth, 1.0),
+                        _ => (weighted / surface, surface / total * falloff(distance)),
+                    };
+                }
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use nalgebra::Point2;
+    use crate::projection::Projection;
+    use super::*;
+
+    #[test]
+    fn test_densify() {
+        // a wall at 10m with a post at 2m in front of it, sampled every 4 pixels
+        let mut sparse = DepthImage::new(40, 20);
+        for y in (0..20).step_by(4) {
+            for x in (0..40).step_by(4) {
+                let depth = if (16..24).contains(&x) { 2.0 } else { 10.0 };
+                sparse.insert(&Projection { pixel: Point2::new(x as f32 + 0.5, y as f32 + 0.5), depth });
+            }
+        }
+
+        for threads in [1, 3] {
+            let dense = Densify { method: DensifyMethod::Dilation, radius: 5, threads, ..Densify::default() }.apply(&sparse);
+            assert_eq!(dense.depth.get(1, 1), Some(10.0));
+            assert_eq!(dense.confidence[0], 1.0);
+            assert!(dense.confidence[21] < 1.0 && dense.confidence[21] > 0.0);
+            assert_eq!(dense.depth.pixels().count(), 40 * 20);
+        }
+
+        let dense = Densify { radius: 3, threads: 2, ..Densify::default() }.apply(&sparse);
+        // next to the post, only the post is averaged, with a lower confidence than on the wall
+        assert_eq!(dense.depth.get(14, 2), Some(2.0));
+        assert_eq!(dense.depth.get(6, 2), Some(10.0));
+        assert!(dense.confidence[2 * 40 + 14] < dense.confidence[2 * 40 + 6]);
+    }
+}
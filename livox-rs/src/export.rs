@@ -0,0 +1,463 @@
+use std::fs::File;
+use std::io::{BufWriter, Seek, SeekFrom, Write};
+use std::path::Path;
+use std::time::{SystemTime, UNIX_EPOCH};
+
+use crate::LivoxResult;
+use crate::model::PointCloudFrame;
+use crate::result_util::ToLivoxResult;
+
+/// A point as written by the exporters.
+#[derive(Debug, Clone, Copy, PartialEq, Default)]
+pub struct ExportPoint {
+    /// Coordinates in metres.
+    pub x: f32,
+    pub y: f32,
+    pub z: f32,
+    pub reflectivity: u8,
+    /// Raw [`TagInfo`](crate::model::data_type::TagInfo) byte.
+    pub tag: u8,
+    /// Sensor timestamp in nanoseconds.
+    pub timestamp: u64,
+}
+
+impl ExportPoint {
+    /// Points of a point cloud frame, with per-point timestamps.
+    pub fn from_frame(frame: &PointCloudFrame) -> Vec<ExportPoint> {
+        frame.data.points().iter().enumerate().map(|(i, p)| ExportPoint {
+            x: p.x,
+            y: p.y,
+            z: p.z,
+            reflectivity: p.reflectivity,
+            tag: p.tag,
+            timestamp: frame.point_timestamp(i),
+        }).collect()
+    }
+
+    fn seconds(&self) -> f64 {
+        self.timestamp as f64 / 1e9
+    }
+}
+
+/// A point with the colour of the camera pixel it falls in, see
+/// [`Colouriser`](crate::projection::colour::Colouriser).
+#[derive(Debug, Clone, Copy, PartialEq, Default)]
+pub struct ColourPoint {
+    pub point: ExportPoint,
+    pub rgb: [u8; 3],
+}
+
+/// A point cloud file writer, streaming points to disk as they come.
+pub trait PointWriter {
+    fn write_point(&mut self, point: &ExportPoint) -> LivoxResult<()>;
+
+    fn write_points(&mut self, points: &[ExportPoint]) -> LivoxResult<()> {
+        points.iter().try_for_each(|p| self.write_point(p))
+    }
+
+    fn write_frame(&mut self, frame: &PointCloudFrame) -> LivoxResult<()> {
+        self.write_points(&ExportPoint::from_frame(frame))
+    }
+
+    /// Number of points written so far.
+    fn count(&self) -> u64;
+
+    /// Patch the header with final point count and flush the file.
+    fn finish(&mut self) -> LivoxResult<()>;
+}
+
+/// Supported export formats.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Format {
+    PcdAscii,
+    PcdBinary,
+    Ply,
+    Las,
+}
+
+impl Format {
+    /// Guess the format from a file extension, PCD defaults to binary.
+    pub fn from_path(path: impl AsRef<Path>) -> Option<Format> {
+        let extension = path.as_ref().extension()?.to_str()?.to_ascii_lowercase();
+        match extension.as_str() {
+            "pcd" => Some(Format::PcdBinary),
+            "ply" => Some(Format::Ply),
+            "las" => Some(Format::Las),
+            _ => None,
+        }
+    }
+
+    /// Create a file and a writer of this format for it.
+    pub fn create(self, path: impl AsRef<Path>) -> LivoxResult<Box<dyn PointWriter>> {
+        let file = File::create(path).err_reason("While creating export file")?;
+        Ok(match self {
+            Format::PcdAscii => Box::new(PcdWriter::new(file, PcdEncoding::Ascii)?),
+            Format::PcdBinary => Box::new(PcdWriter::new(file, PcdEncoding::Binary)?),
+            Format::Ply => Box::new(PlyWriter::new(file)?),
+            Format::Las => Box::new(LasWriter::new(file)?),
+        })
+    }
+}
+
+/// Width reserved in text headers for the point count, so it can be patched in place.
+const COUNT_WIDTH: usize = 20;
+
+/// Data encoding of a PCD file.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum PcdEncoding {
+    Ascii,
+    Binary,
+}
+
+/// Writes [PCD v0.7](https://pointclouds.org/documentation/tutorials/pcd_file_format.html) files.
+pub struct PcdWriter<W: Write + Seek> {
+    writer: BufWriter<W>,
+    encoding: PcdEncoding,
+    count: u64,
+}
+
+impl<W: Write + Seek> PcdWriter<W> {
+    pub fn new(writer: W, encoding: PcdEncoding) -> LivoxResult<Self> {
+        let mut writer = PcdWriter { writer: BufWriter::new(writer), encoding, count: 0 };
+        writer.write_header()?;
+        Ok(writer)
+    }
+
+    fn write_header(&mut self) -> LivoxResult<()> {
+        let count = format!("{:<width$}", self.count, width = COUNT_WIDTH);
+        let encoding = match self.encoding {
+            PcdEncoding::Ascii => "ascii",
+            PcdEncoding::Binary => "binary",
+        };
+        write!(self.writer, "# .PCD v0.7 - Point Cloud Data file format\n\
+                             VERSION 0.7\n\
+                             FIELDS x y z reflectivity tag timestamp\n\
+                             SIZE 4 4 4 1 1 8\n\
+                             TYPE F F F U U F\n\
+                             COUNT 1 1 1 1 1 1\n\
+                             WIDTH {count}\n\
+                             HEIGHT 1\n\
+                             VIEWPOINT 0 0 0 1 0 0 0\n\
+                             POINTS {count}\n\
+                             DATA {encoding}\n").err_reason("While writing PCD header")
+    }
+
+    pub fn into_inner(mut self) -> LivoxResult<W> {
+        self.finish()?;
+        self.writer.into_inner().map_err(|err| err.into_error()).err_reason("While flushing PCD file")
+    }
+}
+
+impl<W: Write + Seek> PointWriter for PcdWriter<W> {
+    fn write_point(&mut self, p: &ExportPoint) -> LivoxResult<()> {
+        match self.encoding {
+            PcdEncoding::Ascii => writeln!(self.writer, "{} {} {} {} {} {:.9}",
+                                           p.x, p.y, p.z, p.reflectivity, p.tag, p.seconds()),
+            PcdEncoding::Binary => {
+                let mut record = [0u8; 22];
+                record[0..4].copy_from_slice(&p.x.to_le_bytes());
+                record[4..8].copy_from_slice(&p.y.to_le_bytes());
+                record[8..12].copy_from_slice(&p.z.to_le_bytes());
+                record[12] = p.reflectivity;
+                record[13] = p.tag;
+                record[14..22].copy_from_slice(&p.seconds().to_le_bytes());
+                self.writer.write_all(&record)
+            }
+        }.err_reason("While writing PCD point")?;
+        self.count += 1;
+        Ok(())
+    }
+
+    fn count(&self) -> u64 {
+        self.count
+    }
+
+    fn finish(&mut self) -> LivoxResult<()> {
+        let end = self.writer.stream_position().err_reason("While finishing PCD file")?;
+        self.writer.seek(SeekFrom::Start(0)).err_reason("While finishing PCD file")?;
+        self.write_header()?;
+        self.writer.seek(SeekFrom::Start(end)).err_reason("While finishing PCD file")?;
+        self.writer.flush().err_reason("While flushing PCD file")
+    }
+}
+
+/// Writes binary little endian [PLY](http://paulbourke.net/dataformats/ply/) files.
+///
+/// Writers created with [`PlyWriter::with_colour`] also store the colour of [`ColourPoint`]s,
+/// as `red`, `green` and `blue` properties most viewers understand.
+pub struct PlyWriter<W: Write + Seek> {
+    writer: BufWriter<W>,
+    colour: bool,
+    count: u64,
+}
+
+impl<W: Write + Seek> PlyWriter<W> {
+    pub fn new(writer: W) -> LivoxResult<Self> {
+        Self::create(writer, false)
+    }
+
+    pub fn with_colour(writer: W) -> LivoxResult<Self> {
+        Self::create(writer, true)
+    }
+
+    fn create(writer: W, colour: bool) -> LivoxResult<Self> {
+        let mut writer = PlyWriter { writer: BufWriter::new(writer), colour, count: 0 };
+        writer.write_header()?;
+        Ok(writer)
+    }
+
+    fn write_header(&mut self) -> LivoxResult<()> {
+        let colour = if self.colour { "property uchar red\nproperty uchar green\nproperty uchar blue\n" } else { "" };
+        write!(self.writer, "ply\n\
+                             format binary_little_endian 1.0\n\
+                             comment generated by livox-rs\n\
+                             element vertex {:<width$}\n\
+                             property float x\n\
+                             property float y\n\
+                             property float z\n\
+                             {colour}\
+                             property uchar reflectivity\n\
+                             property uchar tag\n\
+                             property double timestamp\n\
+                             end_header\n", self.count, width = COUNT_WIDTH).err_reason("While writing PLY header")
+    }
+
+    /// Write a point with its colour, black for [`PointWriter::write_point`].
+    /// The colour is left out if the writer was not created [`PlyWriter::with_colour`].
+    pub fn write_coloured(&mut self, p: &ColourPoint) -> LivoxResult<()> {
+        let mut record = [0u8; 25];
+        record[0..4].copy_from_slice(&p.point.x.to_le_bytes());
+        record[4..8].copy_from_slice(&p.point.y.to_le_bytes());
+        record[8..12].copy_from_slice(&p.point.z.to_le_bytes());
+        let rest = if self.colour {
+            record[12..15].copy_from_slice(&p.rgb);
+            15
+        } else { 12 };
+        record[rest] = p.point.reflectivity;
+        record[rest + 1] = p.point.tag;
+        record[rest + 2..rest + 10].copy_from_slice(&p.point.seconds().to_le_bytes());
+        self.writer.write_all(&record[..rest + 10]).err_reason("While writing PLY point")?;
+        self.count += 1;
+        Ok(())
+    }
+
+    pub fn write_coloured_points(&mut self, points: &[ColourPoint]) -> LivoxResult<()> {
+        points.iter().try_for_each(|p| self.write_coloured(p))
+    }
+
+    pub fn into_inner(mut self) -> LivoxResult<W> {
+        self.finish()?;
+        self.writer.into_inner().map_err(|err| err.into_error()).err_reason("While flushing PLY file")
+    }
+}
+
+impl<W: Write + Seek> PointWriter for PlyWriter<W> {
+    fn write_point(&mut self, p: &ExportPoint) -> LivoxResult<()> {
+        self.write_coloured(&ColourPoint { point: *p, rgb: [0; 3] })
+    }
+
+    fn count(&self) -> u64 {
+        self.count
+    }
+
+    fn finish(&mut self) -> LivoxResult<()> {
+        let end = self.writer.stream_position().err_reason("While finishing PLY file")?;
+        self.writer.seek(SeekFrom::Start(0)).err_reason("While finishing PLY file")?;
+        self.write_header()?;
+        self.writer.seek(SeekFrom::Start(end)).err_reason("While finishing PLY file")?;
+        self.writer.flush().err_reason("While flushing PLY file")
+    }
+}
+
+/// Writes [LAS 1.4](https://www.asprs.org/divisions-committees/lidar-division/laser-las-file-format-exchange-activities)
+/// files with point data record format 6, coordinates stored in millimetres.
+///
+/// Reflectivity is stored as intensity, the tag byte as user data and the sensor timestamp
+/// (in seconds) as GPS time.
+pub struct LasWriter<W: Write + Seek> {
+    writer: BufWriter<W>,
+    count: u64,
+    min: [i32; 3],
+    max: [i32; 3],
+}
+
+impl<W: Write + Seek> LasWriter<W> {
+    const HEADER_SIZE: u16 = 375;
+    const POINT_FORMAT: u8 = 6;
+    const POINT_RECORD_LEN: u16 = 30;
+    const SCALE: f64 = 0.001;
+
+    pub fn new(writer: W) -> LivoxResult<Self> {
+        let mut writer = LasWriter { writer: BufWriter::new(writer), count: 0, min: [i32::MAX; 3], max: [i32::MIN; 3] };
+        writer.write_header()?;
+        Ok(writer)
+    }
+
+    fn write_header(&mut self) -> LivoxResult<()> {
+        let (day_of_year, year) = creation_date();
+        let (min, max) = if self.count == 0 { ([0; 3], [0; 3]) } else { (self.min, self.max) };
+
+        let mut header = Vec::with_capacity(Self::HEADER_SIZE as usize);
+        header.extend_from_slice(b"LASF");
+        header.extend_from_slice(&0u16.to_le_bytes()); // File source ID
+        header.extend_from_slice(&0x0010u16.to_le_bytes()); // Global encoding: WKT
+        header.extend_from_slice(&[0u8; 16]); // Project GUID
+        header.extend_from_slice(&[1, 4]); // Version 1.4
+        header.extend_from_slice(&fixed_str::<32>("livox-rs")); // System identifier
+        header.extend_from_slice(&fixed_str::<32>(concat!("livox-rs ", env!("CARGO_PKG_VERSION"))));
+        header.extend_from_slice(&day_of_year.to_le_bytes());
+        header.extend_from_slice(&year.to_le_bytes());
+        header.extend_from_slice(&Self::HEADER_SIZE.to_le_bytes());
+        header.extend_from_slice(&(Self::HEADER_SIZE as u32).to_le_bytes()); // Offset to point data
+        header.extend_from_slice(&0u32.to_le_bytes()); // Number of VLRs
+        header.push(Self::POINT_FORMAT);
+        header.extend_from_slice(&Self::POINT_RECORD_LEN.to_le_bytes());
+        header.extend_from_slice(&[0u8; 4 + 5 * 4]); // Legacy point counts, unused for format 6
+        for _ in 0..3 { header.extend_from_slice(&Self::SCALE.to_le_bytes()); }
+        for _ in 0..3 { header.extend_from_slice(&0f64.to_le_bytes()); }
+        for (max, min) in max.iter().zip(min.iter()) {
+            header.extend_from_slice(&(*max as f64 * Self::SCALE).to_le_bytes());
+            header.extend_from_slice(&(*min as f64 * Self::SCALE).to_le_bytes());
+        }
+        header.extend_from_slice(&0u64.to_le_bytes()); // Start of waveform data
+        header.extend_from_slice(&0u64.to_le_bytes()); // Start of first EVLR
+        header.extend_from_slice(&0u32.to_le_bytes()); // Number of EVLRs
+        header.extend_from_slice(&self.count.to_le_bytes());
+        header.extend_from_slice(&self.count.to_le_bytes()); // All points are first returns
+        header.extend_from_slice(&[0u8; 14 * 8]);
+        debug_assert_eq!(header.len(), Self::HEADER_SIZE as usize);
+
+        self.writer.write_all(&header).err_reason("While writing LAS header")
+    }
+
+    pub fn into_inner(mut self) -> LivoxResult<W> {
+        self.finish()?;
+        self.writer.into_inner().map_err(|err| err.into_error()).err_reason("While flushing LAS file")
+    }
+}
+
+impl<W: Write + Seek> PointWriter for LasWriter<W> {
+    fn write_point(&mut self, p: &ExportPoint) -> LivoxResult<()> {
+        let coordinates = [p.x, p.y, p.z].map(|v| (v as f64 / Self::SCALE).round() as i32);
+        for (axis, value) in coordinates.iter().enumerate() {
+            self.min[axis] = self.min[axis].min(*value);
+            self.max[axis] = self.max[axis].max(*value);
+        }
+
+        let mut record = [0u8; 30];
+        record[0..4].copy_from_slice(&coordinates[0].to_le_bytes());
+        record[4..8].copy_from_slice(&coordinates[1].to_le_bytes());
+        record[8..12].copy_from_slice(&coordinates[2].to_le_bytes());
+        record[12..14].copy_from_slice(&(p.reflectivity as u16).to_le_bytes());
+        record[14] = 0x11; // Return 1 of 1
+        record[17] = p.tag; // User data
+        record[22..30].copy_from_slice(&p.seconds().to_le_bytes());
+        self.writer.write_all(&record).err_reason("While writing LAS point")?;
+        self.count += 1;
+        Ok(())
+    }
+
+    fn count(&self) -> u64 {
+        self.count
+    }
+
+    fn finish(&mut self) -> LivoxResult<()> {
+        let end = self.writer.stream_position().err_reason("While finishing LAS file")?;
+        self.writer.seek(SeekFrom::Start(0)).err_reason("While finishing LAS file")?;
+        self.write_header()?;
+        self.writer.seek(SeekFrom::Start(end)).err_reason("While finishing LAS file")?;
+        self.writer.flush().err_reason("While flushing LAS file")
+    }
+}
+
+/// Null padded, truncated copy of `s`.
+fn fixed_str<const N: usize>(s: &str) -> [u8; N] {
+    let mut buf = [0u8; N];
+    let len = s.len().min(N);
+    buf[..len].copy_from_slice(&s.as_bytes()[..len]);
+    buf
+}
+
+/// Current UTC `(day of year, year)`, day of year starting at 1.
+fn creation_date() -> (u16, u16) {
+    let days = SystemTime::now().duration_since(UNIX_EPOCH).unwrap_or_default().as_secs() / 86400;
+    let mut year = 1970;
+    let mut days = days as u32;
+    loop {
+        let leap = (year % 4 == 0 && year % 100 != 0) || year % 400 == 0;
+        let year_len = if leap { 366 } else { 365 };
+        if days < year_len { break; }
+        days -= year_len;
+        year += 1;
+    }
+    (days as u16 + 1, year as u16)
+}
+
+#[cfg(test)]
+mod test {
+    use std::io::Cursor;
+    use super::*;
+
+    fn points() -> Vec<ExportPoint> {
+        vec![
+            ExportPoint { x: 1.0, y: -2.0, z: 0.5, reflectivity: 10, tag: 0x10, timestamp: 1_000_000_000 },
+            ExportPoint { x: 3.25, y: 0.0, z: -1.5, reflectivity: 200, tag: 0, timestamp: 1_000_010_000 },
+        ]
+    }
+
+    #[test]
+    fn test_pcd_ascii() {
+        let mut writer = PcdWriter::new(Cursor::new(Vec::new()), PcdEncoding::Ascii).unwrap();
+        writer.write_points(&points()).unwrap();
+        let data = String::from_utf8(writer.into_inner().unwrap().into_inner()).unwrap();
+
+        let lines = data.lines().collect::<Vec<_>>();
+        assert_eq!(lines[6].split_whitespace().collect::<Vec<_>>(), ["WIDTH", "2"]);
+        assert_eq!(lines[9].split_whitespace().collect::<Vec<_>>(), ["POINTS", "2"]);
+        assert_eq!(lines[11], "1 -2 0.5 10 16 1.000000000");
+        assert_eq!(lines.len(), 13);
+    }
+
+    #[test]
+    fn test_ply_binary() {
+        let mut writer = PlyWriter::new(Cursor::new(Vec::new())).unwrap();
+        writer.write_points(&points()).unwrap();
+        let data = writer.into_inner().unwrap().into_inner();
+
+        let header_end = data.windows(11).position(|w| w == b"end_header\n").unwrap() + 11;
+        let header = std::str::from_utf8(&data[..header_end]).unwrap();
+        assert!(header.lines().any(|l| l.split_whitespace().eq(["element", "vertex", "2"])));
+        assert_eq!(data.len() - header_end, 2 * 22);
+        assert_eq!(f32::from_le_bytes(data[header_end + 22..header_end + 26].try_into().unwrap()), 3.25);
+    }
+
+    #[test]
+    fn test_ply_colour() {
+        let mut writer = PlyWriter::with_colour(Cursor::new(Vec::new())).unwrap();
+        writer.write_coloured_points(&points().into_iter().map(|point| ColourPoint { point, rgb: [255, 128, 0] }).collect::<Vec<_>>()).unwrap();
+        let data = writer.into_inner().unwrap().into_inner();
+
+        let header_end = data.windows(11).position(|w| w == b"end_header\n").unwrap() + 11;
+        let header = std::str::from_utf8(&data[..header_end]).unwrap();
+        assert!(header.lines().any(|l| l.split_whitespace().eq(["element", "vertex", "2"])));
+        assert!(header.contains("property uchar red\nproperty uchar green\nproperty uchar blue\n"));
+        assert_eq!(data.len() - header_end, 2 * 25);
+        assert_eq!(&data[header_end + 12..header_end + 15], [255, 128, 0]);
+    }
+
+    #[test]
+    fn test_las_header() {
+        let mut writer = LasWriter::new(Cursor::new(Vec::new())).unwrap();
+        writer.write_points(&points()).unwrap();
+        let data = writer.into_inner().unwrap().into_inner();
+
+        assert_eq!(&data[0..4], b"LASF");
+        assert_eq!(data.len(), 375 + 2 * 30);
+        assert_eq!(u64::from_le_bytes(data[247..255].try_into().unwrap()), 2);
+        let max_x = f64::from_le_bytes(data[179..187].try_into().unwrap());
+        let min_z = f64::from_le_bytes(data[219..227].try_into().unwrap());
+        assert!((max_x - 3.25).abs() < 1e-9);
+        assert!((min_z + 1.5).abs() < 1e-9);
+        assert_eq!(i32::from_le_bytes(data[375 + 30..375 + 34].try_into().unwrap()), 3250);
+    }
+}
@@ -0,0 +1,186 @@
+use nalgebra::{Isometry3, Matrix4, Point3, SMatrix, Translation3, UnitQuaternion, Vector3};
+use tokio_stream::{Stream, StreamExt};
+
+use crate::LivoxResult;
+use crate::export::ExportPoint;
+use crate::point::PointBatch;
+use crate::model::deku_data_type::lidar;
+
+/// Extrinsic parameters in the representation used by the device:
+/// angles in degrees, translation in millimetres.
+#[derive(Debug, Clone, Copy, PartialEq, Default)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct DeviceExtrinsics {
+    pub roll: f32,
+    pub pitch: f32,
+    pub yaw: f32,
+    pub x: i32,
+    pub y: i32,
+    pub z: i32,
+}
+
+impl From<lidar::response::ReadLiDARExtrinsicParameters> for DeviceExtrinsics {
+    fn from(ack: lidar::response::ReadLiDARExtrinsicParameters) -> Self {
+        let lidar::response::ReadLiDARExtrinsicParameters { roll, pitch, yaw, x, y, z, .. } = ack;
+        DeviceExtrinsics { roll, pitch, yaw, x, y, z }
+    }
+}
+
+impl From<DeviceExtrinsics> for lidar::request::WriteLiDARExtrinsicParameters {
+    fn from(e: DeviceExtrinsics) -> Self {
+        let DeviceExtrinsics { roll, pitch, yaw, x, y, z } = e;
+        lidar::request::WriteLiDARExtrinsicParameters { roll, pitch, yaw, x, y, z }
+    }
+}
+
+/// Rigid transform from the LiDAR frame to a target frame, in SI units.
+///
+/// The rotation follows the device convention: roll around X, pitch around Y,
+/// yaw around Z, applied in that order (`R = Rz(yaw) * Ry(pitch) * Rx(roll)`).
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Extrinsics {
+    pub isometry: Isometry3<f64>,
+}
+
+impl Default for Extrinsics {
+    fn default() -> Self {
+        Self::identity()
+    }
+}
+
+impl Extrinsics {
+    pub fn identity() -> Self {
+        Extrinsics { isometry: Isometry3::identity() }
+    }
+
+    /// Angles in radians, translation in metres.
+    pub fn from_euler(roll: f64, pitch: f64, yaw: f64, translation: Vector3<f64>) -> Self {
+        Extrinsics {
+            isometry: Isometry3::from_parts(
+                Translation3::from(translation),
+                UnitQuaternion::from_euler_angles(roll, pitch, yaw),
+            )
+        }
+    }
+
+    /// Roll, pitch and yaw in radians.
+    pub fn euler_angles(&self) -> (f64, f64, f64) {
+        self.isometry.rotation.euler_angles()
+    }
+
+    /// Translation in metres.
+    pub fn translation(&self) -> Vector3<f64> {
+        self.isometry.translation.vector
+    }
+
+    /// Homogeneous transform matrix, in metres.
+    pub fn to_homogeneous(&self) -> Matrix4<f32> {
+        self.isometry.to_homogeneous().cast()
+    }
+
+    /// Homogeneous transform matrix for coordinates in millimetres,
+    /// as yielded by [`LivoxClient::homogeneous_matrix_stream`](crate::LivoxClient::homogeneous_matrix_stream).
+    pub fn to_homogeneous_mm(&self) -> Matrix4<f32> {
+        let mut matrix = self.to_homogeneous();
+        matrix.fixed_slice_mut::<3, 1>(0, 3).scale_mut(1000.0);
+        matrix
+    }
+
+    /// Transform a point in metres.
+    pub fn transform_point(&self, point: &Point3<f32>) -> Point3<f32> {
+        self.isometry.transform_point(&point.cast()).cast()
+    }
+
+    /// Transform the coordinates of an exported point in place.
+    pub fn transform_export_point(&self, point: &mut ExportPoint) {
+        let p = self.transform_point(&Point3::new(point.x, point.y, point.z));
+        (point.x, point.y, point.z) = (p.x, p.y, p.z);
+    }
+
+    /// Transform the points of a batch in place, coordinates being in metres.
+    /// No-return points are left at `(0, 0, 0)`, so they can still be told apart.
+    pub fn transform_batch(&self, batch: &mut PointBatch) {
+        let isometry = self.isometry.cast::<f32>();
+        for i in 0..batch.len() {
+            if (batch.x[i], batch.y[i], batch.z[i]) == (0.0, 0.0, 0.0) { continue; }
+            let p = isometry.transform_point(&Point3::new(batch.x[i], batch.y[i], batch.z[i]));
+            (batch.x[i], batch.y[i], batch.z[i]) = (p.x, p.y, p.z);
+        }
+    }
+
+    /// Transform a homogeneous matrix with coordinates in millimetres.
+    pub fn transform_matrix_mm<const C: usize>(&self, matrix: &SMatrix<f32, 4, C>) -> SMatrix<f32, 4, C> {
+        self.to_homogeneous_mm() * matrix
+    }
+
+    /// Apply the transform to every matrix of a stream,
+    /// like the one from [`LivoxClient::homogeneous_matrix_stream`](crate::LivoxClient::homogeneous_matrix_stream).
+    pub fn apply<const C: usize>(&self, stream: impl Stream<Item=LivoxResult<SMatrix<f32, 4, C>>>)
+                                 -> impl Stream<Item=LivoxResult<SMatrix<f32, 4, C>>> {
+        let transform = self.to_homogeneous_mm();
+        stream.map(move |matrix| matrix.map(|m| transform * m))
+    }
+}
+
+impl From<DeviceExtrinsics> for Extrinsics {
+    fn from(e: DeviceExtrinsics) -> Self {
+        Extrinsics::from_euler(
+            (e.roll as f64).to_radians(),
+            (e.pitch as f64).to_radians(),
+            (e.yaw as f64).to_radians(),
+            Vector3::new(e.x as f64, e.y as f64, e.z as f64) / 1000.0,
+        )
+    }
+}
+
+impl From<Extrinsics> for DeviceExtrinsics {
+    /// Translation is rounded to whole millimetres.
+    fn from(e: Extrinsics) -> Self {
+        let (roll, pitch, yaw) = e.euler_angles();
+        let t = e.translation() * 1000.0;
+        DeviceExtrinsics {
+            roll: roll.to_degrees() as f32,
+            pitch: pitch.to_degrees() as f32,
+            yaw: yaw.to_degrees() as f32,
+            x: t.x.round() as i32,
+            y: t.y.round() as i32,
+            z: t.z.round() as i32,
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn test_device_round_trip() {
+        let device = DeviceExtrinsics { roll: 1.5, pitch: -20.0, yaw: 90.0, x: 100, y: -250, z: 42 };
+        let extrinsics = Extrinsics::from(device);
+        assert!((extrinsics.translation() - Vector3::new(0.1, -0.25, 0.042)).norm() < 1e-9);
+
+        let back = DeviceExtrinsics::from(extrinsics);
+        assert!((back.roll - device.roll).abs() < 1e-4);
+        assert!((back.pitch - device.pitch).abs() < 1e-4);
+        assert!((back.yaw - device.yaw).abs() < 1e-4);
+        assert_eq!((back.x, back.y, back.z), (device.x, device.y, device.z));
+    }
+
+    #[test]
+    fn test_transform() {
+        // yaw 90°: X axis turns into Y axis
+        let extrinsics = Extrinsics::from(DeviceExtrinsics { yaw: 90.0, x: 1000, ..Default::default() });
+        let p = extrinsics.transform_point(&Point3::new(1.0, 0.0, 0.0));
+        assert!((p - Point3::new(1.0, 1.0, 0.0)).norm() < 1e-6);
+
+        let m = SMatrix::<f32, 4, 1>::new(1000.0, 0.0, 0.0, 1.0);
+        let t = extrinsics.transform_matrix_mm(&m);
+        assert!((t - SMatrix::<f32, 4, 1>::new(1000.0, 1000.0, 0.0, 1.0)).norm() < 1e-3);
+
+        let mut batch = PointBatch { x: vec![1.0, 0.0], y: vec![0.0, 0.0], z: vec![0.0, 0.0], ..PointBatch::default() };
+        extrinsics.transform_batch(&mut batch);
+        assert!((batch.x[0] - 1.0).abs() < 1e-6 && (batch.y[0] - 1.0).abs() < 1e-6);
+        // a no-return point does not land at the mount position
+        assert_eq!((batch.x[1], batch.y[1], batch.z[1]), (0.0, 0.0, 0.0));
+    }
+}
@@ -0,0 +1,161 @@
+use tokio_stream::{Stream, StreamExt};
+
+use crate::LivoxResult;
+use crate::model::data_type::LivoxPoint;
+use crate::point::PointBatch;
+
+pub mod crop;
+pub mod voxel;
+pub mod outlier;
+
+/// Confidence level of a noise flag in [`TagInfo`](crate::model::data_type::TagInfo).
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+#[cfg_attr(feature = "serde", serde(rename_all = "snake_case"))]
+pub enum NoiseConfidence {
+    Low,
+    Medium,
+    High,
+}
+
+impl NoiseConfidence {
+    /// Confidence of a 2-bit tag field: `01` high, `10` medium, `11` low, `00` not noise.
+    pub fn from_tag_bits(bits: u8) -> Option<Self> {
+        match bits & 0b11 {
+            0b01 => Some(NoiseConfidence::High),
+            0b10 => Some(NoiseConfidence::Medium),
+            0b11 => Some(NoiseConfidence::Low),
+            _ => None,
+        }
+    }
+}
+
+/// Drops invalid and noisy points.
+///
+/// Noise rejection levels drop points flagged as noise with at least the given confidence,
+/// e.g. `Medium` drops points flagged with high or medium confidence.
+#[derive(Debug, Clone, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+#[cfg_attr(feature = "serde", serde(default, deny_unknown_fields))]
+pub struct PointFilter {
+    /// Drop no-return points, sent as `(0, 0, 0)`.
+    pub drop_zero: bool,
+    /// Reject points flagged by the spatial position based noise detection.
+    pub space_noise: Option<NoiseConfidence>,
+    /// Reject points flagged by the intensity based noise detection (rain, fog, dust).
+    pub strength_noise: Option<NoiseConfidence>,
+    /// Drop points with a non-zero near-field distortion tag.
+    pub drop_near_distortion: bool,
+    /// Range limits in metres.
+    pub min_range: Option<f32>,
+    pub max_range: Option<f32>,
+    pub min_reflectivity: Option<u8>,
+    pub max_reflectivity: Option<u8>,
+}
+
+impl Default for PointFilter {
+    /// Drop zero points and high confidence noise.
+    fn default() -> Self {
+        PointFilter {
+            drop_zero: true,
+            space_noise: Some(NoiseConfidence::High),
+            strength_noise: Some(NoiseConfidence::High),
+            drop_near_distortion: false,
+            min_range: None,
+            max_range: None,
+            min_reflectivity: None,
+            max_reflectivity: None,
+        }
+    }
+}
+
+impl PointFilter {
+    /// A filter keeping every point.
+    pub fn pass_all() -> Self {
+        PointFilter {
+            drop_zero: false,
+            space_noise: None,
+            strength_noise: None,
+            ..Self::default()
+        }
+    }
+
+    pub fn accepts(&self, point: &LivoxPoint) -> bool {
+        if self.drop_zero && point.is_zero() { return false; }
+
+        let tag = point.tag_info();
+        let noisy = |bits: u8, reject: Option<NoiseConfidence>| match (NoiseConfidence::from_tag_bits(bits), reject) {
+            (Some(confidence), Some(reject)) => confidence >= reject,
+            _ => false,
+        };
+        if noisy(tag.space, self.space_noise) || noisy(tag.strength, self.strength_noise) {
+            return false;
+        }
+        if self.drop_near_distortion && tag.near_distortion != 0 {
+            return false;
+        }
+
+        if self.min_range.is_some() || self.max_range.is_some() {
+            let range = point.to_point().coords.norm();
+            if self.min_range.is_some_and(|min| range < min) || self.max_range.is_some_and(|max| range > max) {
+                return false;
+            }
+        }
+        !(self.min_reflectivity.is_some_and(|min| point.reflectivity < min)
+            || self.max_reflectivity.is_some_and(|max| point.reflectivity > max))
+    }
+
+    /// Remove rejected points from `batch`.
+    pub fn apply(&self, batch: &mut PointBatch) {
+        batch.retain(|p| self.accepts(&p.point));
+    }
+
+    /// Apply the filter to every batch of a stream,
+    /// like the one from [`LivoxClient::point_stream`](crate::LivoxClient::point_stream).
+    pub fn apply_stream(self, stream: impl Stream<Item=LivoxResult<PointBatch>>) -> impl Stream<Item=LivoxResult<PointBatch>> {
+        stream.map(move |batch| batch.map(|mut batch| {
+            self.apply(&mut batch);
+            batch
+        }))
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    fn point(x: f32, reflectivity: u8, tag: u8) -> LivoxPoint {
+        LivoxPoint { x, y: 0.0, z: 0.0, reflectivity, tag }
+    }
+
+    #[test]
+    fn test_filter() {
+        let filter = PointFilter::default();
+        assert!(!filter.accepts(&point(0.0, 10, 0)));
+        assert!(filter.accepts(&point(1.0, 10, 0)));
+        // high confidence spatial noise
+        assert!(!filter.accepts(&point(1.0, 10, 0b0000_0001)));
+        // medium confidence intensity noise is kept unless asked for
+        assert!(filter.accepts(&point(1.0, 10, 0b0000_1000)));
+        let filter = PointFilter { strength_noise: Some(NoiseConfidence::Medium), ..filter };
+        assert!(!filter.accepts(&point(1.0, 10, 0b0000_1000)));
+        assert!(filter.accepts(&point(1.0, 10, 0b0000_1100)));
+
+        let filter = PointFilter {
+            min_range: Some(0.5),
+            max_range: Some(100.0),
+            min_reflectivity: Some(5),
+            ..PointFilter::pass_all()
+        };
+        // kept zero points still go through the range and reflectivity checks
+        assert!(!filter.accepts(&point(0.0, 10, 0)));
+        assert!(!filter.accepts(&point(0.3, 10, 0)));
+        assert!(!filter.accepts(&point(150.0, 10, 0)));
+        assert!(!filter.accepts(&point(10.0, 4, 0)));
+        assert!(filter.accepts(&point(10.0, 5, 0b0101_0101)));
+        let filter = PointFilter { min_range: None, ..filter };
+        assert!(filter.accepts(&point(0.0, 10, 0)));
+        assert!(!filter.accepts(&point(0.0, 4, 0)));
+        assert!(PointFilter::pass_all().accepts(&point(0.0, 0, 0)));
+    }
+}
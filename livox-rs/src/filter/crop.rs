@@ -0,0 +1,215 @@
+use nalgebra::{Isometry3, Matrix3x4, Point2, Point3, Vector3};
+use tokio_stream::{Stream, StreamExt};
+
+use crate::LivoxResult;
+use crate::point::PointBatch;
+
+/// A region of space, in metres, in the frame of the points it is applied to.
+#[derive(Debug, Clone, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+#[cfg_attr(feature = "serde", serde(rename_all = "snake_case"))]
+pub enum CropRegion {
+    /// Axis-aligned box.
+    Box { min: Point3<f32>, max: Point3<f32> },
+    /// Box of size `2 * half_extents` centred on the origin of `pose`, along its axes.
+    OrientedBox { pose: Isometry3<f32>, half_extents: Vector3<f32> },
+    /// Cylinder from `base` to `base + axis`.
+    Cylinder { base: Point3<f32>, axis: Vector3<f32>, radius: f32 },
+    /// What a camera sees, `camera` projecting homogeneous points to homogeneous pixels
+    /// with the depth as third coordinate, e.g. `K * [R | t]`.
+    Frustum { camera: Matrix3x4<f32>, width: u32, height: u32, near: f32, far: f32 },
+    /// Polygon in the XY plane, extruded from `min_z` to `max_z`.
+    Polygon { vertices: Vec<Point2<f32>>, min_z: f32, max_z: f32 },
+    /// Inside any of the regions.
+    Union(Vec<CropRegion>),
+    /// Inside all of the regions.
+    Intersection(Vec<CropRegion>),
+}
+
+impl CropRegion {
+    pub fn contains(&self, p: &Point3<f32>) -> bool {
+        match self {
+            CropRegion::Box { min, max } => (0..3).all(|i| min[i] <= p[i] && p[i] <= max[i]),
+            CropRegion::OrientedBox { pose, half_extents } => {
+                let local = pose.inverse_transform_point(p);
+                (0..3).all(|i| local[i].abs() <= half_extents[i])
+            }
+            CropRegion::Cylinder { base, axis, radius } => {
+                let length_sq = axis.norm_squared();
+                if length_sq == 0.0 { return false; }
+                let v = p - base;
+                let t = v.dot(axis) / length_sq;
+                (0.0..=1.0).contains(&t) && (v - axis * t).norm_squared() <= radius * radius
+            }
+            CropRegion::Frustum { camera, width, height, near, far } => {
+                let pixel = camera * p.to_homogeneous();
+                let depth = pixel.z;
+                if depth < *near || depth > *far || depth <= 0.0 { return false; }
+                let (u, v) = (pixel.x / depth, pixel.y / depth);
+                (0.0..*width as f32).contains(&u) && (0.0..*height as f32).contains(&v)
+            }
+            CropRegion::Polygon { vertices, min_z, max_z } =>
+                (*min_z..=*max_z).contains(&p.z) && polygon_contains(vertices, p.x, p.y),
+            CropRegion::Union(regions) => regions.iter().any(|r| r.contains(p)),
+            CropRegion::Intersection(regions) => regions.iter().all(|r| r.contains(p)),
+        }
+    }
+}
+
+/// Even-odd rule, by casting a ray towards +x.
+fn polygon_contains(vertices: &[Point2<f32>], x: f32, y: f32) -> bool {
+    let mut inside = false;
+    let mut j = vertices.len().wrapping_sub(1);
+    for (i, a) in vertices.iter().enumerate() {
+        let b = &vertices[j];
+        if (a.y > y) != (b.y > y) && x < (b.x - a.x) * (y - a.y) / (b.y - a.y) + a.x {
+            inside = !inside;
+        }
+        j = i;
+    }
+    inside
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+#[cfg_attr(feature = "serde", serde(rename_all = "snake_case"))]
+pub enum CropMode {
+    KeepInside,
+    RemoveInside,
+}
+
+/// Keep or remove the points inside a region, e.g. to keep a region of interest
+/// or to mask out the robot's own body.
+#[derive(Debug, Clone, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct Crop {
+    pub region: CropRegion,
+    pub mode: CropMode,
+}
+
+impl Crop {
+    pub fn keep_inside(region: CropRegion) -> Self {
+        Crop { region, mode: CropMode::KeepInside }
+    }
+
+    pub fn remove_inside(region: CropRegion) -> Self {
+        Crop { region, mode: CropMode::RemoveInside }
+    }
+
+    pub fn accepts(&self, p: &Point3<f32>) -> bool {
+        self.region.contains(p) == (self.mode == CropMode::KeepInside)
+    }
+
+    /// Remove rejected points from `batch`.
+    pub fn apply(&self, batch: &mut PointBatch) {
+        batch.retain(|p| self.accepts(&p.point.to_point()));
+    }
+}
+
+/// Crops applied one after the other, a point is kept if every crop accepts it.
+#[derive(Debug, Clone, PartialEq, Default)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+#[cfg_attr(feature = "serde", serde(transparent))]
+pub struct CropFilter {
+    pub crops: Vec<Crop>,
+}
+
+impl CropFilter {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn keep_inside(mut self, region: CropRegion) -> Self {
+        self.crops.push(Crop::keep_inside(region));
+        self
+    }
+
+    pub fn remove_inside(mut self, region: CropRegion) -> Self {
+        self.crops.push(Crop::remove_inside(region));
+        self
+    }
+
+    pub fn accepts(&self, p: &Point3<f32>) -> bool {
+        self.crops.iter().all(|crop| crop.accepts(p))
+    }
+
+    /// Remove rejected points from `batch`.
+    pub fn apply(&self, batch: &mut PointBatch) {
+        if self.crops.is_empty() { return; }
+        batch.retain(|p| self.accepts(&p.point.to_point()));
+    }
+
+    /// Apply the crops to every batch of a stream.
+    pub fn apply_stream(self, stream: impl Stream<Item=LivoxResult<PointBatch>>) -> impl Stream<Item=LivoxResult<PointBatch>> {
+        stream.map(move |batch| batch.map(|mut batch| {
+            self.apply(&mut batch);
+            batch
+        }))
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use nalgebra::{Matrix3, UnitQuaternion};
+    use super::*;
+
+    #[test]
+    fn test_regions() {
+        let p = Point3::new;
+
+        let aabb = CropRegion::Box { min: p(0.0, -1.0, -1.0), max: p(10.0, 1.0, 1.0) };
+        assert!(aabb.contains(&p(5.0, 0.5, 0.0)));
+        assert!(!aabb.contains(&p(5.0, 1.5, 0.0)));
+
+        // 2m x 1m x 1m box centred at (5, 0, 0), turned 90 degrees around z
+        let obb = CropRegion::OrientedBox {
+            pose: Isometry3::from_parts(Vector3::new(5.0, 0.0, 0.0).into(),
+                                        UnitQuaternion::from_euler_angles(0.0, 0.0, std::f32::consts::FRAC_PI_2)),
+            half_extents: Vector3::new(1.0, 0.5, 0.5),
+        };
+        assert!(obb.contains(&p(5.0, 0.9, 0.0)));
+        assert!(!obb.contains(&p(5.9, 0.0, 0.0)));
+
+        let cylinder = CropRegion::Cylinder { base: p(0.0, 0.0, -1.0), axis: Vector3::new(0.0, 0.0, 2.0), radius: 0.5 };
+        assert!(cylinder.contains(&p(0.3, 0.3, 0.9)));
+        assert!(!cylinder.contains(&p(0.4, 0.4, 0.0)));
+        assert!(!cylinder.contains(&p(0.0, 0.0, 1.1)));
+
+        // camera looking along +x of the LiDAR
+        let k = Matrix3::new(500.0, 0.0, 320.0, 0.0, 500.0, 240.0, 0.0, 0.0, 1.0);
+        let rt = Matrix3x4::new(0.0, -1.0, 0.0, 0.0,
+                                0.0, 0.0, -1.0, 0.0,
+                                1.0, 0.0, 0.0, 0.0);
+        let frustum = CropRegion::Frustum { camera: k * rt, width: 640, height: 480, near: 0.1, far: 50.0 };
+        assert!(frustum.contains(&p(10.0, 0.0, 0.0)));
+        assert!(!frustum.contains(&p(-10.0, 0.0, 0.0)));
+        assert!(!frustum.contains(&p(10.0, 8.0, 0.0)));
+        assert!(!frustum.contains(&p(60.0, 0.0, 0.0)));
+
+        // L-shaped zone
+        let polygon = CropRegion::Polygon {
+            vertices: vec![Point2::new(0.0, 0.0), Point2::new(2.0, 0.0), Point2::new(2.0, 1.0),
+                           Point2::new(1.0, 1.0), Point2::new(1.0, 2.0), Point2::new(0.0, 2.0)],
+            min_z: 0.0,
+            max_z: 1.0,
+        };
+        assert!(polygon.contains(&p(0.5, 1.5, 0.5)));
+        assert!(!polygon.contains(&p(1.5, 1.5, 0.5)));
+        assert!(!polygon.contains(&p(0.5, 1.5, 1.5)));
+
+        let union = CropRegion::Union(vec![aabb.clone(), cylinder.clone()]);
+        assert!(union.contains(&p(0.0, 0.0, 0.9)) && union.contains(&p(9.0, 0.0, 0.0)));
+        assert!(!CropRegion::Intersection(vec![aabb, cylinder]).contains(&p(9.0, 0.0, 0.0)));
+    }
+
+    #[test]
+    fn test_crop_filter() {
+        let filter = CropFilter::new()
+            .keep_inside(CropRegion::Box { min: Point3::new(-20.0, -2.0, -1.0), max: Point3::new(20.0, 2.0, 3.0) })
+            .remove_inside(CropRegion::Box { min: Point3::new(-0.5, -0.5, -1.0), max: Point3::new(0.5, 0.5, 1.0) });
+        assert!(filter.accepts(&Point3::new(5.0, 0.0, 0.0)));
+        assert!(!filter.accepts(&Point3::new(0.2, 0.0, 0.0)));
+        assert!(!filter.accepts(&Point3::new(5.0, 3.0, 0.0)));
+        assert!(CropFilter::new().accepts(&Point3::new(100.0, 0.0, 0.0)));
+    }
+}
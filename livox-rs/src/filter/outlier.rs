@@ -0,0 +1,95 @@
+use crate::cloud::kdtree::KdTree;
+use crate::cloud::PointCloud;
+
+/// Removes points whose mean distance to their `k` nearest neighbours is more than
+/// `std_ratio` standard deviations above the mean over the whole cloud.
+#[derive(Debug, Clone, Copy, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct StatisticalOutlierRemoval {
+    pub k: usize,
+    pub std_ratio: f32,
+}
+
+impl Default for StatisticalOutlierRemoval {
+    fn default() -> Self {
+        StatisticalOutlierRemoval { k: 16, std_ratio: 1.0 }
+    }
+}
+
+impl StatisticalOutlierRemoval {
+    /// Does nothing if `k` is `0`, or the cloud has no more than `k` points.
+    pub fn apply(&self, cloud: &mut PointCloud) {
+        if self.k == 0 || cloud.len() <= self.k { return; }
+        let tree = KdTree::new(cloud.positions());
+        let mean_distances = (0..tree.len()).map(|i| {
+            // the point itself comes first
+            let neighbours = tree.nearest(tree.point(i), self.k + 1);
+            neighbours[1..].iter().map(|(_, d)| d.sqrt() as f64).sum::<f64>() / self.k as f64
+        }).collect::<Vec<_>>();
+
+        let n = mean_distances.len() as f64;
+        let mean = mean_distances.iter().sum::<f64>() / n;
+        let std = (mean_distances.iter().map(|d| (d - mean).powi(2)).sum::<f64>() / (n - 1.0)).sqrt();
+        let threshold = mean + self.std_ratio as f64 * std;
+
+        let mut keep = mean_distances.into_iter().map(|d| d <= threshold);
+        cloud.retain(|_| keep.next().unwrap());
+    }
+}
+
+/// Removes points with fewer than `min_neighbors` other points within `radius` metres.
+#[derive(Debug, Clone, Copy, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct RadiusOutlierRemoval {
+    pub radius: f32,
+    pub min_neighbors: usize,
+}
+
+impl RadiusOutlierRemoval {
+    pub fn apply(&self, cloud: &mut PointCloud) {
+        let tree = KdTree::new(cloud.positions());
+        let mut keep = (0..tree.len())
+            .map(|i| tree.count_within(tree.point(i), self.radius, self.min_neighbors + 1) > self.min_neighbors)
+            .collect::<Vec<_>>()
+            .into_iter();
+        cloud.retain(|_| keep.next().unwrap());
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use crate::model::data_type::LivoxPoint;
+    use crate::point::BatchPoint;
+    use super::*;
+
+    /// A 20 x 20 grid of points 5cm apart on a wall, and two strays.
+    fn wall_with_strays() -> PointCloud {
+        let point = |x: f32, y: f32, z: f32| BatchPoint {
+            point: LivoxPoint { x, y, z, reflectivity: 0, tag: 0 },
+            ..BatchPoint::default()
+        };
+        let mut cloud = (0..400).map(|i| point(5.0, (i % 20) as f32 * 0.05, (i / 20) as f32 * 0.05)).collect::<PointCloud>();
+        cloud.points.push(point(3.0, 0.5, 0.5));
+        cloud.points.push(point(5.0, 2.0, 2.0));
+        cloud
+    }
+
+    #[test]
+    fn test_statistical() {
+        let mut cloud = wall_with_strays();
+        StatisticalOutlierRemoval { k: 8, std_ratio: 2.0 }.apply(&mut cloud);
+        assert_eq!(cloud.len(), 400);
+        assert!(cloud.points.iter().all(|p| p.point.x == 5.0 && p.point.y < 1.0));
+
+        let mut cloud = wall_with_strays();
+        StatisticalOutlierRemoval { k: 0, std_ratio: 2.0 }.apply(&mut cloud);
+        assert_eq!(cloud.len(), 402);
+    }
+
+    #[test]
+    fn test_radius() {
+        let mut cloud = wall_with_strays();
+        RadiusOutlierRemoval { radius: 0.06, min_neighbors: 2 }.apply(&mut cloud);
+        assert_eq!(cloud.len(), 400);
+    }
+}
@@ -0,0 +1,143 @@
+use std::collections::HashMap;
+use std::collections::hash_map::Entry;
+use tracing::warn;
+
+use crate::cloud::PointCloud;
+use crate::point::BatchPoint;
+
+/// Which point stands for a voxel.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+#[cfg_attr(feature = "serde", serde(rename_all = "snake_case"))]
+pub enum VoxelPolicy {
+    /// Centroid of the points in the voxel.
+    #[default]
+    Centroid,
+    /// Position of the first point falling in the voxel, cheaper and keeps points on real surfaces.
+    FirstPoint,
+}
+
+/// Downsamples a cloud to one point per cubic voxel.
+///
+/// Whatever the policy, the reflectivity of the output is the average of the voxel
+/// and tag, timestamp and return index are those of its first point.
+#[derive(Debug, Clone, Copy, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct VoxelGrid {
+    /// Voxel edge length in metres.
+    pub leaf_size: f32,
+    #[cfg_attr(feature = "serde", serde(default))]
+    pub policy: VoxelPolicy,
+}
+
+struct Voxel {
+    first: BatchPoint,
+    sum: [f64; 3],
+    reflectivity: u32,
+    count: u32,
+}
+
+impl VoxelGrid {
+    /// `None` unless `leaf_size` is a positive finite number.
+    pub fn new(leaf_size: f32) -> Option<Self> {
+        let grid = VoxelGrid { leaf_size, policy: VoxelPolicy::default() };
+        grid.is_valid().then_some(grid)
+    }
+
+    /// Whether [`VoxelGrid::leaf_size`] is a positive finite number.
+    pub fn is_valid(&self) -> bool {
+        self.leaf_size > 0.0 && self.leaf_size.is_finite()
+    }
+
+    pub fn with_policy(mut self, policy: VoxelPolicy) -> Self {
+        self.policy = policy;
+        self
+    }
+
+    /// Voxels in order of their first point.
+    /// The cloud is returned as it is if the grid is not [valid](VoxelGrid::is_valid),
+    /// rather than collapsing into a few saturated voxels.
+    pub fn downsample(&self, cloud: &PointCloud) -> PointCloud {
+        if !self.is_valid() {
+            warn!("Invalid voxel leaf size {}, not downsampling", self.leaf_size);
+            return cloud.clone();
+        }
+        let scale = 1.0 / self.leaf_size;
+        let mut index = HashMap::<(i32, i32, i32), usize>::with_capacity(cloud.len() / 4);
+        let mut voxels = Vec::<Voxel>::new();
+
+        for p in &cloud.points {
+            let key = (
+                (p.point.x * scale).floor() as i32,
+                (p.point.y * scale).floor() as i32,
+                (p.point.z * scale).floor() as i32,
+            );
+            let voxel = match index.entry(key) {
+                Entry::Occupied(entry) => &mut voxels[*entry.get()],
+                Entry::Vacant(entry) => {
+                    entry.insert(voxels.len());
+                    voxels.push(Voxel { first: *p, sum: [0.0; 3], reflectivity: 0, count: 0 });
+                    voxels.last_mut().unwrap()
+                }
+            };
+            voxel.sum[0] += p.point.x as f64;
+            voxel.sum[1] += p.point.y as f64;
+            voxel.sum[2] += p.point.z as f64;
+            voxel.reflectivity += p.point.reflectivity as u32;
+            voxel.count += 1;
+        }
+
+        voxels.into_iter().map(|voxel| {
+            let mut p = voxel.first;
+            let n = voxel.count;
+            if self.policy == VoxelPolicy::Centroid {
+                p.point.x = (voxel.sum[0] / n as f64) as f32;
+                p.point.y = (voxel.sum[1] / n as f64) as f32;
+                p.point.z = (voxel.sum[2] / n as f64) as f32;
+            }
+            p.point.reflectivity = ((voxel.reflectivity + n / 2) / n) as u8;
+            p
+        }).collect()
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use crate::model::data_type::LivoxPoint;
+    use super::*;
+
+    #[test]
+    fn test_downsample() {
+        let point = |x: f32, y: f32, reflectivity: u8, timestamp: u64| BatchPoint {
+            point: LivoxPoint { x, y, z: 1.0, reflectivity, tag: 0 },
+            timestamp,
+            return_index: 0,
+        };
+        let cloud = PointCloud::from_iter([
+            point(0.01, 0.01, 10, 0),
+            point(5.0, 5.0, 7, 1),
+            point(0.07, 0.03, 21, 2),
+            point(-0.01, 0.01, 3, 3),
+        ]);
+
+        let centroid = VoxelGrid::new(0.1).unwrap().downsample(&cloud);
+        assert_eq!(centroid.len(), 3);
+        let p = centroid.points[0];
+        assert!((p.point.x - 0.04).abs() < 1e-6 && (p.point.y - 0.02).abs() < 1e-6);
+        assert_eq!((p.point.reflectivity, p.timestamp), (16, 0));
+        assert_eq!(centroid.points[2].point.x, -0.01);
+
+        let first = VoxelGrid::new(0.1).unwrap().with_policy(VoxelPolicy::FirstPoint).downsample(&cloud);
+        assert_eq!((first.points[0].point.x, first.points[0].point.reflectivity), (0.01, 16));
+    }
+
+    #[test]
+    fn test_invalid_leaf_size() {
+        assert_eq!(VoxelGrid::new(0.0), None);
+        assert_eq!(VoxelGrid::new(-0.1), None);
+        assert_eq!(VoxelGrid::new(f32::NAN), None);
+        let cloud = PointCloud::from_iter([BatchPoint::default(), BatchPoint::default()]);
+        let grid = VoxelGrid { leaf_size: -0.1, policy: VoxelPolicy::Centroid };
+        assert_eq!(grid.downsample(&cloud), cloud);
+    }
+}
@@ -0,0 +1,324 @@
+use std::collections::BTreeMap;
+use std::pin::Pin;
+use std::sync::Arc;
+use std::time::Duration;
+use async_stream::stream;
+use tokio_stream::{Stream, StreamExt, StreamMap};
+use tracing::{debug, info};
+
+use crate::{HandshakeOption, Livox, LivoxClient, LivoxError, LivoxResult};
+use crate::cloud::PointCloud;
+use crate::extrinsics::Extrinsics;
+use crate::point::PointBatch;
+
+/// A LiDAR of a [`MultiLivox`].
+#[derive(Debug, Clone, PartialEq)]
+pub struct SensorConfig {
+    /// As in [`Livox::broadcast_code_str`].
+    pub broadcast_code: String,
+    /// From the LiDAR frame to the vehicle frame, `None` for the extrinsics stored on the device.
+    pub extrinsics: Option<Extrinsics>,
+}
+
+/// A batch of one of the LiDARs of a [`MultiLivox`], in the vehicle frame.
+#[derive(Debug, Clone, PartialEq)]
+pub struct TaggedBatch {
+    /// Broadcast code of the LiDAR.
+    pub sensor: Arc<str>,
+    pub batch: PointBatch,
+}
+
+/// Points of every LiDAR over a period, in the vehicle frame.
+#[derive(Debug, Clone, PartialEq, Default)]
+pub struct FusedFrame {
+    /// Start of the period, in nanoseconds of [`PacketInfo::time`](crate::point::PacketInfo::time).
+    pub timestamp: u64,
+    pub cloud: PointCloud,
+    /// Broadcast codes of the LiDARs.
+    pub sensors: Vec<Arc<str>>,
+    /// Index in `sensors` of the LiDAR of every point of `cloud`.
+    pub sources: Vec<usize>,
+    /// Number of batches of every LiDAR, `0` for one missing from the frame.
+    pub batches: Vec<usize>,
+}
+
+/// Groups the batches of several LiDARs into [`FusedFrame`]s of a fixed period by
+/// [`PacketInfo::time`](crate::point::PacketInfo::time), so synchronised LiDARs are aligned on
+/// their shared clock and the others on host time; mixing both needs that clock to follow the host's.
+///
+/// A frame is complete once every LiDAR sent a batch past its end. So that a LiDAR dropping out
+/// does not stall the others, it is also complete once any LiDAR is `max_delay` past its end.
+/// Batches arriving after their frame was completed, or of other LiDARs, are dropped.
+#[derive(Debug, Clone)]
+pub struct FrameAligner {
+    period: u64,
+    max_delay: u64,
+    sensors: Vec<Arc<str>>,
+    /// Latest batch time of every LiDAR.
+    latest: Vec<Option<u64>>,
+    /// Frames by index of their period.
+    pending: BTreeMap<u64, FusedFrame>,
+    /// Frames before this one are completed.
+    next: u64,
+    late: u64,
+}
+
+impl FrameAligner {
+    /// `sensors` are the broadcast codes of the LiDARs.
+    pub fn new(sensors: Vec<Arc<str>>, period: Duration, max_delay: Duration) -> Self {
+        FrameAligner {
+            period: (period.as_nanos() as u64).max(1),
+            max_delay: max_delay.as_nanos() as u64,
+            latest: vec![None; sensors.len()],
+            sensors,
+            pending: BTreeMap::new(),
+            next: 0,
+            late: 0,
+        }
+    }
+
+    /// Add a batch, returning the frames it completes, oldest first.
+    pub fn push(&mut self, tagged: TaggedBatch) -> Vec<FusedFrame> {
+        let Some(sensor) = self.sensors.iter().position(|code| *code == tagged.sensor) else {
+            debug!("Dropping a batch of unknown LiDAR {}", tagged.sensor);
+            return Vec::new();
+        };
+        let time = tagged.batch.packet.time();
+        let index = time / self.period;
+        if index < self.next {
+            self.late += 1;
+            debug!("Dropping a batch of LiDAR {} {}ms late", tagged.sensor,
+                (self.next * self.period).saturating_sub(time) / 1_000_000);
+            return Vec::new();
+        }
+        let latest = &mut self.latest[sensor];
+        *latest = Some(latest.map_or(time, |latest| latest.max(time)));
+
+        let frame = self.pending.entry(index).or_insert_with(|| FusedFrame {
+            timestamp: index * self.period,
+            sensors: self.sensors.clone(),
+            batches: vec![0; self.sensors.len()],
+            ..FusedFrame::default()
+        });
+        frame.cloud.push_batch(&tagged.batch);
+        frame.sources.resize(frame.cloud.len(), sensor);
+        frame.batches[sensor] += 1;
+
+        let mut completed = Vec::new();
+        while let Some(entry) = self.pending.first_entry() {
+            let end = (*entry.key() + 1) * self.period;
+            let all_past = self.latest.iter().all(|latest| latest.is_some_and(|latest| latest >= end));
+            let any_late = self.latest.iter().flatten().any(|latest| *latest >= end + self.max_delay);
+            if !all_past && !any_late { break; }
+            self.next = *entry.key() + 1;
+            completed.push(entry.remove());
+        }
+        completed
+    }
+
+    /// Complete every pending frame, e.g. when the LiDARs stopped.
+    pub fn flush(&mut self) -> Vec<FusedFrame> {
+        if let Some(last) = self.pending.keys().next_back() {
+            self.next = last + 1;
+        }
+        std::mem::take(&mut self.pending).into_values().collect()
+    }
+
+    /// Number of batches dropped for arriving after their frame was completed.
+    pub fn late(&self) -> u64 {
+        self.late
+    }
+}
+
+/// Several LiDARs, e.g. with overlapping fields of view, whose points are merged in a common vehicle frame.
+pub struct MultiLivox {
+    sensors: Vec<Arc<str>>,
+    clients: Vec<LivoxClient>,
+    extrinsics: Vec<Extrinsics>,
+}
+
+impl MultiLivox {
+    /// Wait for the broadcast of every LiDAR and handshake with it.
+    /// Fixed ports of `option` are offset by the index of the LiDAR, so that clients do not share them.
+    /// Fails with [`LivoxError::InvalidSensorConfig`] if a broadcast code is listed twice or an offset port overflows.
+    pub async fn connect(sensors: &[SensorConfig], option: HandshakeOption) -> LivoxResult<Self> {
+        if sensors.iter().enumerate().any(|(i, sensor)| sensors[..i].iter().any(|other| other.broadcast_code == sensor.broadcast_code)) {
+            return Err(LivoxError::InvalidSensorConfig("a broadcast code is listed twice"));
+        }
+        let last = sensors.len().saturating_sub(1);
+        let offset = |port: u16, i: usize| match port {
+            0 => Some(0),
+            port => u16::try_from(i).ok().and_then(|i| port.checked_add(i)),
+        };
+        if [option.cmd_port, option.data_port, option.imu_port].into_iter().any(|port| offset(port, last).is_none()) {
+            return Err(LivoxError::InvalidSensorConfig("a port offset by the index of the LiDAR is past 65535"));
+        }
+
+        let mut connected: Vec<Option<(LivoxClient, Extrinsics)>> = sensors.iter().map(|_| None).collect();
+        while connected.iter().any(Option::is_none) {
+            let livox = Livox::wait_for(|livox| {
+                let code = livox.broadcast_code_str();
+                sensors.iter().zip(&connected).any(|(sensor, client)| client.is_none() && sensor.broadcast_code == code)
+            }).await?;
+            let code = livox.broadcast_code_str();
+            let i = sensors.iter().position(|sensor| sensor.broadcast_code == code).unwrap();
+
+            // checked above
+            let offset = |port: u16| offset(port, i).unwrap();
+            let option = HandshakeOption {
+                cmd_port: offset(option.cmd_port),
+                data_port: offset(option.data_port),
+                imu_port: offset(option.imu_port),
+                ..option.clone()
+            };
+            let client = livox.handshake(option).await?;
+            let extrinsics = match sensors[i].extrinsics {
+                Some(extrinsics) => extrinsics,
+                None => client.read_extrinsics().await?,
+            };
+            info!("LiDAR {} connected ({} of {})", code, connected.iter().flatten().count() + 1, sensors.len());
+            connected[i] = Some((client, extrinsics));
+        }
+
+        let (clients, extrinsics) = connected.into_iter().flatten().unzip();
+        Ok(MultiLivox {
+            sensors: sensors.iter().map(|sensor| sensor.broadcast_code.as_str().into()).collect(),
+            clients,
+            extrinsics,
+        })
+    }
+
+    /// Broadcast codes of the LiDARs.
+    pub fn sensors(&self) -> &[Arc<str>] {
+        &self.sensors
+    }
+
+    pub fn clients(&self) -> &[LivoxClient] {
+        &self.clients
+    }
+
+    /// From the frame of every LiDAR to the vehicle frame.
+    pub fn extrinsics(&self) -> &[Extrinsics] {
+        &self.extrinsics
+    }
+
+    /// Start or stop sampling of every LiDAR.
+    pub async fn set_sampling(&self, start: bool) -> LivoxResult<()> {
+        for client in &self.clients {
+            client.set_sampling(start).await?;
+        }
+        Ok(())
+    }
+
+    /// Get a async stream of the point batches of every LiDAR as they arrive, in the vehicle frame,
+    /// without no-return points. Ends when every client is disconnected or dropped.
+    pub fn batch_stream(&self) -> impl Stream<Item=LivoxResult<TaggedBatch>> {
+        type BatchStream = Pin<Box<dyn Stream<Item=LivoxResult<TaggedBatch>> + Send>>;
+
+        let mut streams = StreamMap::new();
+        for (i, (client, extrinsics)) in self.clients.iter().zip(self.extrinsics.iter().copied()).enumerate() {
+            let sensor = self.sensors[i].clone();
+            let stream = client.point_stream().map(move |batch| batch.map(|mut batch| {
+                batch.retain(|p| !p.point.is_zero());
+                extrinsics.transform_batch(&mut batch);
+                TaggedBatch { sensor: sensor.clone(), batch }
+            }));
+            streams.insert(i, Box::pin(stream) as BatchStream);
+        }
+        streams.map(|(_, batch)| batch)
+    }
+
+    /// Get a async stream of the points of every LiDAR merged over `period`, in the vehicle frame,
+    /// see [`FrameAligner`]. Ends when every client is disconnected or dropped.
+    pub fn frame_stream(&self, period: Duration, max_delay: Duration) -> impl Stream<Item=LivoxResult<FusedFrame>> {
+        let batches = self.batch_stream();
+        let mut aligner = FrameAligner::new(self.sensors.clone(), period, max_delay);
+        stream! {
+            tokio::pin!(batches);
+            while let Some(batch) = batches.next().await {
+                match batch {
+                    Ok(batch) => for frame in aligner.push(batch) {
+                        yield Ok(frame);
+                    },
+                    Err(err) => yield Err(err),
+                }
+            }
+            for frame in aligner.flush() {
+                yield Ok(frame);
+            }
+        }
+    }
+
+    /// Disconnect every LiDAR, returning all of them along with the first failure to disconnect one,
+    /// see [`LivoxClient::disconnect`].
+    pub async fn disconnect(self) -> (Vec<Livox>, LivoxResult<()>) {
+        let mut devices = Vec::with_capacity(self.clients.len());
+        let mut result = Ok(());
+        for client in self.clients {
+            let (device, disconnected) = client.disconnect().await;
+            devices.push(device);
+            result = result.and(disconnected);
+        }
+        (devices, result)
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::point::{BatchPoint, PacketInfo};
+
+    const A: &str = "0TFDG3B006H2Z11";
+    const B: &str = "0TFDG3U99101431";
+
+    fn tagged(sensor: &str, time_ms: u64) -> TaggedBatch {
+        let mut batch = PointBatch {
+            packet: PacketInfo { timestamp: 7, host_timestamp: Some(time_ms * 1_000_000), ..PacketInfo::default() },
+            ..PointBatch::default()
+        };
+        batch.push(BatchPoint::default());
+        TaggedBatch { sensor: sensor.into(), batch }
+    }
+
+    #[test]
+    fn test_align() {
+        let mut aligner = FrameAligner::new(vec![A.into(), B.into()], Duration::from_millis(100), Duration::from_millis(100));
+        assert!(aligner.push(tagged(A, 10)).is_empty());
+        assert!(aligner.push(tagged(B, 20)).is_empty());
+        assert!(aligner.push(tagged(A, 50)).is_empty());
+        // LiDAR 1 has not reached the end of the first frame yet
+        assert!(aligner.push(tagged(A, 110)).is_empty());
+
+        let frames = aligner.push(tagged(B, 120));
+        assert_eq!(frames.len(), 1);
+        assert_eq!(frames[0].timestamp, 0);
+        assert_eq!(frames[0].sensors, [Arc::from(A), Arc::from(B)]);
+        assert_eq!(frames[0].sources, [0, 1, 0]);
+        assert_eq!(frames[0].batches, [2, 1]);
+
+        assert!(aligner.push(tagged(B, 90)).is_empty());
+        assert_eq!(aligner.late(), 1);
+        // neither is a LiDAR it does not know
+        assert!(aligner.push(tagged("0TFDG3U99101432", 400)).is_empty());
+
+        // LiDAR 1 dropped out, LiDAR 0 is far enough past the second frame
+        let frames = aligner.push(tagged(A, 350));
+        assert_eq!(frames.iter().map(|f| f.timestamp).collect::<Vec<_>>(), [100_000_000]);
+        assert_eq!(frames[0].batches, [1, 1]);
+
+        let frames = aligner.flush();
+        assert_eq!(frames.len(), 1);
+        assert_eq!((frames[0].timestamp, frames[0].batches.as_slice()), (300_000_000, [1, 0].as_slice()));
+        assert!(aligner.push(tagged(A, 380)).is_empty());
+        assert_eq!(aligner.late(), 2);
+    }
+
+    #[tokio::test]
+    async fn test_connect_rejects_config() {
+        let sensor = |code: &str| SensorConfig { broadcast_code: code.into(), extrinsics: None };
+        let result = MultiLivox::connect(&[sensor(A), sensor(B), sensor(A)], HandshakeOption::new()).await;
+        assert!(matches!(result, Err(LivoxError::InvalidSensorConfig(_))));
+        let result = MultiLivox::connect(&[sensor(A), sensor(B)], HandshakeOption::new().data_port(65535)).await;
+        assert!(matches!(result, Err(LivoxError::InvalidSensorConfig(_))));
+    }
+}
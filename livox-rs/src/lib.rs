@@ -1,31 +1,50 @@
 use std::time::Duration;
 use std::error::Error;
-use std::net::Ipv4Addr;
+use std::net::{IpAddr, Ipv4Addr};
 use std::net::SocketAddr;
-use std::sync::Arc;
-use async_stream::try_stream;
+use std::sync::{Arc, Mutex};
+use std::time::SystemTime;
+use async_stream::stream;
+use bytes::Bytes;
 use nalgebra::SMatrix;
 use tokio::{select, spawn};
 use tokio::net::UdpSocket;
-use tokio::sync::{mpsc, oneshot};
+use tokio::sync::{mpsc, oneshot, watch};
 use tokio::task::JoinHandle;
-use tokio::time::interval;
-use tracing::{error, info, info_span, instrument, Instrument, warn};
+use tokio::time::{Instant, interval, timeout_at};
+use tracing::{debug, error, info, info_span, instrument, Instrument, warn};
 
-use crate::LivoxError::{BadResponse, ParseError};
+use crate::LivoxError::{BadResponse, CommandTimeout, ParseError};
 use crate::model::{ControlFrame, FrameData};
+use crate::model::data_type::LiDARStatusCode;
 use crate::model::deku_data_type::{ExtractError, general, MessageData, RequestData, ResponseData};
+use crate::model::traits::{Request, Response};
 use crate::result_util::ToLivoxResult;
+use crate::time_sync::{ClockSource, TimeSyncState, TimeSyncStatus};
 
 
 pub mod model;
+pub mod capture;
+pub mod export;
+pub mod point;
+pub mod filter;
+pub mod cloud;
+pub mod projection;
+pub mod depth;
+pub mod extrinsics;
+pub mod time_sync;
+pub mod clock_align;
+pub mod network;
+pub mod fusion;
+#[cfg(feature = "shm")]
+pub mod shm;
 
 #[cfg(test)]
 mod test;
 
 /// Represents a Livox device.
 /// See [Livox SDK Communication Protocol](https://github.com/Livox-SDK/Livox-SDK/wiki/Livox-SDK-Communication-Protocol#0x00-broadcast-message) for more information.
-#[derive(Debug)]
+#[derive(Debug, Clone)]
 pub struct Livox {
     /// UDP socket address of the Livox device for commands, port should always be 65000.
     /// (Note: Data transmissions are not from the same socket port as the command transmission.)
@@ -37,7 +56,7 @@ pub struct Livox {
 }
 
 /// Livox device type.
-#[derive(Debug)]
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
 #[repr(u8)]
 pub enum DeviceType {
     /// Livox Mid-70 (0x06)
@@ -45,6 +64,16 @@ pub enum DeviceType {
     NotImplemented = 255,
 }
 
+/// Coordinate system of point cloud data, see [`LivoxClient::set_coordinate_system`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[repr(u8)]
+pub enum CoordinateSystem {
+    /// Data type 2, [`DT2`](model::data_type::DT2).
+    Cartesian = 0,
+    /// Data type 3, [`DT3`](model::data_type::DT3).
+    Spherical = 1,
+}
+
 /// Error types in [`Livox`] and [`LivoxClient`].
 #[derive(Debug)]
 pub enum LivoxError {
@@ -52,11 +81,25 @@ pub enum LivoxError {
     ParseError(model::ParseError),
     NoneBroadcastReceived,
     HandshakeFailed(Livox),
+    /// No local IPv4 address routes to the LiDAR, set one with [`HandshakeOption::user_ip`].
+    NoHostAddress(Livox),
     AckFailed(u8),
     AckWrong(ResponseData),
     BadResponse(FrameData),
     AsyncChannelError(&'static str, mpsc::error::SendError<AsyncCommandTask>),
     AsyncCallbackError(&'static str, oneshot::error::RecvError),
+    CaptureError(&'static str, pcap_file::PcapError),
+    CommandTimeout,
+    InvalidIpConfig(&'static str),
+    /// [`MultiLivox::connect`](fusion::MultiLivox::connect) was given sensors it can not connect to.
+    InvalidSensorConfig(&'static str),
+    /// [`LivoxClient::reconfigure_network`] could not apply the configuration, the device was left as it was
+    /// and the client still works.
+    NotReconfigured { client: Box<LivoxClient>, reason: Box<LivoxError> },
+    /// [`LivoxClient::reconfigure_network`] could not reconnect, `rolled_back` if the device was set back to DHCP.
+    ReconfigureFailed { rolled_back: bool, reason: Box<LivoxError> },
+    /// The device did not come back within [`LivoxClient::REBOOT_TIMEOUT`] after a reboot.
+    RebootTimeout,
 }
 
 impl std::fmt::Display for LivoxError {
@@ -75,6 +118,31 @@ pub type LivoxResult<T> = Result<T, LivoxError>;
 
 mod result_util;
 
+/// A point cloud packet as received, see [`LivoxClient::packet_stream`].
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct RawPacket {
+    /// Host system time of arrival, in nanoseconds since UNIX epoch.
+    pub arrival: u64,
+    /// Address of the LiDAR.
+    pub src: SocketAddr,
+    pub payload: Bytes,
+}
+
+/// Parse a stream of packets as [`LivoxClient::frame_stream`] does, estimating host timestamps
+/// from arrival times. Lets packets be forwarded as they are before being parsed.
+pub fn parse_packet_stream(packets: impl tokio_stream::Stream<Item=LivoxResult<RawPacket>>)
+                           -> impl tokio_stream::Stream<Item=LivoxResult<model::PointCloudFrame>> {
+    use tokio_stream::StreamExt;
+
+    let mut estimator = clock_align::ClockEstimator::default();
+    packets.map(move |packet| {
+        let packet = packet?;
+        let mut frame = model::PointCloudFrame::parse(&packet.payload).map_err(ParseError)?;
+        frame.align_host_time(&mut estimator, packet.arrival);
+        Ok(frame)
+    })
+}
+
 /// A asynchronous Livox command task.
 #[derive(Debug)]
 pub struct AsyncCommandTask {
@@ -82,19 +150,45 @@ pub struct AsyncCommandTask {
     callback: oneshot::Sender<LivoxResult<ResponseData>>,
 }
 
+/// Options of [`Livox::handshake`], built like `HandshakeOption::new().data_port(56001)`.
+#[derive(Debug, Clone, Default)]
 pub struct HandshakeOption {
-    user_ip: Ipv4Addr,
+    user_ip: Option<Ipv4Addr>,
     cmd_port: u16,
     data_port: u16,
+    imu_port: u16,
 }
 
-impl Default for HandshakeOption {
-    fn default() -> Self {
-        HandshakeOption {
-            user_ip: Ipv4Addr::new(192, 168, 1, 50),
-            cmd_port: 0,
-            data_port: 0,
-        }
+impl HandshakeOption {
+    /// Detect host IP, bind command and data sockets to any free port, no IMU data.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Host IP the LiDAR sends data to.
+    /// By default, the address of the local interface routing to the LiDAR.
+    pub fn user_ip(mut self, ip: Ipv4Addr) -> Self {
+        self.user_ip = Some(ip);
+        self
+    }
+
+    /// Local port for commands, `0` for any.
+    pub fn cmd_port(mut self, port: u16) -> Self {
+        self.cmd_port = port;
+        self
+    }
+
+    /// Local port for point cloud data, `0` for any.
+    pub fn data_port(mut self, port: u16) -> Self {
+        self.data_port = port;
+        self
+    }
+
+    /// Port the LiDAR sends IMU data to, `0` for none.
+    /// The client does not listen on it, bind a socket to it yourself.
+    pub fn imu_port(mut self, port: u16) -> Self {
+        self.imu_port = port;
+        self
     }
 }
 
@@ -107,18 +201,58 @@ impl Livox {
     /// [Livox SDK Communication Protocol](https://github.com/Livox-SDK/Livox-SDK/wiki/Livox-SDK-Communication-Protocol#23-sdk-connection).
     #[instrument]
     pub async fn wait_for_one() -> LivoxResult<Self> {
-        use LivoxError::*;
+        Self::wait_for(|_| true).await
+    }
+
+    /// Wait for the first broadcast from a Livox device accepted by `filter`.
+    pub async fn wait_for(filter: impl Fn(&Livox) -> bool) -> LivoxResult<Self> {
         let broadcast_receiver = UdpSocket::bind(
             (Ipv4Addr::UNSPECIFIED, Livox::BROADCAST_LISTEN_PORT))
             .await.err_reason("While creating broadcast socket")?;
         let mut buf = [0u8; 1024];
 
         info!("Waiting for broadcast on {}", Livox::BROADCAST_LISTEN_PORT);
-        let (size, lidar_addr) = broadcast_receiver.recv_from(&mut buf)
-            .await.err_reason("While receiving broadcast")?;
-        info!("Received {} bytes from {}...", size, lidar_addr);
+        loop {
+            let (size, lidar_addr) = broadcast_receiver.recv_from(&mut buf)
+                .await.err_reason("While receiving broadcast")?;
+            info!("Received {} bytes from {}...", size, lidar_addr);
+
+            let livox = Livox::from_broadcast(&buf[..size], lidar_addr)?;
+            if filter(&livox) { return Ok(livox); }
+            info!("Skipping LiDAR {} at {}", livox.broadcast_code_str(), livox.lidar_addr);
+        }
+    }
 
-        let ControlFrame { data, .. } = ControlFrame::parse(&buf[..size]).map_err(ParseError)?;
+    /// Collect every Livox device broadcasting within `duration`.
+    pub async fn discover(duration: Duration) -> LivoxResult<Vec<Self>> {
+        let broadcast_receiver = UdpSocket::bind(
+            (Ipv4Addr::UNSPECIFIED, Livox::BROADCAST_LISTEN_PORT))
+            .await.err_reason("While creating broadcast socket")?;
+        let mut buf = [0u8; 1024];
+        let mut found: Vec<Livox> = Vec::new();
+
+        let deadline = tokio::time::sleep(duration);
+        tokio::pin!(deadline);
+        loop {
+            select! {
+                received = broadcast_receiver.recv_from(&mut buf) => {
+                    let (size, lidar_addr) = received.err_reason("While receiving broadcast")?;
+                    match Livox::from_broadcast(&buf[..size], lidar_addr) {
+                        Ok(livox) if !found.iter().any(|f| f.broadcast_code == livox.broadcast_code) => found.push(livox),
+                        Ok(_) => {}
+                        Err(err) => warn!("Bad broadcast from {}: {}", lidar_addr, err),
+                    }
+                }
+                _ = &mut deadline => break,
+            }
+        }
+        Ok(found)
+    }
+
+    /// Parse a broadcast message received from `lidar_addr`.
+    pub fn from_broadcast(buf: &[u8], lidar_addr: SocketAddr) -> LivoxResult<Self> {
+        use LivoxError::*;
+        let ControlFrame { data, .. } = ControlFrame::parse(buf).map_err(ParseError)?;
 
         let (broadcast_code, dev_type) = {
             if let FrameData::Message(MessageData::General(
@@ -151,6 +285,11 @@ impl Livox {
         })
     }
 
+    /// Broadcast code as a string, without the trailing '\0'.
+    pub fn broadcast_code_str(&self) -> String {
+        String::from_utf8_lossy(&self.broadcast_code).trim_end_matches('\0').to_string()
+    }
+
     /// Try to send handshake message to this Livox device.
     /// Returns a [`LivoxClient`] if handshake succeeded.
     #[instrument(skip(self, option), fields(lidar = % self.lidar_addr))]
@@ -164,6 +303,16 @@ impl Livox {
 
         command_socket.connect(self.lidar_addr).await.err_reason("While connecting socket to LiDAR")?;
 
+        // The connected socket is bound to the interface routing to the LiDAR.
+        let user_ip = match option.user_ip {
+            Some(ip) => ip,
+            None => match command_socket.local_addr().err_reason("While detecting host IP")?.ip() {
+                IpAddr::V4(ip) if !ip.is_unspecified() => ip,
+                _ => return Err(NoHostAddress(self)),
+            }
+        };
+        info!("Host IP is {}", user_ip);
+
         let data_socket = UdpSocket::bind(
             (Ipv4Addr::UNSPECIFIED, option.data_port)).await.err_reason("While creating data socket")?;
         let data_port = data_socket.local_addr().unwrap().port();
@@ -173,10 +322,10 @@ impl Livox {
         let handshake = ControlFrame {
             version: 1,
             data: FrameData::Request(general::request::Handshake {
-                user_ip: option.user_ip.octets(),
+                user_ip: user_ip.octets(),
                 data_port,
                 cmd_port,
-                imu_port: 0,
+                imu_port: option.imu_port,
             }.into()),
             seq_num: 0,
         };
@@ -199,15 +348,20 @@ impl Livox {
                 let task_thread = LivoxClient::spawn_task_thread(command_socket, task_receiver);
 
                 let (heartbeat_stop, heartbeat_rx) = oneshot::channel();
-                let heartbeat_thread = LivoxClient::spawn_heartbeat(task_channel.clone(), heartbeat_rx);
+                let (status_tx, status) = watch::channel(None);
+                let heartbeat_thread = LivoxClient::spawn_heartbeat(task_channel.clone(), heartbeat_rx, status_tx);
 
                 return Ok(LivoxClient {
                     lidar: self,
                     task_channel,
                     task_thread,
-                    heartbeat_stop,
+                    heartbeat_stop: Some(heartbeat_stop),
                     heartbeat_thread,
+                    status,
+                    time_sync: Mutex::new(None),
                     data_socket: Arc::new(data_socket),
+                    closed: watch::channel(false).0,
+                    disconnected: false,
                 });
             }
         }
@@ -224,13 +378,27 @@ pub struct LivoxClient {
     pub lidar: Livox,
     task_channel: mpsc::Sender<AsyncCommandTask>,
     task_thread: JoinHandle<()>,
-    heartbeat_stop: oneshot::Sender<()>,
+    heartbeat_stop: Option<oneshot::Sender<()>>,
     heartbeat_thread: JoinHandle<()>,
+    status: watch::Receiver<Option<u32>>,
+    time_sync: Mutex<Option<TimeSyncTask>>,
     data_socket: Arc<UdpSocket>,
+    /// Ends the point cloud streams when sent or dropped.
+    closed: watch::Sender<bool>,
+    disconnected: bool,
+}
+
+/// A running time synchronisation task, see [`LivoxClient::start_time_sync`].
+#[derive(Debug)]
+struct TimeSyncTask {
+    stop: oneshot::Sender<()>,
+    state: Arc<Mutex<TimeSyncState>>,
 }
 
 impl LivoxClient {
     const HEARTBEAT_PERIOD: Duration = Duration::from_millis(750);
+    /// Time to wait for the acknowledge of a command.
+    pub const COMMAND_TIMEOUT: Duration = Duration::from_secs(2);
 
     async fn send_command_to_channel(channel: &mpsc::Sender<AsyncCommandTask>, command: impl Into<RequestData>) -> LivoxResult<ResponseData> {
         let (callback, task) = oneshot::channel::<LivoxResult<ResponseData>>();
@@ -253,7 +421,7 @@ impl LivoxClient {
 
                 let callback = |result: LivoxResult<ResponseData>| {
                     if let Err(data) = callback.send(result) {
-                        error!("Synchronized sender callback failed! {:?}", data)
+                        debug!("Nobody is waiting for the response: {:?}", data)
                     }
                 };
 
@@ -267,32 +435,33 @@ impl LivoxClient {
                 };
                 // info!("Sent {} bytes of command", _sent_size);
 
-                let recv_size = match command_socket.recv(&mut buf).await.err_reason("While receiving command") {
-                    Ok(size) => size,
-                    Err(err) => {
-                        callback(Err(err));
-                        continue;
+                let deadline = Instant::now() + LivoxClient::COMMAND_TIMEOUT;
+                let ack = loop {
+                    let recv_size = match timeout_at(deadline, command_socket.recv(&mut buf)).await {
+                        Ok(received) => match received.err_reason("While receiving command") {
+                            Ok(size) => size,
+                            Err(err) => break Err(err),
+                        },
+                        Err(_) => break Err(CommandTimeout),
+                    };
+                    match ControlFrame::parse(&buf[..recv_size]).map_err(ParseError) {
+                        Ok(ControlFrame { seq_num: ack_seq, .. }) if ack_seq != seq_num => {
+                            debug!("Dropping stale acknowledge #{}", ack_seq);
+                        }
+                        Ok(ControlFrame { data: FrameData::Response(ack), .. }) => break Ok(ack),
+                        Ok(ControlFrame { .. }) => break Err(BadResponse(frame.data)),
+                        Err(err) => break Err(err),
                     }
                 };
-                let ack = match ControlFrame::parse(&buf[..recv_size]).map_err(ParseError) {
-                    Ok(ControlFrame { data: FrameData::Response(ack), .. }) => ack,
-                    Ok(ControlFrame { .. }) => {
-                        callback(Err(BadResponse(frame.data)));
-                        continue;
-                    }
-                    Err(err) => {
-                        callback(Err(err));
-                        continue;
-                    }
-                };
-                callback(Ok(ack));
+                callback(ack);
             }
-            warn!("Task thread exited");
+            info!("Task thread exited");
         }.instrument(info_span!("command synchronized sender")))
     }
 
     // #[instrument]
-    fn spawn_heartbeat(channel: mpsc::Sender<AsyncCommandTask>, stop_signal: oneshot::Receiver<()>) -> JoinHandle<()> {
+    fn spawn_heartbeat(channel: mpsc::Sender<AsyncCommandTask>, stop_signal: oneshot::Receiver<()>,
+                       status: watch::Sender<Option<u32>>) -> JoinHandle<()> {
         use general::*;
 
         spawn(async move {
@@ -303,11 +472,12 @@ impl LivoxClient {
             loop {
                 select! {
                 _ = interval.tick() => {
-                    let ack = LivoxClient::send_command_to_channel(&channel, request::Heartbeat{}).await.unwrap().try_into();
-                    if matches!(ack, Ok(response::Heartbeat { ret_code: 0,.. })) {
-                        info!("Heartbeat OK @ {}ms", start_time.elapsed().as_millis());
-                    } else {
-                        error!("Heartbeat failed @ {}ms: {:?}", start_time.elapsed().as_millis(), ack);
+                    match LivoxClient::request_to_channel(&channel, request::Heartbeat{}).await {
+                        Ok(response::Heartbeat { ack_msg, .. }) => {
+                            info!("Heartbeat OK @ {}ms", start_time.elapsed().as_millis());
+                            status.send_replace(Some(ack_msg));
+                        }
+                        Err(err) => error!("Heartbeat failed @ {}ms: {:?}", start_time.elapsed().as_millis(), err),
                     }
                 }
                 _ = &mut stop_signal => { break; }
@@ -317,43 +487,260 @@ impl LivoxClient {
     }
 
     /// Send a command to the LiDAR.
-    /// See [`CmdGeneral`] and [`CmdLiDAR`] for available commands.
+    /// See [`general`] and [`lidar`](model::deku_data_type::lidar) for available commands.
     pub async fn send_command(&self, command: impl Into<RequestData>) -> LivoxResult<ResponseData> {
         Self::send_command_to_channel(&self.task_channel, command).await
     }
 
+    /// Send a command to the LiDAR and extract its typed acknowledge.
+    /// Fails with [`LivoxError::AckFailed`] if the return code is not `0`.
+    pub async fn request<R>(&self, command: R) -> LivoxResult<R::Response>
+        where R: Request + Into<RequestData>,
+              R::Response: TryFrom<ResponseData, Error=ExtractError<<R::Response as Response>::Enum>>,
+              <R::Response as Response>::Enum: Into<ResponseData> {
+        Self::request_to_channel(&self.task_channel, command).await
+    }
+
+    async fn request_to_channel<R>(channel: &mpsc::Sender<AsyncCommandTask>, command: R) -> LivoxResult<R::Response>
+        where R: Request + Into<RequestData>,
+              R::Response: TryFrom<ResponseData, Error=ExtractError<<R::Response as Response>::Enum>>,
+              <R::Response as Response>::Enum: Into<ResponseData> {
+        use LivoxError::*;
+
+        let ack = Self::send_command_to_channel(channel, command).await?;
+        match R::Response::try_from(ack) {
+            Ok(response) if response.ret_code() == 0 => Ok(response),
+            Ok(response) => Err(AckFailed(response.ret_code())),
+            Err(ExtractError::WrongCommand(c)) => Err(AckWrong(c.into())),
+            Err(ExtractError::WrongCommandSet(any)) => Err(AckWrong(any)),
+        }
+    }
+
+    /// Status code reported by the latest successful heartbeat.
+    pub fn status(&self) -> Option<LiDARStatusCode> {
+        (*self.status.borrow()).map(LiDARStatusCode::from)
+    }
+
+    /// Time synchronisation status reported by the latest successful heartbeat.
+    pub fn time_sync_status(&self) -> Option<TimeSyncStatus> {
+        self.status().as_ref().map(TimeSyncStatus::from)
+    }
+
+    /// Push UTC time from `source` to the LiDAR every `period`,
+    /// replacing the running time synchronisation task if any.
+    /// The protocol expects one push per PPS pulse, so `period` should usually be 1 second.
+    /// [Protocol Definition](https://github.com/Livox-SDK/Livox-SDK/wiki/Livox-SDK-Communication-Protocol#0x0a-update-utc-synchronize-time)
+    pub fn start_time_sync(&self, mut source: impl ClockSource, period: Duration) {
+        use model::deku_data_type::lidar::request::UpdateUTCSynchronizeTime;
+
+        let channel = self.task_channel.clone();
+        let (stop, stop_signal) = oneshot::channel::<()>();
+        let state = Arc::new(Mutex::new(TimeSyncState::default()));
+        let task_state = state.clone();
+
+        spawn(async move {
+            let mut interval = interval(period);
+            tokio::pin!(stop_signal);
+            loop {
+                select! {
+                    _ = interval.tick() => {}
+                    _ = &mut stop_signal => break,
+                }
+                let Some(time) = source.now() else {
+                    warn!("Clock source has no valid time");
+                    task_state.lock().unwrap().failures += 1;
+                    continue;
+                };
+                let host_time = SystemTime::now();
+                let result = Self::request_to_channel(&channel, UpdateUTCSynchronizeTime::from_system_time(time)).await;
+                let mut state = task_state.lock().unwrap();
+                match result {
+                    Ok(_) => {
+                        state.last_sync = Some(host_time);
+                        state.offset_ns = Some(time_sync::signed_diff_ns(time, host_time));
+                        state.failures = 0;
+                    }
+                    Err(err) => {
+                        warn!("Time sync failed: {:?}", err);
+                        state.failures += 1;
+                    }
+                }
+            }
+        }.instrument(info_span!("time sync")));
+
+        let previous = self.time_sync.lock().unwrap().replace(TimeSyncTask { stop, state });
+        if let Some(previous) = previous {
+            let _ = previous.stop.send(());
+        }
+    }
+
+    /// Stop the running time synchronisation task.
+    pub fn stop_time_sync(&self) {
+        if let Some(task) = self.time_sync.lock().unwrap().take() {
+            let _ = task.stop.send(());
+        }
+    }
+
+    /// State of the running time synchronisation task.
+    pub fn time_sync_state(&self) -> Option<TimeSyncState> {
+        self.time_sync.lock().unwrap().as_ref().map(|task| *task.state.lock().unwrap())
+    }
+
     /// Start or stop sampling.
     /// [Protocol Definition](https://github.com/Livox-SDK/Livox-SDK/wiki/Livox-SDK-Communication-Protocol#0x04-startstop-sampling)
     #[instrument]
     pub async fn set_sampling(&self, start: bool) -> Result<(), LivoxError> {
-        use LivoxError::*;
         use general::*;
 
-        let command = request::StartStopSampling {
+        self.request(request::StartStopSampling {
             sample_ctrl: if start { 1 } else { 0 }
-        };
+        }).await.map(|_| ())
+    }
 
-        let ack = self.send_command(command).await?;
-        match ack.try_into() {
-            Ok(response::StartStopSampling { ret_code: 0 }) => Ok(()),
-            Ok(response::StartStopSampling { ret_code }) => Err(AckFailed(ret_code)),
-            // ack if matches!(ack, Acknowledge::General(AckGeneral::StartStopSampling { .. })) => Err(AckFailed(ack)),
-            Err(ExtractError::WrongCommand(c)) => Err(AckWrong(c.into())),
-            Err(ExtractError::WrongCommandSet(any)) => Err(AckWrong(any)),
+    /// Switch the coordinate system of point cloud data.
+    /// Streams convert spherical points to Cartesian ones, so consumers keep working.
+    /// [Protocol Definition](https://github.com/Livox-SDK/Livox-SDK/wiki/Livox-SDK-Communication-Protocol#0x05-change-coordinate-system)
+    #[instrument]
+    pub async fn set_coordinate_system(&self, coordinate: CoordinateSystem) -> LivoxResult<()> {
+        self.request(general::request::ChangeCoordinateSystem {
+            coordinate_type: coordinate as u8
+        }).await.map(|_| ())
+    }
+
+    /// Stop sampling, send [`general::request::Disconnect`], stop background tasks and close sockets,
+    /// returning the LiDAR so it can be handshaked again, along with whether the disconnection was acknowledged.
+    /// The LiDAR is returned even if it was not, it will time out the connection by itself.
+    /// Point cloud streams of this client end.
+    ///
+    /// Dropping a client does the same on a best-effort basis, without waiting for acknowledges.
+    #[instrument(skip(self), fields(lidar = % self.lidar.lidar_addr))]
+    pub async fn disconnect(mut self) -> (Livox, LivoxResult<()>) {
+        if let Err(err) = self.set_sampling(false).await {
+            warn!("Failed to stop sampling: {:?}", err);
         }
+        let result = self.request(general::request::Disconnect {}).await;
+        self.stop_tasks().await;
+        info!("Disconnected");
+
+        (self.lidar.clone(), result.map(|_| ()))
     }
 
-    /// Get a async stream of homogeneous matrix of LiDAR data.
-    /// Each point is presented by a `Vector4<f32>`, with `1` as its 4th component.
-    pub fn homogeneous_matrix_stream(&self) -> impl tokio_stream::Stream<Item=LivoxResult<SMatrix<f32, 4, 96>>> {
-        use model::PointCloudFrame;
+    /// End streams and background tasks, after which dropping the client sends nothing.
+    async fn stop_tasks(&mut self) {
+        self.disconnected = true;
+        self.stop_time_sync();
+        self.closed.send_replace(true);
 
+        if let Some(stop) = self.heartbeat_stop.take() {
+            let _ = stop.send(());
+        }
+        let _ = (&mut self.heartbeat_thread).await;
+        self.task_thread.abort();
+        let _ = (&mut self.task_thread).await;
+    }
+
+    /// Read extrinsic parameters stored on the LiDAR.
+    /// Not every model supports this.
+    pub async fn read_extrinsics(&self) -> LivoxResult<extrinsics::Extrinsics> {
+        self.read_device_extrinsics().await.map(extrinsics::Extrinsics::from)
+    }
+
+    /// Read extrinsic parameters stored on the LiDAR, as the device stores them.
+    /// Not every model supports this.
+    pub async fn read_device_extrinsics(&self) -> LivoxResult<extrinsics::DeviceExtrinsics> {
+        use model::deku_data_type::lidar::request::ReadLiDARExtrinsicParameters;
+
+        self.request(ReadLiDARExtrinsicParameters {}).await.map(extrinsics::DeviceExtrinsics::from)
+    }
+
+    /// Write extrinsic parameters to the LiDAR, to be applied by the device itself.
+    /// Not every model supports this.
+    pub async fn write_extrinsics(&self, extrinsics: &extrinsics::Extrinsics) -> LivoxResult<()> {
+        self.write_device_extrinsics(extrinsics::DeviceExtrinsics::from(*extrinsics)).await
+    }
+
+    /// Write extrinsic parameters to the LiDAR as they are, without going through [`extrinsics::Extrinsics`]
+    /// which would turn the angles into an equivalent but different triple.
+    /// Not every model supports this.
+    pub async fn write_device_extrinsics(&self, extrinsics: extrinsics::DeviceExtrinsics) -> LivoxResult<()> {
+        use model::deku_data_type::lidar::request::WriteLiDARExtrinsicParameters;
+
+        self.request(WriteLiDARExtrinsicParameters::from(extrinsics)).await.map(|_| ())
+    }
+
+    /// Get a async stream of point cloud packets as they are received, e.g. to forward them.
+    /// Ends when the client is disconnected or dropped.
+    pub fn packet_stream(&self) -> impl tokio_stream::Stream<Item=LivoxResult<RawPacket>> {
         let socket = self.data_socket.clone();
+        let mut closed = self.closed.subscribe();
         let mut buf = [0u8; 2048];
 
-        try_stream! {
-            while let size = socket.recv(&mut buf).await.err_reason("While reading point cloud frame")? {
-                yield PointCloudFrame::parse_homogeneous_matrix(&buf[..size]);
+        stream! {
+            loop {
+                let received = select! {
+                    received = socket.recv_from(&mut buf) => received,
+                    _ = closed.changed() => break,
+                };
+                match received.err_reason("While reading point cloud frame") {
+                    Ok((size, src)) => yield Ok(RawPacket {
+                        arrival: clock_align::now_ns(),
+                        src,
+                        payload: Bytes::copy_from_slice(&buf[..size]),
+                    }),
+                    Err(err) => {
+                        yield Err(err);
+                        break;
+                    }
+                }
+            }
+        }
+    }
+
+    /// Local address point cloud packets are received on.
+    pub fn data_addr(&self) -> LivoxResult<SocketAddr> {
+        self.data_socket.local_addr().err_reason("While getting data socket address")
+    }
+
+    /// Get a async stream of parsed point cloud frames.
+    /// [`PointCloudFrame::host_timestamp`](model::PointCloudFrame::host_timestamp) is estimated from arrival times.
+    /// Ends when the client is disconnected or dropped.
+    pub fn frame_stream(&self) -> impl tokio_stream::Stream<Item=LivoxResult<model::PointCloudFrame>> {
+        parse_packet_stream(self.packet_stream())
+    }
+
+    /// Get a async stream of point batches, one per packet.
+    /// Ends when the client is disconnected or dropped.
+    pub fn point_stream(&self) -> impl tokio_stream::Stream<Item=LivoxResult<point::PointBatch>> {
+        use tokio_stream::StreamExt;
+
+        self.frame_stream().map(|frame| frame.map(|frame| point::PointBatch::from_frame(&frame)))
+    }
+
+    /// Get a async stream of homogeneous matrix of LiDAR data, in millimetres.
+    /// Each point is presented by a `Vector4<f32>`, with `1` as its 4th component.
+    pub fn homogeneous_matrix_stream(&self) -> impl tokio_stream::Stream<Item=LivoxResult<SMatrix<f32, 4, 96>>> {
+        use tokio_stream::StreamExt;
+
+        self.point_stream().map(|batch| batch?.to_homogeneous_mm()
+            .ok_or(ParseError(model::ParseError::WrongPointCloudSize)))
+    }
+}
+
+impl Drop for LivoxClient {
+    fn drop(&mut self) {
+        if self.disconnected { return; }
+
+        // Acknowledges can not be awaited here: queue the commands, the task thread sends them
+        // and exits once every sender is gone. Dropping `heartbeat_stop` stops the heartbeat.
+        let commands: [RequestData; 2] = [
+            general::request::StartStopSampling { sample_ctrl: 0 }.into(),
+            general::request::Disconnect {}.into(),
+        ];
+        for command in commands {
+            let (callback, _) = oneshot::channel();
+            if let Err(err) = self.task_channel.try_send(AsyncCommandTask { command, callback }) {
+                warn!("Failed to queue disconnect commands: {}", err);
+                break;
             }
         }
     }
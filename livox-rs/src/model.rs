@@ -4,13 +4,14 @@ use byte_struct::*;
 use bytes::{Buf, BufMut, BytesMut};
 use crc::{Algorithm, Crc};
 use deku::DekuContainerRead;
-use nalgebra::{Point3, SMatrix, Vector4};
+use nalgebra::{Point3, SMatrix};
 
 use tracing::{debug, warn};
 use crate::model::ParseError::{InvalidCommandType, InvalidCrc16, InvalidCrc32, InvalidData, InvalidLength, InvalidSOF, InvalidVersion, WrongPointCloudSize};
 
 use deku_data_type::*;
-use data_type::{DT2, DT3, LiDARStatusCode};
+use data_type::{DT0, DT1, DT2, DT3, LiDARStatusCode, LivoxPoint, SphericalPoint, TagInfo};
+use crate::clock_align::ClockEstimator;
 
 
 const HEADER_CHECKSUM_ALGORITHM: Algorithm<u16> = Algorithm { init: 0x4c49u16.reverse_bits(), ..crc::CRC_16_MCRF4XX };
@@ -19,6 +20,7 @@ const CRC16: Crc<u16> = Crc::<u16>::new(&HEADER_CHECKSUM_ALGORITHM);
 const CRC32: Crc<u32> = Crc::<u32>::new(&FRAME_CHECKSUM_ALGORITHM);
 
 #[derive(PartialEq, Debug)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize))]
 pub struct ControlFrame {
     //	Protocol Version, 1 for The Current Version
     pub version: u8,
@@ -30,6 +32,14 @@ pub struct ControlFrame {
     pub seq_num: u16,
 }
 
+/// Validity of the header and frame checksums of a [`ControlFrame`].
+#[derive(PartialEq, Eq, Debug, Clone, Copy)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize))]
+pub struct FrameChecksums {
+    pub crc16_ok: bool,
+    pub crc32_ok: bool,
+}
+
 #[derive(PartialEq, Debug)]
 pub enum ParseError {
     InvalidSOF,
@@ -59,15 +69,33 @@ impl Error for ParseError {
 
 impl ControlFrame {
     const SOF: u8 = 0xAA;
-    #[tracing::instrument]
-    pub fn parse(frame: &[u8]) -> Result<ControlFrame, ParseError> {
+    /// Header (9 bytes) plus trailing CRC32 (4 bytes).
+    const MIN_LEN: usize = 13;
+    /// Check SOF and length fields, returning the frame length.
+    fn frame_len(frame: &[u8]) -> Result<usize, ParseError> {
+        if frame.len() < ControlFrame::MIN_LEN { return Err(InvalidLength); }
         if frame[0] != ControlFrame::SOF { return Err(InvalidSOF); }
 
         // if frame[1] != VERSION { return Err(InvalidVersion); }
 
-        let len = frame[2] as usize;
-        // if frame[2] != len as u8 { return Err(InvalidLength); }
+        let len = u16::from_le_bytes([frame[2], frame[3]]) as usize;
+        if len < ControlFrame::MIN_LEN || len > frame.len() { return Err(InvalidLength); }
+        Ok(len)
+    }
 
+    /// Verify both checksums of a raw frame without decoding it.
+    pub fn checksums(frame: &[u8]) -> Result<FrameChecksums, ParseError> {
+        let len = ControlFrame::frame_len(frame)?;
+        Ok(FrameChecksums {
+            crc16_ok: u16::from_le_bytes([frame[7], frame[8]]) == CRC16.checksum(&frame[..7]),
+            crc32_ok: u32::from_le_bytes([frame[len - 4], frame[len - 3], frame[len - 2], frame[len - 1]])
+                == CRC32.checksum(&frame[..len - 4]),
+        })
+    }
+
+    #[tracing::instrument]
+    pub fn parse(frame: &[u8]) -> Result<ControlFrame, ParseError> {
+        let len = ControlFrame::frame_len(frame)?;
 
         let frame_crc16 = u16::from_le_bytes([frame[7], frame[8]]);
         let calculated_crc16 = CRC16.checksum(&frame[..7]);
@@ -83,6 +111,12 @@ impl ControlFrame {
             return Err(InvalidCrc32);
         } else { debug!("CRC32 checksum: {:08x}", calculated_crc32); }
 
+        ControlFrame::parse_unchecked(frame)
+    }
+
+    /// Decode a frame without verifying its checksums, see [`ControlFrame::checksums`].
+    pub fn parse_unchecked(frame: &[u8]) -> Result<ControlFrame, ParseError> {
+        let len = ControlFrame::frame_len(frame)?;
         Ok(ControlFrame {
             version: frame[1],
             data: match frame[4] {
@@ -121,6 +155,7 @@ impl ControlFrame {
 }
 
 #[derive(PartialEq, Debug)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize))]
 pub enum FrameData {
     Request(RequestData),
     Response(ResponseData),
@@ -146,8 +181,8 @@ impl From<MessageData> for FrameData {
 }
 
 pub mod data_type;
-mod traits;
-pub(crate) mod deku_data_type;
+pub mod traits;
+pub mod deku_data_type;
 
 #[derive(PartialEq, Debug)]
 pub struct PointCloudFrame {
@@ -163,42 +198,181 @@ pub struct PointCloudFrame {
 
     pub timestamp: u64,
 
+    /// Host system time of `timestamp`, in nanoseconds since UNIX epoch.
+    /// Estimated from arrival times by streams using a [`ClockEstimator`](crate::clock_align::ClockEstimator),
+    /// `None` when freshly parsed.
+    pub host_timestamp: Option<u64>,
+
     pub data: PointCloudFrameData,
 }
 
+/// Points of a frame. Returns of a multi-return shot follow each other, strongest first.
 #[derive(PartialEq, Debug)]
 pub enum PointCloudFrameData {
-    DT2(Box<[DT2; 96]>),
-    DT3(Box<[DT3; 96]>),
+    /// Cartesian, 100 points.
+    DT0(Vec<DT0>),
+    /// Spherical, 100 points.
+    DT1(Vec<DT1>),
+    /// Cartesian, 96 points.
+    DT2(Vec<DT2>),
+    /// Spherical, 96 points.
+    DT3(Vec<DT3>),
+    /// Cartesian dual return, 48 shots.
+    DT4(Vec<DT2>),
+    /// Spherical dual return, 48 shots.
+    DT5(Vec<DT3>),
+    /// Cartesian triple return, 30 shots.
+    DT7(Vec<DT2>),
+    /// Spherical triple return, 30 shots.
+    DT8(Vec<DT3>),
 }
 
 impl PointCloudFrameData {
+    /// Parse the points after the header, any whole number of shots of data type `data_type`.
+    pub fn parse(data_type: u8, data: &[u8]) -> Result<Self, ParseError> {
+        fn points<T: ByteStruct>(data: &[u8], shot_len: usize) -> Result<Vec<T>, ParseError> {
+            if data.is_empty() || !data.len().is_multiple_of(shot_len) { return Err(WrongPointCloudSize); }
+            Ok(data.chunks(T::BYTE_LEN).map(T::read_bytes_default_le).collect())
+        }
+        /// Returns sharing the angles of their shot: `theta, phi` then `depth, reflectivity, tag` each.
+        fn spherical_returns(data: &[u8], returns: usize) -> Result<Vec<DT3>, ParseError> {
+            let shot_len = 4 + returns * 6;
+            if data.is_empty() || !data.len().is_multiple_of(shot_len) { return Err(WrongPointCloudSize); }
+            Ok(data.chunks(shot_len).flat_map(|shot| {
+                let theta = u16::from_le_bytes([shot[0], shot[1]]);
+                let phi = u16::from_le_bytes([shot[2], shot[3]]);
+                shot[4..].chunks(6).map(move |r| DT3 {
+                    depth: u32::from_le_bytes(r[0..4].try_into().unwrap()),
+                    theta,
+                    phi,
+                    reflectivity: r[4],
+                    tag: TagInfo::from(r[5]),
+                })
+            }).collect())
+        }
+
+        Ok(match data_type {
+            0x00 => PointCloudFrameData::DT0(points(data, DT0::BYTE_LEN)?),
+            0x01 => PointCloudFrameData::DT1(points(data, DT1::BYTE_LEN)?),
+            0x02 => PointCloudFrameData::DT2(points(data, DT2::BYTE_LEN)?),
+            0x03 => PointCloudFrameData::DT3(points(data, DT3::BYTE_LEN)?),
+            0x04 => PointCloudFrameData::DT4(points(data, 2 * DT2::BYTE_LEN)?),
+            0x05 => PointCloudFrameData::DT5(spherical_returns(data, 2)?),
+            0x07 => PointCloudFrameData::DT7(points(data, 3 * DT2::BYTE_LEN)?),
+            0x08 => PointCloudFrameData::DT8(spherical_returns(data, 3)?),
+            _ => return Err(InvalidData),
+        })
+    }
+
+    /// Points in millimetres, spherical points are rounded.
     pub fn extract_points(&self) -> Vec<Point3<i32>> {
+        let spherical = |data: &[DT3]| data.iter()
+            .map(|p| p.to_spherical().to_cartesian().map(|c| c.round() as i32)).collect();
         match self {
-            PointCloudFrameData::DT2(data) => data.iter()
-                .map(DT2::to_point).collect::<Vec<_>>(),
-            _ => todo!("not implemented"),
-            // PointCloudFrameData::DT3(data) => data.iter().flat_map(|dt3| dt3.points()).collect(),
+            PointCloudFrameData::DT0(data) => data.iter().map(|p| Point3::new(p.x, p.y, p.z)).collect(),
+            PointCloudFrameData::DT1(data) => data.iter()
+                .map(|p| SphericalPoint { depth: p.depth, theta: p.theta, phi: p.phi }.to_cartesian().map(|c| c.round() as i32))
+                .collect(),
+            PointCloudFrameData::DT2(data) | PointCloudFrameData::DT4(data) | PointCloudFrameData::DT7(data) =>
+                data.iter().map(DT2::to_point).collect(),
+            PointCloudFrameData::DT3(data) | PointCloudFrameData::DT5(data) | PointCloudFrameData::DT8(data) =>
+                spherical(data),
         }
     }
+
+    /// Points in Cartesian coordinates, whichever coordinate system the device sends.
+    pub fn points(&self) -> Vec<LivoxPoint> {
+        match self {
+            PointCloudFrameData::DT0(data) => data.iter().map(LivoxPoint::from).collect(),
+            PointCloudFrameData::DT1(data) => data.iter().map(LivoxPoint::from).collect(),
+            PointCloudFrameData::DT2(data) | PointCloudFrameData::DT4(data) | PointCloudFrameData::DT7(data) =>
+                data.iter().map(LivoxPoint::from).collect(),
+            PointCloudFrameData::DT3(data) | PointCloudFrameData::DT5(data) | PointCloudFrameData::DT8(data) =>
+                data.iter().map(LivoxPoint::from).collect(),
+        }
+    }
+
+    /// Data type byte in the packet header.
+    pub fn data_type(&self) -> u8 {
+        match self {
+            PointCloudFrameData::DT0(_) => 0x00,
+            PointCloudFrameData::DT1(_) => 0x01,
+            PointCloudFrameData::DT2(_) => 0x02,
+            PointCloudFrameData::DT3(_) => 0x03,
+            PointCloudFrameData::DT4(_) => 0x04,
+            PointCloudFrameData::DT5(_) => 0x05,
+            PointCloudFrameData::DT7(_) => 0x07,
+            PointCloudFrameData::DT8(_) => 0x08,
+        }
+    }
+
+    /// Points per shot: 1, or 2 and 3 for dual and triple return data.
+    pub fn returns(&self) -> usize {
+        match self {
+            PointCloudFrameData::DT4(_) | PointCloudFrameData::DT5(_) => 2,
+            PointCloudFrameData::DT7(_) | PointCloudFrameData::DT8(_) => 3,
+            _ => 1,
+        }
+    }
+
+    /// Number of points, counting every return.
+    pub fn len(&self) -> usize {
+        match self {
+            PointCloudFrameData::DT0(data) => data.len(),
+            PointCloudFrameData::DT1(data) => data.len(),
+            PointCloudFrameData::DT2(data) | PointCloudFrameData::DT4(data) | PointCloudFrameData::DT7(data) => data.len(),
+            PointCloudFrameData::DT3(data) | PointCloudFrameData::DT5(data) | PointCloudFrameData::DT8(data) => data.len(),
+        }
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
 }
 
 impl PointCloudFrame {
+    /// Length of the header before point data.
+    pub const HEADER_LEN: usize = 18;
+    /// Time between two consecutive shots in a frame, in nanoseconds (100k shots/s).
+    /// Returns of a shot share its time.
+    pub const POINT_INTERVAL_NS: u64 = 10_000;
+    /// Number of points in a frame of data type 2 or 3.
+    pub const POINTS_PER_FRAME: usize = 96;
+
+    /// Timestamp of the `index`-th point in this frame, in nanoseconds.
+    pub fn point_timestamp(&self, index: usize) -> u64 {
+        self.timestamp + (index / self.data.returns()) as u64 * Self::POINT_INTERVAL_NS
+    }
+
+    /// Host system time of the `index`-th point in this frame, see [`PointCloudFrame::host_timestamp`].
+    pub fn host_point_timestamp(&self, index: usize) -> Option<u64> {
+        self.host_timestamp.map(|t| t + (index / self.data.returns()) as u64 * Self::POINT_INTERVAL_NS)
+    }
+
+    /// Return of the `index`-th point in its shot, `0` for the first or only one.
+    pub fn return_index(&self, index: usize) -> u8 {
+        (index % self.data.returns()) as u8
+    }
+
+    /// Estimate [`PointCloudFrame::host_timestamp`] from the host time this frame arrived at.
+    /// The frame is sent after its last point is measured.
+    pub fn align_host_time(&mut self, estimator: &mut ClockEstimator, arrival: u64) {
+        estimator.update(self.point_timestamp(self.data.len().saturating_sub(1)), arrival);
+        self.host_timestamp = estimator.to_host(self.timestamp);
+    }
+
+
     pub fn parse(frame: &[u8]) -> Result<PointCloudFrame, ParseError> {
+        if frame.len() < PointCloudFrame::HEADER_LEN { return Err(InvalidLength); }
         Ok(PointCloudFrame {
             version: frame[0],
             slot_id: frame[1],
             lidar_id: frame[2],
             status_code: LiDARStatusCode::read_bytes_default_le(&frame[4..8]),
-            // timestamp_type: frame[4],
-            timestamp_type: 0,
-            timestamp: 0,
-            // timestamp: u64::from_le_bytes([frame[5], frame[6], frame[7], frame[8]]),
-            data: match frame[9] {
-                0x02 => PointCloudFrameData::DT2(<Box<[DT2; 96]>>::try_from(frame[18..].chunks(DT2::BYTE_LEN).map(DT2::read_bytes_default_le).collect::<Vec<DT2>>().into_boxed_slice()).map_err(|_| WrongPointCloudSize)?),
-                0x03 => PointCloudFrameData::DT3(<Box<[DT3; 96]>>::try_from(frame[18..].chunks(DT3::BYTE_LEN).map(DT3::read_bytes_default_le).collect::<Vec<DT3>>().into_boxed_slice()).map_err(|_| WrongPointCloudSize)?),
-                _ => return Err(InvalidData),
-            },
+            timestamp_type: frame[8],
+            timestamp: u64::from_le_bytes(frame[10..18].try_into().unwrap()),
+            host_timestamp: None,
+            data: PointCloudFrameData::parse(frame[9], &frame[PointCloudFrame::HEADER_LEN..])?,
         })
     }
     /*pub fn parse_row_matrix(frame: &[u8]) -> SMatrix<f32, 96, 3> {
@@ -217,12 +391,10 @@ impl PointCloudFrame {
             i32::read_bytes_default_le(&d[8..12]) as f32)).collect::<Vec<_>>();
         SMatrix::<f32, 3, 96>::from_columns(vec.as_slice())
     }*/
-    pub fn parse_homogeneous_matrix(frame: &[u8]) -> SMatrix::<f32, 4, 96> {
-        assert_eq!(frame[9], 0x02);
-        let vec = frame[18..].chunks(DT2::BYTE_LEN).map(|d| Vector4::new(
-            i32::read_bytes_default_le(&d[0..4]) as f32,
-            i32::read_bytes_default_le(&d[4..8]) as f32,
-            i32::read_bytes_default_le(&d[8..12]) as f32, 1.0)).collect::<Vec<_>>();
-        SMatrix::<f32, 4, 96>::from_columns(vec.as_slice())
+    /// Points in millimetres as homogeneous columns, from any point cloud data type,
+    /// see [`PointBatch::to_homogeneous_mm`](crate::point::PointBatch::to_homogeneous_mm).
+    pub fn parse_homogeneous_matrix(frame: &[u8]) -> Result<SMatrix::<f32, 4, 96>, ParseError> {
+        crate::point::PointBatch::from_frame(&PointCloudFrame::parse(frame)?)
+            .to_homogeneous_mm().ok_or(WrongPointCloudSize)
     }
 }
@@ -8,45 +8,90 @@ pub(crate) mod prelude {
 bitfields!(
     #[derive(PartialEq, Eq, Debug)]
     pub LiDARStatusCode: u32 {
-        temp_status: 2,
-        volt_status: 2,
-        motor_status: 2,
-        dirty_warn: 2,
-        firmware_status: 1,
-        pps_status: 1,
-        device_status: 1,
-        fan_status: 1,
-        self_heating: 1,
-        ptp_status: 1,
-        time_sync_status: 2,
-        reserved: 13,
-        system_status: 2,
+        pub temp_status: 2,
+        pub volt_status: 2,
+        pub motor_status: 2,
+        pub dirty_warn: 2,
+        pub firmware_status: 1,
+        pub pps_status: 1,
+        pub device_status: 1,
+        pub fan_status: 1,
+        pub self_heating: 1,
+        pub ptp_status: 1,
+        pub time_sync_status: 3,
+        pub reserved: 13,
+        pub system_status: 2,
     }
 );
 
 bitfields!(
     #[derive(PartialEq, Eq, Debug)]
     pub HubStatusCode: u32 {
-        sync_status: 2,
-        temp_status: 2,
-        lidar_status: 1,
-        lidar_link_status: 1,
-        firmware_status: 1,
-        reserved: 23,
-        system_status: 2,
+        pub sync_status: 2,
+        pub temp_status: 2,
+        pub lidar_status: 1,
+        pub lidar_link_status: 1,
+        pub firmware_status: 1,
+        pub reserved: 23,
+        pub system_status: 2,
     }
 );
 
 bitfields!(
     #[derive(PartialEq, Eq, Debug)]
     pub TagInfo: u8 {
-        space: 2,
-        strength: 2,
-        return_count: 2,
-        near_distortion: 2,
+        pub space: 2,
+        pub strength: 2,
+        pub return_count: 2,
+        pub near_distortion: 2,
     }
 );
 
+impl From<u32> for LiDARStatusCode {
+    fn from(raw: u32) -> Self {
+        LiDARStatusCode::from_raw(raw)
+    }
+}
+
+impl From<&LiDARStatusCode> for u32 {
+    fn from(status: &LiDARStatusCode) -> Self {
+        status.to_raw()
+    }
+}
+
+impl From<u8> for TagInfo {
+    fn from(raw: u8) -> Self {
+        TagInfo::from_raw(raw)
+    }
+}
+
+impl From<&TagInfo> for u8 {
+    fn from(tag: &TagInfo) -> Self {
+        tag.to_raw()
+    }
+}
+
+/// Cartesian point of data type 0, without tag.
+#[derive(ByteStruct, PartialEq, Debug)]
+#[byte_struct_le]
+pub struct DT0 {
+    pub x: i32,
+    pub y: i32,
+    pub z: i32,
+    pub reflectivity: u8,
+}
+
+/// Spherical point of data type 1, without tag.
+#[derive(ByteStruct, PartialEq, Debug)]
+#[byte_struct_le]
+pub struct DT1 {
+    pub depth: u32,
+    pub theta: u16,
+    pub phi: u16,
+    pub reflectivity: u8,
+}
+
+/// Cartesian point of data type 2, also every return of data types 4 and 7.
 #[derive(ByteStruct, PartialEq, Debug)]
 #[byte_struct_le]
 pub struct DT2 {
@@ -66,6 +111,7 @@ impl DT2 {
     }
 }
 
+/// Spherical point of data type 3, also every return of data types 5 and 8.
 #[derive(ByteStruct, PartialEq, Debug)]
 #[byte_struct_le]
 pub struct DT3 {
@@ -75,3 +121,146 @@ pub struct DT3 {
     pub reflectivity: u8,
     pub tag: TagInfo,
 }
+
+impl DT3 {
+    pub fn to_spherical(&self) -> SphericalPoint {
+        SphericalPoint { depth: self.depth, theta: self.theta, phi: self.phi }
+    }
+}
+
+/// Spherical coordinates as sent by the device in data type 3.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub struct SphericalPoint {
+    /// Distance in millimetres.
+    pub depth: u32,
+    /// Zenith angle from the Z axis, in 0.01°.
+    pub theta: u16,
+    /// Azimuth angle from the X axis, in 0.01°.
+    pub phi: u16,
+}
+
+impl SphericalPoint {
+    pub fn theta_rad(&self) -> f64 {
+        (self.theta as f64 / 100.0).to_radians()
+    }
+
+    pub fn phi_rad(&self) -> f64 {
+        (self.phi as f64 / 100.0).to_radians()
+    }
+
+    /// Cartesian coordinates in millimetres.
+    pub fn to_cartesian(&self) -> Point3<f64> {
+        let (sin_theta, cos_theta) = self.theta_rad().sin_cos();
+        let (sin_phi, cos_phi) = self.phi_rad().sin_cos();
+        let depth = self.depth as f64;
+        Point3::new(depth * sin_theta * cos_phi, depth * sin_theta * sin_phi, depth * cos_theta)
+    }
+
+    /// Spherical coordinates of a point in millimetres, rounded to device resolution.
+    pub fn from_cartesian(point: &Point3<f64>) -> Self {
+        let depth = point.coords.norm();
+        if depth == 0.0 { return Self::default(); }
+        let theta = (point.z / depth).clamp(-1.0, 1.0).acos().to_degrees();
+        let phi = point.y.atan2(point.x).to_degrees().rem_euclid(360.0);
+        SphericalPoint {
+            depth: depth.round() as u32,
+            theta: (theta * 100.0).round() as u16,
+            phi: ((phi * 100.0).round() as u16) % 36000,
+        }
+    }
+}
+
+/// A point of either data type, in Cartesian coordinates.
+#[derive(Debug, Clone, Copy, PartialEq, Default)]
+pub struct LivoxPoint {
+    /// Coordinates in metres.
+    pub x: f32,
+    pub y: f32,
+    pub z: f32,
+    pub reflectivity: u8,
+    /// Raw [`TagInfo`] byte.
+    pub tag: u8,
+}
+
+impl LivoxPoint {
+    /// Whether this is a no-return point, sent as all zeroes.
+    pub fn is_zero(&self) -> bool {
+        self.x == 0.0 && self.y == 0.0 && self.z == 0.0
+    }
+
+    pub fn to_point(&self) -> Point3<f32> {
+        Point3::new(self.x, self.y, self.z)
+    }
+
+    pub fn tag_info(&self) -> TagInfo {
+        TagInfo::from_raw(self.tag)
+    }
+
+    /// Spherical coordinates, rounded to device resolution.
+    pub fn to_spherical(&self) -> SphericalPoint {
+        SphericalPoint::from_cartesian(&(self.to_point().cast::<f64>() * 1000.0))
+    }
+}
+
+impl From<&DT2> for LivoxPoint {
+    fn from(p: &DT2) -> Self {
+        LivoxPoint {
+            x: p.x as f32 / 1000.0,
+            y: p.y as f32 / 1000.0,
+            z: p.z as f32 / 1000.0,
+            reflectivity: p.reflectivity,
+            tag: (&p.tag).into(),
+        }
+    }
+}
+
+impl From<&DT0> for LivoxPoint {
+    fn from(p: &DT0) -> Self {
+        LivoxPoint {
+            x: p.x as f32 / 1000.0,
+            y: p.y as f32 / 1000.0,
+            z: p.z as f32 / 1000.0,
+            reflectivity: p.reflectivity,
+            tag: 0,
+        }
+    }
+}
+
+impl From<&DT1> for LivoxPoint {
+    fn from(p: &DT1) -> Self {
+        let spherical = SphericalPoint { depth: p.depth, theta: p.theta, phi: p.phi };
+        let point = spherical.to_cartesian() / 1000.0;
+        LivoxPoint { x: point.x as f32, y: point.y as f32, z: point.z as f32, reflectivity: p.reflectivity, tag: 0 }
+    }
+}
+
+impl From<&DT3> for LivoxPoint {
+    fn from(p: &DT3) -> Self {
+        let point = p.to_spherical().to_cartesian() / 1000.0;
+        LivoxPoint {
+            x: point.x as f32,
+            y: point.y as f32,
+            z: point.z as f32,
+            reflectivity: p.reflectivity,
+            tag: (&p.tag).into(),
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn test_spherical() {
+        let spherical = SphericalPoint { depth: 2000, theta: 9000, phi: 4500 };
+        let point = spherical.to_cartesian();
+        assert!((point - Point3::new(2f64.sqrt() * 1000.0, 2f64.sqrt() * 1000.0, 0.0)).norm() < 1e-9);
+        assert_eq!(SphericalPoint::from_cartesian(&point), spherical);
+
+        let dt3 = DT3 { depth: 1000, theta: 0, phi: 12345, reflectivity: 7, tag: TagInfo::from_raw(0b01) };
+        let point = LivoxPoint::from(&dt3);
+        assert_eq!((point.x, point.y, point.z, point.tag), (0.0, 0.0, 1.0, 1));
+        assert_eq!(point.to_spherical().depth, 1000);
+    }
+}
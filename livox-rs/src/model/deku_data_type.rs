@@ -14,6 +14,7 @@ pub trait Parsable<'a>: Sized + DekuContainerRead<'a> + DekuWrite {
 }
 
 #[derive(Debug, PartialEq, DekuRead, DekuWrite)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize))]
 #[deku(type = "u8")]
 pub enum RequestData {
     #[deku(id = "0x00")] General(general::request::Enum),
@@ -35,6 +36,7 @@ impl From<lidar::request::Enum> for RequestData {
 impl Parsable<'_> for RequestData {}
 
 #[derive(Debug, PartialEq, DekuRead, DekuWrite)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize))]
 #[deku(type = "u8")]
 pub enum ResponseData {
     #[deku(id = "0x00")] General(general::response::Enum),
@@ -62,6 +64,7 @@ pub enum ExtractError<T> {
 }
 
 #[derive(Debug, PartialEq, DekuRead, DekuWrite)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize))]
 #[deku(type = "u8")]
 pub enum MessageData {
     #[deku(id = "0x00")] General(general::message::Enum),
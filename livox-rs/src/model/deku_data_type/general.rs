@@ -4,6 +4,7 @@ pub mod request {
     use crate::model::traits::Request;
 
     #[derive(Debug, PartialEq, Eq, DekuRead, DekuWrite)]
+    #[cfg_attr(feature = "serde", derive(serde::Serialize))]
     #[deku(type = "u8")]
     pub enum Enum {
         #[deku(id = "0x01")]
@@ -27,55 +28,64 @@ pub mod request {
     }
 
     #[derive(Debug, PartialEq, Eq, DekuRead, DekuWrite, Request)]
+    #[cfg_attr(feature = "serde", derive(serde::Serialize))]
     #[deku(endian = "little")]
     pub struct Handshake {
-        pub(crate) user_ip: [u8; 4],
-        pub(crate) data_port: u16,
-        pub(crate) cmd_port: u16,
-        pub(crate) imu_port: u16,
+        pub user_ip: [u8; 4],
+        pub data_port: u16,
+        pub cmd_port: u16,
+        pub imu_port: u16,
     }
 
     #[derive(Debug, PartialEq, Eq, DekuRead, DekuWrite, Request)]
+    #[cfg_attr(feature = "serde", derive(serde::Serialize))]
     #[deku(endian = "little")]
     pub struct QueryDeviceInformation {}
 
     #[derive(Debug, PartialEq, Eq, DekuRead, DekuWrite, Request)]
+    #[cfg_attr(feature = "serde", derive(serde::Serialize))]
     #[deku(endian = "little")]
     pub struct Heartbeat {}
 
     #[derive(Debug, PartialEq, Eq, DekuRead, DekuWrite, Request)]
+    #[cfg_attr(feature = "serde", derive(serde::Serialize))]
     #[deku(endian = "little")]
     pub struct StartStopSampling {
-        pub(crate) sample_ctrl: u8,
+        pub sample_ctrl: u8,
     }
 
     #[derive(Debug, PartialEq, Eq, DekuRead, DekuWrite, Request)]
+    #[cfg_attr(feature = "serde", derive(serde::Serialize))]
     #[deku(endian = "little")]
     pub struct ChangeCoordinateSystem {
-        pub(crate) coordinate_type: u8,
+        pub coordinate_type: u8,
     }
 
     #[derive(Debug, PartialEq, Eq, DekuRead, DekuWrite, Request)]
+    #[cfg_attr(feature = "serde", derive(serde::Serialize))]
     #[deku(endian = "little")]
     pub struct Disconnect {}
 
     #[derive(Debug, PartialEq, Eq, DekuRead, DekuWrite, Request)]
+    #[cfg_attr(feature = "serde", derive(serde::Serialize))]
     #[deku(endian = "little")]
     pub struct ConfigureStaticDynamicIP {
-        pub(crate) ip_mode: u8,
-        pub(crate) ip_addr: [u8; 4],
-        pub(crate) net_mask: [u8; 4],
-        pub(crate) gw_addr: [u8; 4],
+        pub ip_mode: u8,
+        pub ip_addr: [u8; 4],
+        pub net_mask: [u8; 4],
+        pub gw_addr: [u8; 4],
     }
 
     #[derive(Debug, PartialEq, Eq, DekuRead, DekuWrite, Request)]
+    #[cfg_attr(feature = "serde", derive(serde::Serialize))]
     #[deku(endian = "little")]
     pub struct GetDeviceIPInformation {}
 
     #[derive(Debug, PartialEq, Eq, DekuRead, DekuWrite, Request)]
+    #[cfg_attr(feature = "serde", derive(serde::Serialize))]
     #[deku(endian = "little")]
     pub struct RebootDevice {
-        pub(crate) timeout: u16,
+        pub timeout: u16,
     }
 
     // #[derive(Debug, PartialEq, Eq, DekuRead, DekuWrite, Request)]
@@ -92,6 +102,7 @@ pub mod response {
     use crate::ResponseData;
 
     #[derive(Debug, PartialEq, Eq, DekuRead, DekuWrite)]
+    #[cfg_attr(feature = "serde", derive(serde::Serialize))]
     #[deku(type = "u8")]
     pub enum Enum {
         #[deku(id = "0x01")]
@@ -126,9 +137,10 @@ pub mod response {
     }
 
     #[derive(Debug, PartialEq, Eq, DekuRead, DekuWrite, Response)]
+    #[cfg_attr(feature = "serde", derive(serde::Serialize))]
     #[deku(endian = "little")]
     pub struct Handshake {
-        pub(crate) ret_code: u8,
+        pub ret_code: u8,
     }
 
     // impl TryFrom<Enum> for Handshake {
@@ -154,59 +166,67 @@ pub mod response {
     // }
 
     #[derive(Debug, PartialEq, Eq, DekuRead, DekuWrite, Response)]
+    #[cfg_attr(feature = "serde", derive(serde::Serialize))]
     #[deku(endian = "little")]
     pub struct QueryDeviceInformation {
-        pub(crate) ret_code: u8,
-        pub(crate) version: [u8; 4],
+        pub ret_code: u8,
+        pub version: [u8; 4],
     }
 
     #[derive(Debug, PartialEq, Eq, DekuRead, DekuWrite, Response)]
+    #[cfg_attr(feature = "serde", derive(serde::Serialize))]
     #[deku(endian = "little")]
     pub struct Heartbeat {
-        pub(crate) ret_code: u8,
-        pub(crate) work_state: u8,
-        pub(crate) feature_msg: u8,
-        pub(crate) ack_msg: u32,
+        pub ret_code: u8,
+        pub work_state: u8,
+        pub feature_msg: u8,
+        pub ack_msg: u32,
     }
 
     #[derive(Debug, PartialEq, Eq, DekuRead, DekuWrite, Response)]
+    #[cfg_attr(feature = "serde", derive(serde::Serialize))]
     #[deku(endian = "little")]
     pub struct StartStopSampling {
-        pub(crate) ret_code: u8,
+        pub ret_code: u8,
     }
 
     #[derive(Debug, PartialEq, Eq, DekuRead, DekuWrite, Response)]
+    #[cfg_attr(feature = "serde", derive(serde::Serialize))]
     #[deku(endian = "little")]
     pub struct ChangeCoordinateSystem {
-        pub(crate) ret_code: u8,
+        pub ret_code: u8,
     }
 
     #[derive(Debug, PartialEq, Eq, DekuRead, DekuWrite, Response)]
+    #[cfg_attr(feature = "serde", derive(serde::Serialize))]
     #[deku(endian = "little")]
     pub struct Disconnect {
-        pub(crate) ret_code: u8,
+        pub ret_code: u8,
     }
 
     #[derive(Debug, PartialEq, Eq, DekuRead, DekuWrite, Response)]
+    #[cfg_attr(feature = "serde", derive(serde::Serialize))]
     #[deku(endian = "little")]
     pub struct ConfigureStaticDynamicIP {
-        pub(crate) ret_code: u8,
+        pub ret_code: u8,
     }
 
     #[derive(Debug, PartialEq, Eq, DekuRead, DekuWrite, Response)]
+    #[cfg_attr(feature = "serde", derive(serde::Serialize))]
     #[deku(endian = "little")]
     pub struct GetDeviceIPInformation {
-        pub(crate) ret_code: u8,
-        pub(crate) ip_mode: u8,
-        pub(crate) ip_addr: [u8; 4],
-        pub(crate) net_mask: [u8; 4],
-        pub(crate) gw_addr: [u8; 4],
+        pub ret_code: u8,
+        pub ip_mode: u8,
+        pub ip_addr: [u8; 4],
+        pub net_mask: [u8; 4],
+        pub gw_addr: [u8; 4],
     }
 
     #[derive(Debug, PartialEq, Eq, DekuRead, DekuWrite, Response)]
+    #[cfg_attr(feature = "serde", derive(serde::Serialize))]
     #[deku(endian = "little")]
     pub struct RebootDevice {
-        ret_code: u8,
+        pub ret_code: u8,
     }
 }
 
@@ -217,6 +237,7 @@ pub mod message {
 
 
     #[derive(Debug, PartialEq, Eq, DekuRead, DekuWrite)]
+    #[cfg_attr(feature = "serde", derive(serde::Serialize))]
     #[deku(type = "u8")]
     pub enum Enum {
         #[deku(id = "0x00")]
@@ -226,17 +247,19 @@ pub mod message {
     }
 
     #[derive(Debug, PartialEq, Eq, DekuRead, DekuWrite, Message)]
+    #[cfg_attr(feature = "serde", derive(serde::Serialize))]
     #[deku(endian = "little")]
     pub struct BroadcastMessage {
-        pub(crate) broadcast_code: [u8; 16],
-        pub(crate) dev_type: u8,
-        pub(crate) reserved: u16,
+        pub broadcast_code: [u8; 16],
+        pub dev_type: u8,
+        pub reserved: u16,
     }
 
     #[derive(Debug, PartialEq, Eq, DekuRead, DekuWrite, Message)]
+    #[cfg_attr(feature = "serde", derive(serde::Serialize))]
     #[deku(endian = "little")]
     pub struct PushAbnormalStatusInformation {
-        status_code: u32,
+        pub status_code: u32,
     }
 }
 
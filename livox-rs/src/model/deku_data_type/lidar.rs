@@ -4,6 +4,7 @@ pub mod request {
     use crate::model::traits::Request;
 
     #[derive(Debug, PartialEq, DekuRead, DekuWrite)]
+    #[cfg_attr(feature = "serde", derive(serde::Serialize))]
     #[deku(type = "u8")]
     pub enum Enum {
         #[deku(id = "0x00")]
@@ -31,70 +32,124 @@ pub mod request {
     }
 
     #[derive(Debug, PartialEq, Eq, DekuRead, DekuWrite, Request)]
+    #[cfg_attr(feature = "serde", derive(serde::Serialize))]
     #[deku(endian = "little")]
     pub struct SetMode {
-        pub(crate) lidar_mode: u8,
+        pub lidar_mode: u8,
     }
 
     #[derive(Debug, PartialEq, DekuRead, DekuWrite, Request)]
+    #[cfg_attr(feature = "serde", derive(serde::Serialize))]
     #[deku(endian = "little")]
     pub struct WriteLiDARExtrinsicParameters {
-        roll: f32,
-        pitch: f32,
-        yaw: f32,
-        x: i32,
-        y: i32,
-        z: i32,
+        pub roll: f32,
+        pub pitch: f32,
+        pub yaw: f32,
+        pub x: i32,
+        pub y: i32,
+        pub z: i32,
     }
 
     #[derive(Debug, PartialEq, Eq, DekuRead, DekuWrite, Request)]
+    #[cfg_attr(feature = "serde", derive(serde::Serialize))]
     #[deku(endian = "little")]
     pub struct ReadLiDARExtrinsicParameters {}
 
     #[derive(Debug, PartialEq, Eq, DekuRead, DekuWrite, Request)]
+    #[cfg_attr(feature = "serde", derive(serde::Serialize))]
     #[deku(endian = "little")]
     pub struct TurnOnOffRainFogSuppression {
-        state: u8,
+        pub state: u8,
     }
 
     #[derive(Debug, PartialEq, Eq, DekuRead, DekuWrite, Request)]
+    #[cfg_attr(feature = "serde", derive(serde::Serialize))]
     #[deku(endian = "little")]
     pub struct SetTurnOnOffFan {
-        state: u8,
+        pub state: u8,
     }
 
     #[derive(Debug, PartialEq, Eq, DekuRead, DekuWrite, Request)]
+    #[cfg_attr(feature = "serde", derive(serde::Serialize))]
     #[deku(endian = "little")]
     pub struct GetTurnOnOffFanState {}
 
     #[derive(Debug, PartialEq, Eq, DekuRead, DekuWrite, Request)]
+    #[cfg_attr(feature = "serde", derive(serde::Serialize))]
     #[deku(endian = "little")]
     pub struct SetLiDARReturnMode {
-        pub(crate) mode: u8,
+        pub mode: u8,
     }
 
     #[derive(Debug, PartialEq, Eq, DekuRead, DekuWrite, Request)]
+    #[cfg_attr(feature = "serde", derive(serde::Serialize))]
     #[deku(endian = "little")]
     pub struct GetLiDARReturnMode {}
 
     #[derive(Debug, PartialEq, Eq, DekuRead, DekuWrite, Request)]
+    #[cfg_attr(feature = "serde", derive(serde::Serialize))]
     #[deku(endian = "little")]
     pub struct SetIMUDataPushFrequency {
-        frequency: u8,
+        pub frequency: u8,
     }
 
     #[derive(Debug, PartialEq, Eq, DekuRead, DekuWrite, Request)]
+    #[cfg_attr(feature = "serde", derive(serde::Serialize))]
     #[deku(endian = "little")]
     pub struct GetIMUDataPushFrequency {}
 
     #[derive(Debug, PartialEq, Eq, DekuRead, DekuWrite, Request)]
+    #[cfg_attr(feature = "serde", derive(serde::Serialize))]
     #[deku(endian = "little")]
     pub struct UpdateUTCSynchronizeTime {
-        year: u8,
-        month: u8,
-        day: u8,
-        hour: u8,
-        microsecond: u32,
+        pub year: u8,
+        pub month: u8,
+        pub day: u8,
+        pub hour: u8,
+        pub microsecond: u32,
+    }
+
+    impl UpdateUTCSynchronizeTime {
+        /// UTC time of `time`, which must be in years 2000 to 2255.
+        pub fn from_system_time(time: std::time::SystemTime) -> Self {
+            let since_epoch = time.duration_since(std::time::UNIX_EPOCH).unwrap_or_default();
+            let secs = since_epoch.as_secs();
+            let (year, month, day) = civil_from_days((secs / 86400) as i64);
+            let secs_in_hour = secs % 3600;
+            UpdateUTCSynchronizeTime {
+                year: (year - 2000) as u8,
+                month,
+                day,
+                hour: (secs % 86400 / 3600) as u8,
+                microsecond: (secs_in_hour * 1_000_000) as u32 + since_epoch.subsec_micros(),
+            }
+        }
+    }
+
+    /// `(year, month, day)` of days since 1970-01-01, see
+    /// [chrono-Compatible Low-Level Date Algorithms](http://howardhinnant.github.io/date_algorithms.html#civil_from_days).
+    pub(crate) fn civil_from_days(days: i64) -> (i64, u8, u8) {
+        let z = days + 719468;
+        let era = z.div_euclid(146097);
+        let doe = z.rem_euclid(146097);
+        let yoe = (doe - doe / 1460 + doe / 36524 - doe / 146096) / 365;
+        let doy = doe - (365 * yoe + yoe / 4 - yoe / 100);
+        let mp = (5 * doy + 2) / 153;
+        let day = (doy - (153 * mp + 2) / 5 + 1) as u8;
+        let month = if mp < 10 { mp + 3 } else { mp - 9 } as u8;
+        let year = yoe + era * 400 + (month <= 2) as i64;
+        (year, month, day)
+    }
+
+    /// Days since 1970-01-01 of `(year, month, day)`, inverse of [`civil_from_days`].
+    pub(crate) fn days_from_civil(year: i64, month: u8, day: u8) -> i64 {
+        let year = if month <= 2 { year - 1 } else { year };
+        let era = year.div_euclid(400);
+        let yoe = year.rem_euclid(400);
+        let mp = (month as i64 + 9) % 12;
+        let doy = (153 * mp + 2) / 5 + day as i64 - 1;
+        let doe = yoe * 365 + yoe / 4 - yoe / 100 + doy;
+        era * 146097 + doe - 719468
     }
 }
 
@@ -105,6 +160,7 @@ pub mod response {
     use crate::ResponseData;
 
     #[derive(Debug, PartialEq, DekuRead, DekuWrite)]
+    #[cfg_attr(feature = "serde", derive(serde::Serialize))]
     #[deku(type = "u8")]
     pub enum Enum {
         #[deku(id = "0x00")]
@@ -143,78 +199,89 @@ pub mod response {
     }
 
     #[derive(Debug, PartialEq, Eq, DekuRead, DekuWrite, Response)]
+    #[cfg_attr(feature = "serde", derive(serde::Serialize))]
     #[deku(endian = "little")]
     pub struct SetMode {
-        pub(crate) ret_code: u8,
+        pub ret_code: u8,
     }
 
     #[derive(Debug, PartialEq, DekuRead, DekuWrite, Response)]
+    #[cfg_attr(feature = "serde", derive(serde::Serialize))]
     #[deku(endian = "little")]
     pub struct WriteLiDARExtrinsicParameters {
-        ret_code: u8,
+        pub ret_code: u8,
     }
 
     #[derive(Debug, PartialEq, DekuRead, DekuWrite, Response)]
+    #[cfg_attr(feature = "serde", derive(serde::Serialize))]
     #[deku(endian = "little")]
     pub struct ReadLiDARExtrinsicParameters {
-        ret_code: u8,
-        roll: f32,
-        pitch: f32,
-        yaw: f32,
-        x: i32,
-        y: i32,
-        z: i32,
+        pub ret_code: u8,
+        pub roll: f32,
+        pub pitch: f32,
+        pub yaw: f32,
+        pub x: i32,
+        pub y: i32,
+        pub z: i32,
     }
 
     #[derive(Debug, PartialEq, Eq, DekuRead, DekuWrite, Response)]
+    #[cfg_attr(feature = "serde", derive(serde::Serialize))]
     #[deku(endian = "little")]
     pub struct TurnOnOffRainFogSuppression {
-        ret_code: u8,
+        pub ret_code: u8,
     }
 
     #[derive(Debug, PartialEq, Eq, DekuRead, DekuWrite, Response)]
+    #[cfg_attr(feature = "serde", derive(serde::Serialize))]
     #[deku(endian = "little")]
     pub struct SetTurnOnOffFan {
-        ret_code: u8,
+        pub ret_code: u8,
     }
 
     #[derive(Debug, PartialEq, Eq, DekuRead, DekuWrite, Response)]
+    #[cfg_attr(feature = "serde", derive(serde::Serialize))]
     #[deku(endian = "little")]
     pub struct GetTurnOnOffFanState {
-        ret_code: u8,
-        state: u8,
+        pub ret_code: u8,
+        pub state: u8,
     }
 
     #[derive(Debug, PartialEq, Eq, DekuRead, DekuWrite, Response)]
+    #[cfg_attr(feature = "serde", derive(serde::Serialize))]
     #[deku(endian = "little")]
     pub struct SetLiDARReturnMode {
-        ret_code: u8,
+        pub ret_code: u8,
     }
 
     #[derive(Debug, PartialEq, Eq, DekuRead, DekuWrite, Response)]
+    #[cfg_attr(feature = "serde", derive(serde::Serialize))]
     #[deku(endian = "little")]
     pub struct GetLiDARReturnMode {
-        ret_code: u32,
-        mode: u8,
+        pub ret_code: u8,
+        pub mode: u8,
     }
 
     #[derive(Debug, PartialEq, Eq, DekuRead, DekuWrite, Response)]
+    #[cfg_attr(feature = "serde", derive(serde::Serialize))]
     #[deku(endian = "little")]
     pub struct SetIMUDataPushFrequency {
-        ret_code: u8,
+        pub ret_code: u8,
     }
 
     #[derive(Debug, PartialEq, Eq, DekuRead, DekuWrite, Response)]
+    #[cfg_attr(feature = "serde", derive(serde::Serialize))]
     #[deku(endian = "little")]
     pub struct GetIMUDataPushFrequency {
-        ret_code: u8,
-        frequency: u8,
+        pub ret_code: u8,
+        pub frequency: u8,
     }
 
     #[derive(Debug, PartialEq, Eq, DekuRead, DekuWrite, Response)]
+    #[cfg_attr(feature = "serde", derive(serde::Serialize))]
     #[deku(endian = "little")]
     pub struct UpdateUTCSynchronizeTime {
-        ret_code: u8,
+        pub ret_code: u8,
     }
 }
 
@@ -244,6 +311,22 @@ mod test {
         assert_eq!(data, data_out);
     }
 
+    #[test]
+    fn test_utc_time() {
+        use std::time::{Duration, UNIX_EPOCH};
+        use super::request::UpdateUTCSynchronizeTime;
+
+        // 2022-07-23T13:45:06.789012Z
+        let time = UNIX_EPOCH + Duration::from_micros(1_658_583_906_789_012);
+        assert_eq!(UpdateUTCSynchronizeTime::from_system_time(time), UpdateUTCSynchronizeTime {
+            year: 22,
+            month: 7,
+            day: 23,
+            hour: 13,
+            microsecond: (45 * 60 + 6) * 1_000_000 + 789_012,
+        });
+    }
+
     #[test]
     fn test_response() {
         use super::response::*;
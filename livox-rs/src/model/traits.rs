@@ -6,5 +6,8 @@ pub trait Request/*<'a, 'b>: DekuRead<'a> + DekuWrite*/ {
 
 pub trait Response/*: DekuRead<'_> + DekuWrite*/ {
     type Enum;
+
+    /// Return code of the acknowledge, `0` for success.
+    fn ret_code(&self) -> u8;
 }
 pub trait Message/*: DekuRead<'_> + DekuWrite*/ {}
\ No newline at end of file
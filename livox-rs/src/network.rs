@@ -0,0 +1,180 @@
+use std::net::Ipv4Addr;
+use std::time::Duration;
+use tokio::time::{sleep, timeout};
+use tracing::{info, instrument, warn};
+
+use crate::{HandshakeOption, Livox, LivoxClient, LivoxError, LivoxResult};
+use crate::model::deku_data_type::general;
+
+/// IP configuration of a device, see [`LivoxClient::reconfigure_network`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum IpConfig {
+    Dhcp,
+    Static {
+        ip: Ipv4Addr,
+        netmask: Ipv4Addr,
+        /// `0.0.0.0` for no gateway.
+        gateway: Ipv4Addr,
+    },
+}
+
+impl IpConfig {
+    /// Check that a static address is usable, fails with [`LivoxError::InvalidIpConfig`].
+    pub fn validate(&self) -> LivoxResult<()> {
+        use LivoxError::InvalidIpConfig;
+
+        let (ip, netmask, gateway) = match *self {
+            IpConfig::Dhcp => return Ok(()),
+            IpConfig::Static { ip, netmask, gateway } => (u32::from(ip), u32::from(netmask), u32::from(gateway)),
+        };
+        if netmask == 0 || netmask.leading_ones() + netmask.trailing_zeros() != 32 {
+            return Err(InvalidIpConfig("netmask must be a contiguous prefix"));
+        }
+        let addr = Ipv4Addr::from(ip);
+        if addr.is_unspecified() || addr.is_loopback() || addr.is_multicast() || addr.is_broadcast() {
+            return Err(InvalidIpConfig("not a unicast address"));
+        }
+        if netmask != u32::MAX && (ip & !netmask == 0 || ip & !netmask == !netmask) {
+            return Err(InvalidIpConfig("address is the network or broadcast address of its subnet"));
+        }
+        if gateway != 0 && (gateway & netmask != ip & netmask || gateway == ip) {
+            return Err(InvalidIpConfig("gateway must be another address in the same subnet"));
+        }
+        Ok(())
+    }
+
+    fn to_request(self) -> general::request::ConfigureStaticDynamicIP {
+        match self {
+            IpConfig::Dhcp => general::request::ConfigureStaticDynamicIP {
+                ip_mode: 0,
+                ip_addr: [0; 4],
+                net_mask: [0; 4],
+                gw_addr: [0; 4],
+            },
+            IpConfig::Static { ip, netmask, gateway } => general::request::ConfigureStaticDynamicIP {
+                ip_mode: 1,
+                ip_addr: ip.octets(),
+                net_mask: netmask.octets(),
+                gw_addr: gateway.octets(),
+            },
+        }
+    }
+}
+
+impl From<general::response::GetDeviceIPInformation> for IpConfig {
+    fn from(ack: general::response::GetDeviceIPInformation) -> Self {
+        match ack.ip_mode {
+            0 => IpConfig::Dhcp,
+            _ => IpConfig::Static {
+                ip: ack.ip_addr.into(),
+                netmask: ack.net_mask.into(),
+                gateway: ack.gw_addr.into(),
+            },
+        }
+    }
+}
+
+impl LivoxClient {
+    /// Time to wait for the device to come back after a reboot.
+    pub const REBOOT_TIMEOUT: Duration = Duration::from_secs(60);
+    /// Broadcasts sent right after the reboot command may still come from the old address.
+    const REBOOT_GRACE: Duration = Duration::from_secs(3);
+
+    /// Current IP configuration of the device.
+    pub async fn ip_config(&self) -> LivoxResult<IpConfig> {
+        self.request(general::request::GetDeviceIPInformation {}).await.map(IpConfig::from)
+    }
+
+    /// Validate and send `config`, which the device takes at its next reboot.
+    pub async fn set_ip_config(&self, config: IpConfig) -> LivoxResult<()> {
+        config.validate()?;
+        self.request(config.to_request()).await.map(|_| ())
+    }
+
+    /// Apply `config`, reboot the device, wait for its broadcast on the new address and handshake again.
+    ///
+    /// If `config` is invalid or the device does not take it, [`LivoxError::NotReconfigured`] hands the client back.
+    /// If the reboot command fails, the device does not come back or the handshake fails,
+    /// it is configured back to DHCP (if it can still be reached) and [`LivoxError::ReconfigureFailed`] is returned.
+    #[instrument(skip(self, option), fields(lidar = % self.lidar.lidar_addr))]
+    pub async fn reconfigure_network(mut self, config: IpConfig, option: HandshakeOption) -> LivoxResult<LivoxClient> {
+        if let Err(reason) = self.set_ip_config(config).await {
+            return Err(LivoxError::NotReconfigured { client: Box::new(self), reason: Box::new(reason) });
+        }
+        let code = self.lidar.broadcast_code;
+
+        let rebooted = self.request(general::request::RebootDevice { timeout: 0 }).await;
+        self.stop_tasks().await;
+        drop(self);
+        if let Err(error) = rebooted {
+            // the configuration may be applied at the next boot anyway
+            warn!("Reboot failed: {:?}, rolling back to DHCP", error);
+            return Err(Self::rollback(code, option, error).await);
+        }
+        info!("Configured {:?}, rebooting", config);
+        sleep(Self::REBOOT_GRACE).await;
+
+        let reconnect = async {
+            let livox = Livox::wait_for(|livox| livox.broadcast_code == code && match config {
+                IpConfig::Static { ip, .. } => livox.lidar_addr.ip() == ip,
+                IpConfig::Dhcp => true,
+            }).await?;
+            livox.handshake(option.clone()).await
+        };
+        let error = match timeout(Self::REBOOT_TIMEOUT, reconnect).await {
+            Ok(Ok(client)) => return Ok(client),
+            Ok(Err(err)) => err,
+            Err(_) => LivoxError::RebootTimeout,
+        };
+
+        warn!("Device did not come back: {:?}, rolling back to DHCP", error);
+        Err(Self::rollback(code, option, error).await)
+    }
+
+    /// Configure the device back to DHCP if it can be reached, returning the error to report for `reason`.
+    async fn rollback(code: [u8; 16], option: HandshakeOption, reason: LivoxError) -> LivoxError {
+        let rollback = async {
+            let livox = Livox::wait_for(|livox| livox.broadcast_code == code).await?;
+            let mut client = livox.handshake(option).await?;
+            client.request(IpConfig::Dhcp.to_request()).await?;
+            client.request(general::request::RebootDevice { timeout: 0 }).await?;
+            client.stop_tasks().await;
+            Ok::<_, LivoxError>(())
+        };
+        let rolled_back = match timeout(Self::REBOOT_TIMEOUT, rollback).await {
+            Ok(Ok(())) => true,
+            Ok(Err(err)) => {
+                warn!("Rollback failed: {:?}", err);
+                false
+            }
+            Err(_) => {
+                warn!("Rollback failed: {:?}", LivoxError::RebootTimeout);
+                false
+            }
+        };
+        LivoxError::ReconfigureFailed { rolled_back, reason: Box::new(reason) }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn test_validate() {
+        let config = |ip: [u8; 4], netmask: [u8; 4], gateway: [u8; 4]| IpConfig::Static {
+            ip: ip.into(),
+            netmask: netmask.into(),
+            gateway: gateway.into(),
+        };
+        assert!(IpConfig::Dhcp.validate().is_ok());
+        assert!(config([192, 168, 1, 3], [255, 255, 255, 0], [192, 168, 1, 1]).validate().is_ok());
+        assert!(config([192, 168, 1, 3], [255, 255, 255, 0], [0, 0, 0, 0]).validate().is_ok());
+        assert!(config([192, 168, 1, 3], [255, 0, 255, 0], [0, 0, 0, 0]).validate().is_err());
+        assert!(config([192, 168, 1, 255], [255, 255, 255, 0], [0, 0, 0, 0]).validate().is_err());
+        assert!(config([192, 168, 1, 0], [255, 255, 255, 0], [0, 0, 0, 0]).validate().is_err());
+        assert!(config([224, 0, 0, 1], [255, 255, 255, 0], [0, 0, 0, 0]).validate().is_err());
+        assert!(config([192, 168, 1, 3], [255, 255, 255, 0], [192, 168, 2, 1]).validate().is_err());
+        assert!(config([192, 168, 1, 3], [255, 255, 255, 0], [192, 168, 1, 3]).validate().is_err());
+    }
+}
@@ -0,0 +1,273 @@
+use nalgebra::{Matrix3xX, Matrix4xX, SMatrix, Vector4};
+
+use crate::model::PointCloudFrame;
+use crate::model::data_type::{LiDARStatusCode, LivoxPoint};
+
+/// Header of the packet a [`PointBatch`] comes from.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub struct PacketInfo {
+    pub version: u8,
+    pub slot_id: u8,
+    pub lidar_id: u8,
+    /// Raw [`LiDARStatusCode`].
+    pub status_code: u32,
+    pub timestamp_type: u8,
+    /// Sensor timestamp of the first point, in nanoseconds.
+    pub timestamp: u64,
+    /// See [`PointCloudFrame::host_timestamp`].
+    pub host_timestamp: Option<u64>,
+    pub data_type: u8,
+}
+
+impl PacketInfo {
+    pub fn status(&self) -> LiDARStatusCode {
+        LiDARStatusCode::from(self.status_code)
+    }
+
+    /// Whether the sensor clock is synchronised, by PTP, GPS or PPS, rather than counting from power on.
+    pub fn is_synchronised(&self) -> bool {
+        self.timestamp_type != 0
+    }
+
+    /// Time of the packet in nanoseconds: the sensor timestamp of a synchronised LiDAR, so LiDARs
+    /// synchronised to the same clock line up on it, else the sensor timestamp mapped to host time
+    /// when known, so sensors need not share a clock, else the sensor timestamp.
+    pub fn time(&self) -> u64 {
+        match self.host_timestamp {
+            Some(host) if !self.is_synchronised() => host,
+            _ => self.timestamp,
+        }
+    }
+}
+
+/// Points of a packet as a struct of arrays, in Cartesian coordinates whatever the data type.
+#[derive(Debug, Clone, PartialEq, Default)]
+pub struct PointBatch {
+    pub packet: PacketInfo,
+    /// Coordinates in metres.
+    pub x: Vec<f32>,
+    pub y: Vec<f32>,
+    pub z: Vec<f32>,
+    pub reflectivity: Vec<u8>,
+    /// Raw [`TagInfo`](crate::model::data_type::TagInfo) bytes.
+    pub tag: Vec<u8>,
+    /// Sensor timestamps, in nanoseconds.
+    pub timestamp: Vec<u64>,
+    /// Return index of each point, `0` for single return data.
+    pub return_index: Vec<u8>,
+}
+
+/// A point of a [`PointBatch`].
+#[derive(Debug, Clone, Copy, PartialEq, Default)]
+pub struct BatchPoint {
+    pub point: LivoxPoint,
+    pub timestamp: u64,
+    pub return_index: u8,
+}
+
+impl PointBatch {
+    pub fn from_frame(frame: &PointCloudFrame) -> Self {
+        let points = frame.data.points();
+        let mut batch = PointBatch {
+            packet: PacketInfo {
+                version: frame.version,
+                slot_id: frame.slot_id,
+                lidar_id: frame.lidar_id,
+                status_code: (&frame.status_code).into(),
+                timestamp_type: frame.timestamp_type,
+                timestamp: frame.timestamp,
+                host_timestamp: frame.host_timestamp,
+                data_type: frame.data.data_type(),
+            },
+            ..Self::with_capacity(points.len())
+        };
+        for (i, p) in points.iter().enumerate() {
+            batch.push(BatchPoint { point: *p, timestamp: frame.point_timestamp(i), return_index: frame.return_index(i) });
+        }
+        batch
+    }
+
+    fn with_capacity(capacity: usize) -> Self {
+        PointBatch {
+            packet: PacketInfo::default(),
+            x: Vec::with_capacity(capacity),
+            y: Vec::with_capacity(capacity),
+            z: Vec::with_capacity(capacity),
+            reflectivity: Vec::with_capacity(capacity),
+            tag: Vec::with_capacity(capacity),
+            timestamp: Vec::with_capacity(capacity),
+            return_index: Vec::with_capacity(capacity),
+        }
+    }
+
+    pub fn push(&mut self, p: BatchPoint) {
+        self.x.push(p.point.x);
+        self.y.push(p.point.y);
+        self.z.push(p.point.z);
+        self.reflectivity.push(p.point.reflectivity);
+        self.tag.push(p.point.tag);
+        self.timestamp.push(p.timestamp);
+        self.return_index.push(p.return_index);
+    }
+
+    pub fn len(&self) -> usize {
+        self.x.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.x.is_empty()
+    }
+
+    pub fn get(&self, i: usize) -> Option<BatchPoint> {
+        (i < self.len()).then(|| BatchPoint {
+            point: LivoxPoint {
+                x: self.x[i],
+                y: self.y[i],
+                z: self.z[i],
+                reflectivity: self.reflectivity[i],
+                tag: self.tag[i],
+            },
+            timestamp: self.timestamp[i],
+            return_index: self.return_index[i],
+        })
+    }
+
+    pub fn iter(&self) -> impl Iterator<Item=BatchPoint> + '_ {
+        (0..self.len()).map(|i| self.get(i).unwrap())
+    }
+
+    /// Host system time of point `i`, in nanoseconds since UNIX epoch,
+    /// `None` if [`PacketInfo::host_timestamp`] is not known.
+    pub fn host_time(&self, i: usize) -> Option<u64> {
+        let offset = self.timestamp[i] as i64 - self.packet.timestamp as i64;
+        self.packet.host_timestamp.map(|host| (host as i64 + offset) as u64)
+    }
+
+    /// Keep the points for which `keep` returns true.
+    pub fn retain(&mut self, mut keep: impl FnMut(&BatchPoint) -> bool) {
+        let kept = self.iter().filter(|p| keep(p)).collect::<Vec<_>>();
+        let packet = self.packet;
+        *self = Self::with_capacity(kept.len());
+        self.packet = packet;
+        kept.into_iter().for_each(|p| self.push(p));
+    }
+
+    /// Coordinates as columns, in metres.
+    pub fn to_matrix(&self) -> Matrix3xX<f32> {
+        Matrix3xX::from_fn(self.len(), |r, c| [&self.x, &self.y, &self.z][r][c])
+    }
+
+    /// Homogeneous coordinates as columns, in metres.
+    pub fn to_homogeneous(&self) -> Matrix4xX<f32> {
+        Matrix4xX::from_fn(self.len(), |r, c| if r == 3 { 1.0 } else { [&self.x, &self.y, &self.z][r][c] })
+    }
+
+    /// Homogeneous coordinates in millimetres as fixed size columns,
+    /// like [`LivoxClient::homogeneous_matrix_stream`](crate::LivoxClient::homogeneous_matrix_stream) yields.
+    /// `None` if the batch does not have `C` points.
+    pub fn to_homogeneous_mm<const C: usize>(&self) -> Option<SMatrix<f32, 4, C>> {
+        (self.len() == C).then(|| SMatrix::from_columns(&self.iter()
+            .map(|p| Vector4::new(p.point.x * 1000.0, p.point.y * 1000.0, p.point.z * 1000.0, 1.0))
+            .collect::<Vec<_>>()))
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use byte_struct::ByteStructLen;
+    use crate::model::data_type::DT2;
+    use crate::model::ParseError::{InvalidData, InvalidLength, WrongPointCloudSize};
+    use super::*;
+
+    fn dt2_frame() -> Vec<u8> {
+        let mut frame = vec![5, 0, 1, 0, 0, 0, 0, 0, 1, 0x02];
+        frame.extend_from_slice(&1_000_000u64.to_le_bytes());
+        for i in 0..96i32 {
+            frame.extend_from_slice(&(i * 10).to_le_bytes());
+            frame.extend_from_slice(&(-i).to_le_bytes());
+            frame.extend_from_slice(&1500i32.to_le_bytes());
+            frame.extend_from_slice(&[i as u8, 0b0001_0000]);
+        }
+        assert_eq!(frame.len(), PointCloudFrame::HEADER_LEN + 96 * DT2::BYTE_LEN);
+        frame
+    }
+
+    #[test]
+    fn test_batch() {
+        let frame = dt2_frame();
+        let batch = PointBatch::from_frame(&PointCloudFrame::parse(&frame).unwrap());
+        assert_eq!(batch.len(), 96);
+        assert_eq!(batch.packet.data_type, 0x02);
+        let p = batch.get(3).unwrap();
+        assert_eq!((p.point.x, p.point.y, p.point.z), (0.03, -0.003, 1.5));
+        assert_eq!((p.point.reflectivity, p.point.tag), (3, 0b0001_0000));
+        assert_eq!(p.timestamp, 1_000_000 + 3 * PointCloudFrame::POINT_INTERVAL_NS);
+        assert_eq!((batch.host_time(3), batch.packet.time()), (None, 1_000_000));
+        let mut aligned = batch.clone();
+        aligned.packet.host_timestamp = Some(5_000_000);
+        // the frame is PTP synchronised
+        assert_eq!(aligned.packet.time(), 1_000_000);
+        aligned.packet.timestamp_type = 0;
+        assert_eq!(aligned.packet.time(), 5_000_000);
+        assert_eq!(aligned.host_time(3), Some(5_000_000 + 3 * PointCloudFrame::POINT_INTERVAL_NS));
+
+        let matrix = batch.to_homogeneous_mm::<96>().unwrap();
+        assert!((matrix - PointCloudFrame::parse_homogeneous_matrix(&frame).unwrap()).norm() < 1e-3);
+        assert!(batch.to_homogeneous_mm::<100>().is_none());
+        assert_eq!(PointCloudFrame::parse_homogeneous_matrix(&frame[..9]), Err(InvalidLength));
+        assert_eq!(PointCloudFrame::parse_homogeneous_matrix(&frame[..frame.len() - DT2::BYTE_LEN]), Err(WrongPointCloudSize));
+
+        let mut batch = batch;
+        batch.retain(|p| p.point.reflectivity % 2 == 0);
+        assert_eq!(batch.len(), 48);
+        assert_eq!(batch.to_matrix().ncols(), 48);
+    }
+
+    #[test]
+    fn test_data_types() {
+        let header = |data_type: u8| {
+            let mut frame = vec![5, 0, 1, 0, 0, 0, 0, 0, 1, data_type];
+            frame.extend_from_slice(&1_000_000u64.to_le_bytes());
+            frame
+        };
+
+        // data type 0, 100 points without tag
+        let mut frame = header(0x00);
+        for i in 0..100i32 {
+            frame.extend_from_slice(&i.to_le_bytes());
+            frame.extend_from_slice(&0i32.to_le_bytes());
+            frame.extend_from_slice(&2000i32.to_le_bytes());
+            frame.push(i as u8);
+        }
+        let batch = PointBatch::from_frame(&PointCloudFrame::parse(&frame).unwrap());
+        assert_eq!((batch.len(), batch.packet.data_type), (100, 0x00));
+        let p = batch.get(99).unwrap();
+        assert_eq!((p.point.x, p.point.z, p.point.reflectivity, p.point.tag), (0.099, 2.0, 99, 0));
+        assert_eq!((p.timestamp, p.return_index), (1_000_000 + 99 * PointCloudFrame::POINT_INTERVAL_NS, 0));
+
+        // data type 5, 2 shots of 2 returns sharing their angles
+        let mut frame = header(0x05);
+        for shot in 0..2u32 {
+            frame.extend_from_slice(&0u16.to_le_bytes());
+            frame.extend_from_slice(&0u16.to_le_bytes());
+            for ret in 0..2u32 {
+                frame.extend_from_slice(&(1000 + 1000 * ret + shot).to_le_bytes());
+                frame.extend_from_slice(&[(10 * shot + ret) as u8, 0b0001_0000]);
+            }
+        }
+        let parsed = PointCloudFrame::parse(&frame).unwrap();
+        assert_eq!((parsed.data.len(), parsed.data.returns()), (4, 2));
+        let batch = PointBatch::from_frame(&parsed);
+        let p = batch.get(3).unwrap();
+        assert_eq!((p.point.reflectivity, p.point.tag, p.return_index), (11, 0b0001_0000, 1));
+        assert!((p.point.z - 2.001).abs() < 1e-6);
+        assert_eq!(p.timestamp, 1_000_000 + PointCloudFrame::POINT_INTERVAL_NS);
+        assert_eq!(batch.get(2).unwrap().timestamp, p.timestamp);
+        assert_eq!(batch.get(2).unwrap().return_index, 0);
+
+        // a partial shot and an unsupported data type are rejected
+        frame.pop();
+        assert_eq!(PointCloudFrame::parse(&frame), Err(WrongPointCloudSize));
+        assert_eq!(PointCloudFrame::parse(&header(0x06)), Err(InvalidData));
+    }
+}
@@ -0,0 +1,269 @@
+use nalgebra::{Isometry3, Matrix3, Matrix3x4, Point2, Point3};
+
+use crate::point::PointBatch;
+
+pub mod colour;
+
+/// Lens distortion, with OpenCV's coefficients.
+#[derive(Debug, Clone, Copy, PartialEq, Default)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+#[cfg_attr(feature = "serde", serde(rename_all = "snake_case"))]
+pub enum Distortion {
+    #[default]
+    None,
+    /// Brown-Conrady model of `cv::projectPoints`.
+    RadialTangential { k1: f32, k2: f32, p1: f32, p2: f32, k3: f32 },
+    /// Kannala-Brandt model of `cv::fisheye::projectPoints`.
+    Fisheye { k1: f32, k2: f32, k3: f32, k4: f32 },
+}
+
+impl Distortion {
+    /// Distort normalised image coordinates `(x / z, y / z)`.
+    pub fn distort(&self, p: Point2<f32>) -> Point2<f32> {
+        match *self {
+            Distortion::None => p,
+            Distortion::RadialTangential { k1, k2, p1, p2, k3 } => {
+                let (x, y) = (p.x, p.y);
+                let r2 = x * x + y * y;
+                let radial = 1.0 + r2 * (k1 + r2 * (k2 + r2 * k3));
+                Point2::new(
+                    x * radial + 2.0 * p1 * x * y + p2 * (r2 + 2.0 * x * x),
+                    y * radial + p1 * (r2 + 2.0 * y * y) + 2.0 * p2 * x * y,
+                )
+            }
+            Distortion::Fisheye { k1, k2, k3, k4 } => {
+                let r = p.coords.norm();
+                if r < 1e-8 { return p; }
+                let theta = r.atan();
+                let t2 = theta * theta;
+                let theta_d = theta * (1.0 + t2 * (k1 + t2 * (k2 + t2 * (k3 + t2 * k4))));
+                p * (theta_d / r)
+            }
+        }
+    }
+}
+
+/// Camera intrinsics, in pixels.
+#[derive(Debug, Clone, Copy, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct Intrinsics {
+    pub fx: f32,
+    pub fy: f32,
+    pub cx: f32,
+    pub cy: f32,
+    pub width: u32,
+    pub height: u32,
+    #[cfg_attr(feature = "serde", serde(default))]
+    pub distortion: Distortion,
+}
+
+impl Intrinsics {
+    /// Camera matrix `K`.
+    pub fn matrix(&self) -> Matrix3<f32> {
+        Matrix3::new(self.fx, 0.0, self.cx,
+                     0.0, self.fy, self.cy,
+                     0.0, 0.0, 1.0)
+    }
+
+    /// Intrinsics of the same camera for images resized to `width` x `height`.
+    pub fn resized(&self, width: u32, height: u32) -> Self {
+        let sx = width as f32 / self.width as f32;
+        let sy = height as f32 / self.height as f32;
+        Intrinsics {
+            fx: self.fx * sx,
+            fy: self.fy * sy,
+            cx: self.cx * sx,
+            cy: self.cy * sy,
+            width,
+            height,
+            distortion: self.distortion,
+        }
+    }
+}
+
+/// A point projected on the image.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Projection {
+    /// Pixel coordinates, `(0, 0)` being the top left corner of the top left pixel.
+    pub pixel: Point2<f32>,
+    /// Distance along the optical axis, in metres.
+    pub depth: f32,
+}
+
+impl Projection {
+    /// Index of the pixel the point falls in.
+    pub fn pixel_index(&self) -> (u32, u32) {
+        (self.pixel.x as u32, self.pixel.y as u32)
+    }
+}
+
+/// Pinhole camera looking at LiDAR points.
+#[derive(Debug, Clone, Copy, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct Camera {
+    pub intrinsics: Intrinsics,
+    /// Transform from the LiDAR frame to the camera frame (x right, y down, z forward), in metres.
+    pub extrinsics: Isometry3<f32>,
+    /// Points closer than this to the camera plane are not visible, in metres.
+    #[cfg_attr(feature = "serde", serde(default = "Camera::default_near"))]
+    pub near: f32,
+}
+
+impl Camera {
+    fn default_near() -> f32 {
+        0.1
+    }
+
+    pub fn new(intrinsics: Intrinsics, extrinsics: Isometry3<f32>) -> Self {
+        Camera { intrinsics, extrinsics, near: Self::default_near() }
+    }
+
+    pub fn width(&self) -> u32 {
+        self.intrinsics.width
+    }
+
+    pub fn height(&self) -> u32 {
+        self.intrinsics.height
+    }
+
+    /// `K * [R | t]`, mapping homogeneous LiDAR points to homogeneous pixels, ignoring distortion.
+    pub fn projection_matrix(&self) -> Matrix3x4<f32> {
+        self.intrinsics.matrix() * self.extrinsics.to_homogeneous().fixed_rows::<3>(0)
+    }
+
+    /// Project a point in the LiDAR frame, in metres.
+    /// `None` if it is behind the near plane or outside of the image.
+    pub fn project(&self, p: &Point3<f32>) -> Option<Projection> {
+        let p = self.extrinsics.transform_point(p);
+        if p.z < self.near { return None; }
+        let distorted = self.intrinsics.distortion.distort(Point2::new(p.x / p.z, p.y / p.z));
+        let pixel = Point2::new(
+            self.intrinsics.fx * distorted.x + self.intrinsics.cx,
+            self.intrinsics.fy * distorted.y + self.intrinsics.cy,
+        );
+        let visible = (0.0..self.intrinsics.width as f32).contains(&pixel.x)
+            && (0.0..self.intrinsics.height as f32).contains(&pixel.y);
+        visible.then_some(Projection { pixel, depth: p.z })
+    }
+
+    /// Visible points of a batch, with their index in the batch.
+    pub fn project_batch<'a>(&'a self, batch: &'a PointBatch) -> impl Iterator<Item=(usize, Projection)> + 'a {
+        (0..batch.len()).filter_map(|i| {
+            self.project(&Point3::new(batch.x[i], batch.y[i], batch.z[i])).map(|p| (i, p))
+        })
+    }
+
+    /// An empty depth image of the size of the camera image.
+    pub fn depth_image(&self) -> DepthImage {
+        DepthImage::new(self.intrinsics.width, self.intrinsics.height)
+    }
+}
+
+/// Depth in metres of the nearest point falling in every pixel, `0` where there is none.
+#[derive(Debug, Clone, PartialEq)]
+pub struct DepthImage {
+    pub width: u32,
+    pub height: u32,
+    /// Row major.
+    pub depth: Vec<f32>,
+}
+
+impl DepthImage {
+    pub fn new(width: u32, height: u32) -> Self {
+        DepthImage { width, height, depth: vec![0.0; width as usize * height as usize] }
+    }
+
+    /// Depth of a pixel, `None` where there is none or outside of the image.
+    pub fn get(&self, x: u32, y: u32) -> Option<f32> {
+        if x >= self.width || y >= self.height { return None; }
+        let depth = self.depth[(y * self.width + x) as usize];
+        (depth > 0.0).then_some(depth)
+    }
+
+    /// Write a projected point if it is nearer than what the pixel holds.
+    pub fn insert(&mut self, projection: &Projection) {
+        let (x, y) = projection.pixel_index();
+        if x >= self.width || y >= self.height { return; }
+        let depth = &mut self.depth[(y * self.width + x) as usize];
+        if *depth == 0.0 || projection.depth < *depth {
+            *depth = projection.depth;
+        }
+    }
+
+    /// Project the visible points of a batch.
+    pub fn insert_batch(&mut self, camera: &Camera, batch: &PointBatch) {
+        camera.project_batch(batch).for_each(|(_, p)| self.insert(&p));
+    }
+
+    pub fn clear(&mut self) {
+        self.depth.fill(0.0);
+    }
+
+    /// Depth in millimetres, saturating at `u16::MAX`, `0` where there is none.
+    pub fn to_millimetres(&self) -> Vec<u16> {
+        self.depth.iter().map(|d| (d * 1000.0).round().min(u16::MAX as f32) as u16).collect()
+    }
+
+    /// Pixels with a depth, as `(x, y, depth)`.
+    pub fn pixels(&self) -> impl Iterator<Item=(u32, u32, f32)> + '_ {
+        self.depth.iter().enumerate()
+            .filter(|(_, d)| **d > 0.0)
+            .map(|(i, d)| (i as u32 % self.width, i as u32 / self.width, *d))
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use nalgebra::{Rotation3, Translation3, UnitQuaternion};
+    use super::*;
+
+    /// Camera looking along +x of the LiDAR, 10cm above it.
+    fn camera(distortion: Distortion) -> Camera {
+        let rotation = Rotation3::from_matrix(&Matrix3::new(0.0, -1.0, 0.0,
+                                                            0.0, 0.0, -1.0,
+                                                            1.0, 0.0, 0.0));
+        let extrinsics = Isometry3::from_parts(Translation3::new(0.0, 0.1, 0.0), UnitQuaternion::from_rotation_matrix(&rotation));
+        Camera::new(Intrinsics { fx: 500.0, fy: 500.0, cx: 320.0, cy: 240.0, width: 640, height: 480, distortion }, extrinsics)
+    }
+
+    #[test]
+    fn test_project() {
+        let camera = camera(Distortion::None);
+        let p = camera.project(&Point3::new(10.0, 1.0, 0.0)).unwrap();
+        assert!((p.pixel - Point2::new(270.0, 245.0)).norm() < 1e-3);
+        assert!((p.depth - 10.0).abs() < 1e-5);
+        // behind the camera, its projection would be in the image
+        assert!(camera.project(&Point3::new(-10.0, 1.0, 0.0)).is_none());
+        assert!(camera.project(&Point3::new(10.0, 20.0, 0.0)).is_none());
+
+        let homogeneous = camera.projection_matrix() * Point3::new(10.0, 1.0, 0.0).to_homogeneous();
+        assert!((homogeneous.xy() / homogeneous.z - p.pixel.coords).norm() < 1e-3);
+
+        // barrel distortion pulls points towards the centre
+        let distorted = self::camera(Distortion::RadialTangential { k1: -0.2, k2: 0.0, p1: 0.0, p2: 0.0, k3: 0.0 })
+            .project(&Point3::new(10.0, 5.0, 0.1)).unwrap();
+        assert!((distorted.pixel.x - (320.0 - 250.0 * (1.0 - 0.2 * 0.25))).abs() < 1e-3);
+        let fisheye = self::camera(Distortion::Fisheye { k1: 0.0, k2: 0.0, k3: 0.0, k4: 0.0 })
+            .project(&Point3::new(10.0, 5.0, 0.1)).unwrap();
+        assert!((fisheye.pixel.x - (320.0 - 500.0 * 0.5f32.atan())).abs() < 1e-3);
+    }
+
+    #[test]
+    fn test_depth_image() {
+        let camera = camera(Distortion::None);
+        let mut image = camera.depth_image();
+        // nearest point wins whatever the order
+        for depth in [10.0, 5.0, 20.0] {
+            image.insert(&Projection { pixel: Point2::new(320.5, 240.5), depth });
+        }
+        image.insert(&Projection { pixel: Point2::new(700.0, 240.0), depth: 1.0 });
+        assert_eq!(image.get(320, 240), Some(5.0));
+        assert_eq!(image.pixels().collect::<Vec<_>>(), vec![(320, 240, 5.0)]);
+        // neither wraps to the next row nor reads past the end
+        image.insert(&Projection { pixel: Point2::new(0.5, 241.5), depth: 2.0 });
+        assert_eq!((image.get(640, 240), image.get(0, 480)), (None, None));
+
+        let batch = PointBatch { x: vec![10.0, -10.0], y: vec![1.0, 1.0], z: vec![0.0, 0.0], ..PointBatch::default() };
+        assert_eq!(camera.project_batch(&batch).map(|(i, _)| i).collect::<Vec<_>>(), vec![0]);
+    }
+}
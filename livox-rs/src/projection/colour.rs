@@ -0,0 +1,235 @@
+use std::collections::VecDeque;
+use std::time::Duration;
+
+use crate::cloud::PointCloud;
+use crate::export::{ColourPoint, ExportPoint};
+use crate::projection::{Camera, DepthImage, Projection};
+
+/// An 8-bit RGB camera frame.
+#[derive(Debug, Clone, PartialEq)]
+pub struct RgbFrame {
+    pub width: u32,
+    pub height: u32,
+    /// Row major, 3 bytes per pixel.
+    pub data: Vec<u8>,
+    /// Capture time in nanoseconds, from the clock of the point timestamps.
+    pub timestamp: u64,
+}
+
+impl RgbFrame {
+    /// `None` if `data` is not `width * height * 3` bytes.
+    pub fn new(width: u32, height: u32, data: Vec<u8>, timestamp: u64) -> Option<Self> {
+        (data.len() == width as usize * height as usize * 3).then_some(RgbFrame { width, height, data, timestamp })
+    }
+
+    pub fn get(&self, x: u32, y: u32) -> Option<[u8; 3]> {
+        if x >= self.width || y >= self.height { return None; }
+        let i = (y * self.width + x) as usize * 3;
+        Some([self.data[i], self.data[i + 1], self.data[i + 2]])
+    }
+}
+
+/// Points hidden from the camera by nearer ones, which the LiDAR sees from its own viewpoint,
+/// would otherwise get the colour of what is in front of them.
+#[derive(Debug, Clone, Copy, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+#[cfg_attr(feature = "serde", serde(default, deny_unknown_fields))]
+pub struct Occlusion {
+    /// Neighbourhood searched for nearer points, in pixels.
+    pub radius: u32,
+    /// A point is hidden if a neighbour is nearer by more than this, in metres.
+    pub tolerance: f32,
+}
+
+impl Default for Occlusion {
+    fn default() -> Self {
+        Occlusion { radius: 2, tolerance: 0.2 }
+    }
+}
+
+/// Colours LiDAR points with the camera frame nearest in time.
+///
+/// Frames and points must be timestamped by the same clock, e.g. host time with
+/// [`PointBatch::host_time`](crate::point::PointBatch::host_time). Frames may be of another
+/// resolution than the calibration, pixels are scaled to them.
+#[derive(Debug, Clone)]
+pub struct Colouriser {
+    camera: Camera,
+    frames: VecDeque<RgbFrame>,
+    capacity: usize,
+    max_offset_ns: u64,
+    occlusion: Option<Occlusion>,
+}
+
+impl Colouriser {
+    /// Keeps 10 frames, matching points up to 50ms away from them, with the default occlusion check.
+    pub fn new(camera: Camera) -> Self {
+        Colouriser {
+            camera,
+            frames: VecDeque::new(),
+            capacity: 10,
+            max_offset_ns: Duration::from_millis(50).as_nanos() as u64,
+            occlusion: Some(Occlusion::default()),
+        }
+    }
+
+    /// Points farther than this from every frame are left out.
+    pub fn with_max_offset(mut self, max_offset: Duration) -> Self {
+        self.max_offset_ns = max_offset.as_nanos() as u64;
+        self
+    }
+
+    /// Number of recent frames kept.
+    pub fn with_capacity(mut self, frames: usize) -> Self {
+        self.capacity = frames.max(1);
+        self
+    }
+
+    /// `None` to colour every point, hidden or not.
+    pub fn with_occlusion(mut self, occlusion: Option<Occlusion>) -> Self {
+        self.occlusion = occlusion;
+        self
+    }
+
+    pub fn camera(&self) -> &Camera {
+        &self.camera
+    }
+
+    /// Add a frame, dropping the oldest one when full.
+    pub fn push_frame(&mut self, frame: RgbFrame) {
+        let at = self.frames.partition_point(|f| f.timestamp <= frame.timestamp);
+        self.frames.insert(at, frame);
+        while self.frames.len() > self.capacity {
+            self.frames.pop_front();
+        }
+    }
+
+    fn nearest(&self, timestamp: u64) -> Option<usize> {
+        let at = self.frames.partition_point(|f| f.timestamp < timestamp);
+        [at.checked_sub(1), (at < self.frames.len()).then_some(at)].into_iter().flatten()
+            .min_by_key(|i| self.frames[*i].timestamp.abs_diff(timestamp))
+            .filter(|i| self.frames[*i].timestamp.abs_diff(timestamp) <= self.max_offset_ns)
+    }
+
+    /// Frame nearest to `timestamp`, within the maximum offset.
+    pub fn frame_at(&self, timestamp: u64) -> Option<&RgbFrame> {
+        self.nearest(timestamp).map(|i| &self.frames[i])
+    }
+
+    /// Coloured points of a cloud, leaving out those outside of the image, hidden,
+    /// or without a frame near enough in time.
+    pub fn colourise(&self, cloud: &PointCloud) -> Vec<ColourPoint> {
+        let mut groups = vec![Vec::new(); self.frames.len()];
+        for (i, p) in cloud.points.iter().enumerate() {
+            let Some(frame) = self.nearest(p.timestamp) else { continue; };
+            if let Some(projection) = self.camera.project(&p.point.to_point()) {
+                groups[frame].push((i, projection));
+            }
+        }
+
+        let mut coloured = Vec::new();
+        // one buffer for every frame, only the pixels of the previous frame are reset
+        let mut depth = None;
+        for (frame, projections) in self.frames.iter().zip(groups) {
+            if projections.is_empty() { continue; }
+            if self.occlusion.is_some() {
+                let depth = depth.get_or_insert_with(|| self.camera.depth_image());
+                projections.iter().for_each(|(_, p)| depth.insert(p));
+            }
+            let scale = (frame.width as f32 / self.camera.width() as f32, frame.height as f32 / self.camera.height() as f32);
+            for (i, projection) in &projections {
+                if let (Some(occlusion), Some(depth)) = (&self.occlusion, &depth) {
+                    if hidden(depth, projection, occlusion) { continue; }
+                }
+                let (x, y) = ((projection.pixel.x * scale.0) as u32, (projection.pixel.y * scale.1) as u32);
+                let Some(rgb) = frame.get(x, y) else { continue; };
+                let p = &cloud.points[*i];
+                coloured.push(ColourPoint {
+                    point: ExportPoint {
+                        x: p.point.x,
+                        y: p.point.y,
+                        z: p.point.z,
+                        reflectivity: p.point.reflectivity,
+                        tag: p.point.tag,
+                        timestamp: p.timestamp,
+                    },
+                    rgb,
+                });
+            }
+            if let Some(depth) = &mut depth {
+                for (_, projection) in &projections {
+                    let (x, y) = projection.pixel_index();
+                    if x < depth.width && y < depth.height {
+                        depth.depth[(y * depth.width + x) as usize] = 0.0;
+                    }
+                }
+            }
+        }
+        coloured
+    }
+}
+
+/// Whether a point of the neighbourhood is nearer than `projection` by more than the tolerance.
+fn hidden(depth: &DepthImage, projection: &Projection, occlusion: &Occlusion) -> bool {
+    let (x, y) = projection.pixel_index();
+    let r = occlusion.radius;
+    (y.saturating_sub(r)..=(y + r).min(depth.height - 1))
+        .flat_map(|y| (x.saturating_sub(r)..=(x + r).min(depth.width - 1)).map(move |x| (x, y)))
+        .filter_map(|(x, y)| depth.get(x, y))
+        .any(|d| d < projection.depth - occlusion.tolerance)
+}
+
+#[cfg(test)]
+mod test {
+    use nalgebra::{Isometry3, Matrix3, Rotation3, Translation3, UnitQuaternion};
+    use crate::model::data_type::LivoxPoint;
+    use crate::point::BatchPoint;
+    use crate::projection::{Distortion, Intrinsics};
+    use super::*;
+
+    const MS: u64 = 1_000_000;
+
+    #[test]
+    fn test_colourise() {
+        // looking along +x of the LiDAR, 10cm above it
+        let rotation = Rotation3::from_matrix(&Matrix3::new(0.0, -1.0, 0.0,
+                                                            0.0, 0.0, -1.0,
+                                                            1.0, 0.0, 0.0));
+        let extrinsics = Isometry3::from_parts(Translation3::new(0.0, 0.1, 0.0), UnitQuaternion::from_rotation_matrix(&rotation));
+        let camera = Camera::new(Intrinsics { fx: 500.0, fy: 500.0, cx: 320.0, cy: 240.0, width: 640, height: 480, distortion: Distortion::None }, extrinsics);
+
+        let mut colouriser = Colouriser::new(camera);
+        let frame = |rgb: [u8; 3], width: u32, height: u32, timestamp: u64| {
+            RgbFrame::new(width, height, rgb.repeat((width * height) as usize), timestamp).unwrap()
+        };
+        // out of order, and half the resolution of the calibration
+        colouriser.push_frame(frame([0, 255, 0], 320, 240, 100 * MS));
+        colouriser.push_frame(frame([255, 0, 0], 640, 480, 0));
+        assert_eq!(colouriser.frame_at(60 * MS).unwrap().timestamp, 100 * MS);
+        assert!(colouriser.frame_at(200 * MS).is_none());
+
+        let point = |x: f32, y: f32, z: f32, timestamp: u64| BatchPoint {
+            point: LivoxPoint { x, y, z, reflectivity: 10, tag: 0 },
+            timestamp,
+            return_index: 0,
+        };
+        let cloud = PointCloud {
+            points: vec![
+                point(10.0, 1.0, 0.0, 10 * MS),
+                point(10.0, 1.0, 0.0, 90 * MS),
+                // too far in time, behind the camera
+                point(10.0, 1.0, 0.0, 500 * MS),
+                point(-10.0, 1.0, 0.0, 10 * MS),
+                // the farther point falls right next to the nearer one and is hidden
+                point(5.0, -1.0, 0.09, 10 * MS),
+                point(20.0, -4.0, 0.075, 10 * MS),
+            ],
+        };
+        let coloured = colouriser.colourise(&cloud);
+        let colours = coloured.iter().map(|p| (p.point.x, p.rgb)).collect::<Vec<_>>();
+        assert_eq!(colours, vec![(10.0, [255, 0, 0]), (5.0, [255, 0, 0]), (10.0, [0, 255, 0])]);
+
+        let coloured = colouriser.with_occlusion(None).colourise(&cloud);
+        assert_eq!(coloured.len(), 4);
+    }
+}
@@ -24,6 +24,12 @@ impl ToLivoxError for tokio::sync::oneshot::error::RecvError {
     }
 }
 
+impl ToLivoxError for pcap_file::PcapError {
+    fn of_reason(self, reason: &'static str) -> LivoxError {
+        CaptureError(reason, self)
+    }
+}
+
 pub(crate) trait ToLivoxResult<O> {
     fn err_reason(self, reason: &'static str) -> Result<O, LivoxError>;
 }
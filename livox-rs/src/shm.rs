@@ -0,0 +1,435 @@
+use std::fs::{File, OpenOptions};
+use std::path::{Path, PathBuf};
+use std::sync::atomic::{AtomicU64, fence, Ordering};
+use memmap2::{Mmap, MmapRaw};
+
+use crate::LivoxResult;
+use crate::point::PointBatch;
+use crate::result_util::ToLivoxResult;
+
+use batch::PointsView;
+
+pub mod batch;
+
+/// `magic, version, slot count, slot size, write sequence, generation`, padded to a cache line.
+const HEADER_LEN: usize = 64;
+const MAGIC: &[u8; 8] = b"LVXRING\0";
+const VERSION: u32 = 2;
+/// `sequence, timestamp, kind and length` before the data of every slot.
+const SLOT_HEADER_LEN: usize = 24;
+const WRITE_SEQ: usize = 24;
+const GENERATION: usize = 32;
+
+/// What a record holds.
+pub mod kind {
+    /// A point cloud packet as received from the LiDAR.
+    pub const PACKET: u32 = 1;
+    /// A [`PointBatch`](crate::point::PointBatch) with its packet header and sensor,
+    /// laid out as in [`batch`](super::batch).
+    pub const POINT_BATCH: u32 = 2;
+}
+
+/// Path of a ring buffer: `name` itself if it is a path, else `/dev/shm/<name>`.
+pub fn ring_path(name: &str) -> PathBuf {
+    if name.contains('/') { PathBuf::from(name) } else { Path::new("/dev/shm").join(name) }
+}
+
+/// A record read from the ring.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Record {
+    /// Position in the stream of records, starting at 0.
+    pub seq: u64,
+    /// See [`kind`].
+    pub kind: u32,
+    /// Nanoseconds since UNIX epoch.
+    pub timestamp: u64,
+    pub data: Vec<u8>,
+}
+
+/// A record copied into the buffer of the reader, see [`RingReader::try_next_with`].
+/// The data is 8 bytes aligned.
+#[derive(Debug, Clone, Copy)]
+pub struct RecordRef<'a> {
+    pub seq: u64,
+    pub kind: u32,
+    pub timestamp: u64,
+    pub data: &'a [u8],
+}
+
+impl<'a> RecordRef<'a> {
+    pub fn to_record(&self) -> Record {
+        Record { seq: self.seq, kind: self.kind, timestamp: self.timestamp, data: self.data.to_vec() }
+    }
+
+    /// The points of a [`kind::POINT_BATCH`] record.
+    pub fn points(&self) -> Option<PointsView<'a>> {
+        if self.kind != kind::POINT_BATCH { return None; }
+        PointsView::parse(self.data)
+    }
+}
+
+/// Outcome of [`RingReader::try_next`] and [`RingReader::try_next_with`].
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Read<T = Record> {
+    Record(T),
+    /// The writer overwrote this many records before they were read.
+    Lagged(u64),
+    /// No new record yet.
+    Empty,
+    /// A new ring was created at the path of this one, which gets no more records.
+    /// Open the path again to follow the new ring.
+    Recreated,
+}
+
+/// The mapping of a ring. Other processes write it concurrently, so it is only accessed
+/// through raw pointers, with atomics for everything written after the ring is created.
+enum Map {
+    Writer(MmapRaw),
+    /// Read-only, so readers only need read access to the file.
+    Reader(Mmap),
+}
+
+struct Ring {
+    map: Map,
+    slots: u64,
+    slot_size: usize,
+}
+
+impl Ring {
+    fn new(map: Map) -> LivoxResult<Self> {
+        let invalid = |reason| Err(std::io::Error::new(std::io::ErrorKind::InvalidData, reason))
+            .err_reason("While mapping ring buffer");
+        let (ptr, len) = match &map {
+            Map::Writer(map) => (map.as_ptr(), map.len()),
+            Map::Reader(map) => (map.as_ptr(), map.len()),
+        };
+        if len < HEADER_LEN { return invalid("not a ring buffer"); }
+        // SAFETY: in bounds, these fields are written once before the ring is shared.
+        let header = unsafe { std::slice::from_raw_parts(ptr, 20) };
+        if &header[0..8] != MAGIC || header[8..12] != VERSION.to_le_bytes() {
+            return invalid("not a ring buffer");
+        }
+        let slots = u32::from_le_bytes(header[12..16].try_into().unwrap()) as u64;
+        let slot_size = u32::from_le_bytes(header[16..20].try_into().unwrap()) as usize;
+        if slots == 0 || len < HEADER_LEN + slots as usize * Self::stride(slot_size) {
+            return invalid("truncated ring buffer");
+        }
+        Ok(Ring { map, slots, slot_size })
+    }
+
+    fn ptr(&self) -> *mut u8 {
+        match &self.map {
+            Map::Writer(map) => map.as_mut_ptr(),
+            Map::Reader(map) => map.as_ptr() as *mut u8,
+        }
+    }
+
+    /// Slot header and data, 8 bytes aligned.
+    fn stride(slot_size: usize) -> usize {
+        (SLOT_HEADER_LEN + slot_size).next_multiple_of(8)
+    }
+
+    /// Only stored to through a [`Map::Writer`].
+    fn atomic(&self, offset: usize) -> &AtomicU64 {
+        debug_assert!(offset & 7 == 0 && offset + 8 <= HEADER_LEN + self.slots as usize * Self::stride(self.slot_size));
+        // SAFETY: in bounds and aligned, the map being page aligned, and only accessed atomically.
+        unsafe { &*(self.ptr().add(offset) as *const AtomicU64) }
+    }
+
+    fn write_seq(&self) -> &AtomicU64 {
+        self.atomic(WRITE_SEQ)
+    }
+
+    fn generation(&self) -> &AtomicU64 {
+        self.atomic(GENERATION)
+    }
+
+    fn slot(&self, seq: u64) -> usize {
+        HEADER_LEN + (seq % self.slots) as usize * Self::stride(self.slot_size)
+    }
+}
+
+/// `words` as bytes.
+fn bytes(words: &[u64]) -> &[u8] {
+    // SAFETY: same memory, `u8` has no alignment requirement.
+    unsafe { std::slice::from_raw_parts(words.as_ptr() as *const u8, words.len() * 8) }
+}
+
+fn bytes_mut(words: &mut [u64]) -> &mut [u8] {
+    // SAFETY: as in `bytes`, every bit pattern is a valid `u64`.
+    unsafe { std::slice::from_raw_parts_mut(words.as_mut_ptr() as *mut u8, words.len() * 8) }
+}
+
+/// The single writer of a shared-memory ring buffer, handing LiDAR data to other processes
+/// of the host without serialising it through sockets.
+///
+/// Records are appended to a file in `/dev/shm` (or any path), or to a memfd, that readers map and follow.
+/// Every slot is guarded by a sequence lock: the writer marks the slot odd while copying into it,
+/// readers check the sequence did not change while they copied out, so the writer never waits
+/// for slow readers and they learn how many records they missed.
+pub struct RingWriter {
+    ring: Ring,
+    path: PathBuf,
+    /// A record is built here, then copied to its slot word by word.
+    buf: Vec<u64>,
+    /// Keeps a memfd alive for readers opening it by path.
+    _file: File,
+}
+
+impl RingWriter {
+    /// Create a ring of `slots` records of at most `slot_size` bytes at `path`.
+    ///
+    /// A ring already there is replaced rather than truncated, as readers may still map it,
+    /// and they get [`Read::Recreated`] from it.
+    pub fn create(path: impl AsRef<Path>, slots: u32, slot_size: u32) -> LivoxResult<Self> {
+        let path = path.as_ref().to_owned();
+        let previous = OpenOptions::new().read(true).write(true).open(&path).ok()
+            .and_then(|file| MmapRaw::map_raw(&file).ok())
+            .and_then(|map| Ring::new(Map::Writer(map)).ok());
+        let generation = previous.as_ref().map_or(0, |ring| ring.generation().load(Ordering::Acquire)) + 1;
+
+        let mut name = path.file_name().unwrap_or_default().to_owned();
+        name.push(format!(".{}.tmp", std::process::id()));
+        let tmp = path.with_file_name(name);
+        let file = OpenOptions::new().read(true).write(true).create(true).truncate(true).open(&tmp)
+            .err_reason("While creating ring buffer")?;
+        let writer = Self::init(file, path.clone(), slots, slot_size, generation)?;
+        std::fs::rename(&tmp, &path).err_reason("While creating ring buffer")?;
+        if let Some(previous) = previous {
+            previous.generation().store(generation, Ordering::Release);
+        }
+        Ok(writer)
+    }
+
+    /// Create a ring in an anonymous memfd, gone with the writer. Readers open it at
+    /// [`path`](Self::path), `/proc/<pid>/fd/<fd>`.
+    #[cfg(target_os = "linux")]
+    pub fn create_memfd(name: &str, slots: u32, slot_size: u32) -> LivoxResult<Self> {
+        use std::os::fd::{FromRawFd, AsRawFd};
+
+        let name = std::ffi::CString::new(name)
+            .map_err(|err| std::io::Error::new(std::io::ErrorKind::InvalidInput, err))
+            .err_reason("While creating ring buffer")?;
+        // SAFETY: `name` is NUL terminated, the returned descriptor is owned by the file.
+        let fd = unsafe { libc::memfd_create(name.as_ptr(), libc::MFD_CLOEXEC) };
+        if fd < 0 {
+            return Err(std::io::Error::last_os_error()).err_reason("While creating ring buffer");
+        }
+        let file = unsafe { File::from_raw_fd(fd) };
+        let path = PathBuf::from(format!("/proc/{}/fd/{}", std::process::id(), file.as_raw_fd()));
+        Self::init(file, path, slots, slot_size, 1)
+    }
+
+    fn init(file: File, path: PathBuf, slots: u32, slot_size: u32, generation: u64) -> LivoxResult<Self> {
+        let slots = slots.max(1);
+        let len = HEADER_LEN + slots as usize * Ring::stride(slot_size as usize);
+        file.set_len(len as u64).err_reason("While creating ring buffer")?;
+        let map = MmapRaw::map_raw(&file).err_reason("While mapping ring buffer")?;
+        let mut header = [0u8; HEADER_LEN];
+        header[0..8].copy_from_slice(MAGIC);
+        header[8..12].copy_from_slice(&VERSION.to_le_bytes());
+        header[12..16].copy_from_slice(&slots.to_le_bytes());
+        header[16..20].copy_from_slice(&slot_size.to_le_bytes());
+        header[GENERATION..GENERATION + 8].copy_from_slice(&generation.to_ne_bytes());
+        // SAFETY: in bounds, no reader can map the file before it is returned.
+        unsafe { std::ptr::copy_nonoverlapping(header.as_ptr(), map.as_mut_ptr(), HEADER_LEN) };
+        Ok(RingWriter { ring: Ring::new(Map::Writer(map))?, path, buf: Vec::new(), _file: file })
+    }
+
+    pub fn path(&self) -> &Path {
+        &self.path
+    }
+
+    /// Largest record.
+    pub fn slot_size(&self) -> usize {
+        self.ring.slot_size
+    }
+
+    /// Append a record, `false` if it is larger than a slot and was dropped.
+    pub fn write(&mut self, kind: u32, timestamp: u64, data: &[u8]) -> bool {
+        self.write_with(kind, timestamp, data.len(), |buf| buf.copy_from_slice(data))
+    }
+
+    /// Append the points of a batch as a [`kind::POINT_BATCH`] record timestamped by host time
+    /// when known, `false` if it is larger than a slot and was dropped.
+    pub fn write_batch(&mut self, sensor_id: &str, batch: &PointBatch) -> bool {
+        self.write_with(kind::POINT_BATCH, batch.packet.time(), batch::record_len(batch.len()),
+                        |buf| batch::encode(sensor_id, batch, buf))
+    }
+
+    /// Append a record of `len` bytes filled by `fill`, 8 bytes aligned.
+    fn write_with(&mut self, kind: u32, timestamp: u64, len: usize, fill: impl FnOnce(&mut [u8])) -> bool {
+        if len > self.ring.slot_size { return false; }
+        self.buf.clear();
+        self.buf.resize(len.div_ceil(8), 0);
+        fill(&mut bytes_mut(&mut self.buf)[..len]);
+
+        let ring = &self.ring;
+        let seq = ring.write_seq().load(Ordering::Relaxed);
+        let slot = ring.slot(seq);
+        let lock = ring.atomic(slot);
+        lock.store(seq * 2 + 1, Ordering::Relaxed);
+        fence(Ordering::Release);
+        ring.atomic(slot + 8).store(timestamp, Ordering::Relaxed);
+        ring.atomic(slot + 16).store(kind as u64 | (len as u64) << 32, Ordering::Relaxed);
+        for (i, word) in self.buf.iter().enumerate() {
+            ring.atomic(slot + SLOT_HEADER_LEN + i * 8).store(*word, Ordering::Relaxed);
+        }
+        lock.store(seq * 2 + 2, Ordering::Release);
+        ring.write_seq().store(seq + 1, Ordering::Release);
+        true
+    }
+}
+
+/// A reader following a ring buffer, from the records written after it was opened.
+/// It only needs read access to the file.
+///
+/// Reads are not zero-copy: the writer may overwrite a slot at any time, and handing out a
+/// reference into the mapping would be a data race, so each record is copied once, word by word
+/// with atomic loads, into a buffer of the reader, and only handed out if the slot's sequence
+/// shows it was not overwritten meanwhile. Views of the record, e.g. [`RecordRef::points`],
+/// borrow that buffer without copying again.
+pub struct RingReader {
+    ring: Ring,
+    generation: u64,
+    next: u64,
+    buf: Vec<u64>,
+}
+
+impl RingReader {
+    pub fn open(path: impl AsRef<Path>) -> LivoxResult<Self> {
+        let file = File::open(path).err_reason("While opening ring buffer")?;
+        // SAFETY: the map is only accessed through raw pointers and atomics, see `Map`.
+        let map = unsafe { Mmap::map(&file) }.err_reason("While mapping ring buffer")?;
+        let ring = Ring::new(Map::Reader(map))?;
+        let generation = ring.generation().load(Ordering::Acquire);
+        let next = ring.write_seq().load(Ordering::Acquire);
+        Ok(RingReader { ring, generation, next, buf: Vec::new() })
+    }
+
+    /// Sequence of the next record to read.
+    pub fn position(&self) -> u64 {
+        self.next
+    }
+
+    /// Read the next record without waiting.
+    pub fn try_next(&mut self) -> Read {
+        self.try_next_with(|record| record.to_record())
+    }
+
+    /// Read the next record without waiting or allocating, e.g. its [`points`](RecordRef::points).
+    ///
+    /// The record is copied once into a buffer of the reader, which `read` gets once the copy is known
+    /// not to have been overwritten meanwhile, see [`RingReader`].
+    pub fn try_next_with<T>(&mut self, read: impl FnOnce(RecordRef<'_>) -> T) -> Read<T> {
+        if self.ring.generation().load(Ordering::Acquire) != self.generation {
+            return Read::Recreated;
+        }
+        let written = self.ring.write_seq().load(Ordering::Acquire);
+        if self.next >= written { return Read::Empty; }
+        if written - self.next > self.ring.slots {
+            return self.skip_to(written);
+        }
+
+        let seq = self.next;
+        let ring = &self.ring;
+        let slot = ring.slot(seq);
+        let lock = ring.atomic(slot);
+        let before = lock.load(Ordering::Acquire);
+        if before != seq * 2 + 2 {
+            return self.skip_to(ring.write_seq().load(Ordering::Acquire));
+        }
+        let timestamp = ring.atomic(slot + 8).load(Ordering::Relaxed);
+        let kind_len = ring.atomic(slot + 16).load(Ordering::Relaxed);
+        let len = ((kind_len >> 32) as usize).min(ring.slot_size);
+        self.buf.clear();
+        self.buf.extend((0..len.div_ceil(8)).map(|i| ring.atomic(slot + SLOT_HEADER_LEN + i * 8).load(Ordering::Relaxed)));
+        fence(Ordering::Acquire);
+        if lock.load(Ordering::Relaxed) != before {
+            // overwritten while copying
+            return self.skip_to(ring.write_seq().load(Ordering::Acquire));
+        }
+        self.next += 1;
+        Read::Record(read(RecordRef { seq, kind: kind_len as u32, timestamp, data: &bytes(&self.buf)[..len] }))
+    }
+
+    /// Give up on overwritten records, resuming from the oldest one still in the ring.
+    fn skip_to<T>(&mut self, written: u64) -> Read<T> {
+        let oldest = written.saturating_sub(self.ring.slots - 1).max(self.next + 1);
+        let lost = oldest - self.next;
+        self.next = oldest;
+        Read::Lagged(lost)
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn test_ring() {
+        let path = std::env::temp_dir().join(format!("livox-rs-ring-{}", std::process::id()));
+        let mut writer = RingWriter::create(&path, 4, 16).unwrap();
+        let mut reader = RingReader::open(&path).unwrap();
+        assert_eq!(reader.try_next(), Read::Empty);
+
+        assert!(writer.write(kind::PACKET, 42, b"hello"));
+        assert!(!writer.write(kind::PACKET, 43, &[0; 17]));
+        assert_eq!(reader.try_next(), Read::Record(Record { seq: 0, kind: kind::PACKET, timestamp: 42, data: b"hello".to_vec() }));
+        assert_eq!(reader.try_next(), Read::Empty);
+
+        // a late reader only sees new records, a slow one is told what it missed
+        for i in 1..=6u8 {
+            writer.write(kind::PACKET, i as u64, &[i]);
+        }
+        let mut late = RingReader::open(&path).unwrap();
+        assert_eq!(late.try_next(), Read::Empty);
+        assert_eq!(reader.try_next(), Read::Lagged(3));
+        let data = std::iter::from_fn(|| match reader.try_next() {
+            Read::Record(record) => Some(record.data[0]),
+            _ => None,
+        }).collect::<Vec<_>>();
+        assert_eq!(data, [4, 5, 6]);
+
+        // readers of a replaced ring are told so, the new one starts over
+        let mut writer = RingWriter::create(&path, 4, 16).unwrap();
+        writer.write(kind::PACKET, 7, &[7]);
+        assert_eq!(reader.try_next(), Read::Recreated);
+        assert_eq!(late.try_next(), Read::Recreated);
+        let mut reader = RingReader::open(&path).unwrap();
+        assert_eq!(reader.try_next(), Read::Empty);
+        writer.write(kind::PACKET, 8, &[8]);
+        assert_eq!(reader.try_next(), Read::Record(Record { seq: 1, kind: kind::PACKET, timestamp: 8, data: vec![8] }));
+        std::fs::remove_file(&path).unwrap();
+    }
+
+    #[test]
+    #[cfg(target_os = "linux")]
+    fn test_batches() {
+        use crate::model::data_type::LivoxPoint;
+        use crate::point::{BatchPoint, PacketInfo};
+
+        let mut batch = PointBatch {
+            packet: PacketInfo { timestamp: 1000, host_timestamp: Some(5000), data_type: 2, ..PacketInfo::default() },
+            ..PointBatch::default()
+        };
+        for i in 0..3 {
+            batch.push(BatchPoint {
+                point: LivoxPoint { x: i as f32, y: 1.0, z: 2.0, reflectivity: 10, tag: 0x10 },
+                timestamp: 1000 + i,
+                return_index: 0,
+            });
+        }
+        let mut writer = RingWriter::create_memfd("livox-rs-test", 4, batch::record_len(3) as u32).unwrap();
+        let mut reader = RingReader::open(writer.path()).unwrap();
+        assert!(writer.write_batch("3GGDJ6K00100101", &batch));
+
+        let read = reader.try_next_with(|record| {
+            let view = record.points().unwrap();
+            assert_eq!(view.header.sensor_id(), "3GGDJ6K00100101");
+            assert_eq!(view.points[2].x, 2.0);
+            (record.timestamp, view.to_batch())
+        });
+        assert_eq!(read, Read::Record((5000, batch)));
+    }
+}
@@ -0,0 +1,155 @@
+use std::mem::{align_of, size_of};
+
+use crate::model::data_type::LivoxPoint;
+use crate::point::{BatchPoint, PacketInfo, PointBatch};
+
+/// Header of a [`kind::POINT_BATCH`](super::kind::POINT_BATCH) record, followed by
+/// [`BatchHeader::len`] [`ShmPoint`]s. In native byte order, the ring never leaves the host.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+#[repr(C)]
+pub struct BatchHeader {
+    /// Broadcast code of the LiDAR, NUL padded.
+    pub sensor_id: [u8; 16],
+    /// Sensor timestamp of the first point, in nanoseconds.
+    pub timestamp: u64,
+    /// See [`PacketInfo::host_timestamp`], `0` if unknown.
+    pub host_timestamp: u64,
+    pub status_code: u32,
+    /// Number of points.
+    pub len: u32,
+    pub version: u8,
+    pub slot_id: u8,
+    pub lidar_id: u8,
+    pub timestamp_type: u8,
+    pub data_type: u8,
+    pub _reserved: [u8; 3],
+}
+
+/// A point of a batch record.
+#[derive(Debug, Clone, Copy, PartialEq, Default)]
+#[repr(C)]
+pub struct ShmPoint {
+    /// Coordinates in metres.
+    pub x: f32,
+    pub y: f32,
+    pub z: f32,
+    pub reflectivity: u8,
+    /// Raw [`TagInfo`](crate::model::data_type::TagInfo) byte.
+    pub tag: u8,
+    pub return_index: u8,
+    pub _reserved: u8,
+    /// Sensor timestamp, in nanoseconds.
+    pub timestamp: u64,
+}
+
+pub const HEADER_LEN: usize = size_of::<BatchHeader>();
+pub const POINT_LEN: usize = size_of::<ShmPoint>();
+
+/// Length of the record of a batch of `points` points.
+pub fn record_len(points: usize) -> usize {
+    HEADER_LEN + points * POINT_LEN
+}
+
+impl BatchHeader {
+    fn new(sensor_id: &str, packet: &PacketInfo, len: usize) -> Self {
+        let mut id = [0; 16];
+        let bytes = &sensor_id.as_bytes()[..sensor_id.len().min(16)];
+        id[..bytes.len()].copy_from_slice(bytes);
+        BatchHeader {
+            sensor_id: id,
+            timestamp: packet.timestamp,
+            host_timestamp: packet.host_timestamp.unwrap_or(0),
+            status_code: packet.status_code,
+            len: len as u32,
+            version: packet.version,
+            slot_id: packet.slot_id,
+            lidar_id: packet.lidar_id,
+            timestamp_type: packet.timestamp_type,
+            data_type: packet.data_type,
+            _reserved: [0; 3],
+        }
+    }
+
+    /// Broadcast code of the LiDAR, empty if not UTF-8.
+    pub fn sensor_id(&self) -> &str {
+        let len = self.sensor_id.iter().position(|b| *b == 0).unwrap_or(16);
+        std::str::from_utf8(&self.sensor_id[..len]).unwrap_or_default()
+    }
+
+    pub fn packet(&self) -> PacketInfo {
+        PacketInfo {
+            version: self.version,
+            slot_id: self.slot_id,
+            lidar_id: self.lidar_id,
+            status_code: self.status_code,
+            timestamp_type: self.timestamp_type,
+            timestamp: self.timestamp,
+            host_timestamp: (self.host_timestamp != 0).then_some(self.host_timestamp),
+            data_type: self.data_type,
+        }
+    }
+}
+
+impl From<ShmPoint> for BatchPoint {
+    fn from(p: ShmPoint) -> Self {
+        BatchPoint {
+            point: LivoxPoint { x: p.x, y: p.y, z: p.z, reflectivity: p.reflectivity, tag: p.tag },
+            timestamp: p.timestamp,
+            return_index: p.return_index,
+        }
+    }
+}
+
+/// Write the record of a batch to `buf`, [`record_len`] bytes long and 8 bytes aligned.
+pub(crate) fn encode(sensor_id: &str, batch: &PointBatch, buf: &mut [u8]) {
+    let header = BatchHeader::new(sensor_id, &batch.packet, batch.len());
+    let (head, points) = buf.split_at_mut(HEADER_LEN);
+    // SAFETY: both types are plain old data, the caller checked length and alignment.
+    unsafe {
+        (head.as_mut_ptr() as *mut BatchHeader).write(header);
+        let points = std::slice::from_raw_parts_mut(points.as_mut_ptr() as *mut ShmPoint, batch.len());
+        for (dst, p) in points.iter_mut().zip(batch.iter()) {
+            *dst = ShmPoint {
+                x: p.point.x,
+                y: p.point.y,
+                z: p.point.z,
+                reflectivity: p.point.reflectivity,
+                tag: p.point.tag,
+                return_index: p.return_index,
+                _reserved: 0,
+                timestamp: p.timestamp,
+            };
+        }
+    }
+}
+
+/// A batch record viewed without copying it out of the reader buffer, see [`RecordRef::points`](super::RecordRef::points).
+#[derive(Debug, Clone, Copy)]
+pub struct PointsView<'a> {
+    pub header: &'a BatchHeader,
+    pub points: &'a [ShmPoint],
+}
+
+impl<'a> PointsView<'a> {
+    /// `None` if `data` is too short for its header or misaligned.
+    pub fn parse(data: &'a [u8]) -> Option<Self> {
+        if data.len() < HEADER_LEN || data.as_ptr().align_offset(align_of::<BatchHeader>()) != 0 {
+            return None;
+        }
+        // SAFETY: aligned and in bounds, every bit pattern is a valid `BatchHeader` and `ShmPoint`.
+        let header = unsafe { &*(data.as_ptr() as *const BatchHeader) };
+        let len = header.len as usize;
+        if data.len() < record_len(len) { return None; }
+        let points = unsafe { std::slice::from_raw_parts(data[HEADER_LEN..].as_ptr() as *const ShmPoint, len) };
+        Some(PointsView { header, points })
+    }
+
+    /// Copy out as a [`PointBatch`].
+    pub fn to_batch(&self) -> PointBatch {
+        let mut batch = PointBatch { packet: self.header.packet(), ..PointBatch::default() };
+        for p in self.points {
+            batch.push((*p).into());
+        }
+        batch
+    }
+}
@@ -0,0 +1,229 @@
+use std::fs::File;
+use std::io::{BufRead, BufReader};
+use std::path::Path;
+use std::sync::{Arc, Mutex};
+use std::time::{Duration, Instant, SystemTime, UNIX_EPOCH};
+use tracing::{info, warn};
+
+use crate::LivoxResult;
+use crate::model::data_type::LiDARStatusCode;
+use crate::model::deku_data_type::lidar::request::days_from_civil;
+use crate::result_util::ToLivoxResult;
+
+/// Where the UTC time pushed to the LiDAR comes from.
+pub trait ClockSource: Send + 'static {
+    /// Current UTC time, or `None` if the source has no valid time right now.
+    fn now(&mut self) -> Option<SystemTime>;
+}
+
+/// The host system clock.
+#[derive(Debug, Default, Clone, Copy)]
+pub struct SystemClock;
+
+impl ClockSource for SystemClock {
+    fn now(&mut self) -> Option<SystemTime> {
+        Some(SystemTime::now())
+    }
+}
+
+/// A user supplied clock.
+pub struct CallbackClock<F>(pub F);
+
+impl<F: FnMut() -> Option<SystemTime> + Send + 'static> ClockSource for CallbackClock<F> {
+    fn now(&mut self) -> Option<SystemTime> {
+        (self.0)()
+    }
+}
+
+/// Time from GPS `RMC` NMEA sentences, read line by line from a serial port, pty or any reader.
+///
+/// Time between sentences is extrapolated with the host monotonic clock.
+/// The serial port must be configured (baud rate etc.) beforehand, e.g. with `stty`.
+pub struct NmeaClock {
+    latest: Arc<Mutex<Option<(SystemTime, Instant)>>>,
+}
+
+impl NmeaClock {
+    /// A fix older than this is considered lost.
+    pub const MAX_AGE: Duration = Duration::from_secs(2);
+
+    pub fn open(path: impl AsRef<Path>) -> LivoxResult<Self> {
+        let file = File::open(path).err_reason("While opening NMEA source")?;
+        Ok(Self::new(BufReader::new(file)))
+    }
+
+    /// Read sentences from `reader` on a background thread.
+    pub fn new(reader: impl BufRead + Send + 'static) -> Self {
+        let latest = Arc::new(Mutex::new(None));
+        let writer = latest.clone();
+        std::thread::spawn(move || {
+            let mut reader = reader;
+            let mut line = Vec::new();
+            loop {
+                line.clear();
+                match reader.read_until(b'\n', &mut line) {
+                    Ok(0) => break,
+                    Ok(_) => {}
+                    Err(err) if err.kind() == std::io::ErrorKind::Interrupted => continue,
+                    Err(err) => {
+                        warn!("NMEA source failed: {}", err);
+                        break;
+                    }
+                }
+                // serial noise or a wrong baud rate gives garbage, which fails the checksum
+                let Ok(line) = std::str::from_utf8(&line) else { continue; };
+                if let Some(time) = Self::parse_rmc(line) {
+                    *writer.lock().unwrap() = Some((time, Instant::now()));
+                }
+            }
+            info!("NMEA source closed");
+        });
+        NmeaClock { latest }
+    }
+
+    /// UTC time of a valid `$--RMC` sentence, `None` for other or invalid sentences.
+    pub fn parse_rmc(line: &str) -> Option<SystemTime> {
+        let line = line.trim().strip_prefix('$')?;
+        let (body, checksum) = line.split_once('*')?;
+        let checksum = u8::from_str_radix(checksum, 16).ok()?;
+        if body.bytes().fold(0, |acc, b| acc ^ b) != checksum {
+            return None;
+        }
+
+        let fields = body.split(',').collect::<Vec<_>>();
+        if fields.len() < 10 || !fields[0].ends_with("RMC") || fields[2] != "A" {
+            return None;
+        }
+        let (time, date) = (fields[1], fields[9]);
+        let digits = |s: &str| !s.is_empty() && s.bytes().all(|b| b.is_ascii_digit());
+        // hhmmss[.sss] and ddmmyy, ASCII so they can be sliced by bytes
+        let (whole, fraction) = time.split_once('.').unwrap_or((time, "0"));
+        if whole.len() != 6 || !digits(whole) || !digits(fraction) || date.len() != 6 || !digits(date) {
+            return None;
+        }
+        let number = |s: &str| s.parse::<u32>().ok();
+        let (hour, minute) = (number(&time[0..2])?, number(&time[2..4])?);
+        let second = time[4..].parse::<f64>().ok()?;
+        // a leap second may read 60
+        if hour >= 24 || minute >= 60 || !(0.0..61.0).contains(&second) {
+            return None;
+        }
+        let (day, month, year) = (number(&date[0..2])?, number(&date[2..4])?, number(&date[4..6])?);
+        if !(1..=12).contains(&month) || !(1..=31).contains(&day) {
+            return None;
+        }
+
+        let days = days_from_civil(2000 + year as i64, month as u8, day as u8);
+        let secs = days as u64 * 86400 + (hour * 3600 + minute * 60) as u64;
+        Some(UNIX_EPOCH + Duration::from_secs(secs) + Duration::from_secs_f64(second))
+    }
+}
+
+impl ClockSource for NmeaClock {
+    fn now(&mut self) -> Option<SystemTime> {
+        let (time, received) = (*self.latest.lock().unwrap())?;
+        let age = received.elapsed();
+        (age < Self::MAX_AGE).then(|| time + age)
+    }
+}
+
+/// `time_sync_status` field of [`LiDARStatusCode`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum TimeSyncStatus {
+    NoSync,
+    Ptp,
+    Gps,
+    Pps,
+    /// System time sync error.
+    Abnormal,
+    Unknown(u8),
+}
+
+impl From<&LiDARStatusCode> for TimeSyncStatus {
+    fn from(status: &LiDARStatusCode) -> Self {
+        match status.time_sync_status {
+            0 => TimeSyncStatus::NoSync,
+            1 => TimeSyncStatus::Ptp,
+            2 => TimeSyncStatus::Gps,
+            3 => TimeSyncStatus::Pps,
+            4 => TimeSyncStatus::Abnormal,
+            other => TimeSyncStatus::Unknown(other as u8),
+        }
+    }
+}
+
+/// State of the time synchronisation task, see [`LivoxClient::start_time_sync`](crate::LivoxClient::start_time_sync).
+#[derive(Debug, Clone, Copy, Default)]
+pub struct TimeSyncState {
+    /// Host system clock time of the last successful push.
+    pub last_sync: Option<SystemTime>,
+    /// Clock source time minus host system time at the last push, in nanoseconds.
+    /// Subtract it from synchronized point timestamps to get host time.
+    pub offset_ns: Option<i64>,
+    /// Consecutive failed pushes.
+    pub failures: u32,
+}
+
+/// Signed difference `a - b` in nanoseconds.
+pub(crate) fn signed_diff_ns(a: SystemTime, b: SystemTime) -> i64 {
+    match a.duration_since(b) {
+        Ok(d) => d.as_nanos() as i64,
+        Err(e) => -(e.duration().as_nanos() as i64),
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use byte_struct::ByteStructUnspecifiedByteOrder;
+    use super::*;
+
+    #[test]
+    fn test_parse_rmc() {
+        assert_eq!(NmeaClock::parse_rmc("$GNGGA,134506.50,3150.7820,N,11711.9250,E,1,08,1.0,50.0,M,,M,,*00"), None);
+
+        // 2022-07-23T13:45:06.5Z
+        let line = "$GNRMC,134506.50,A,3150.7820,N,11711.9250,E,0.0,0.0,230722,,,A";
+        let checksum = line[1..].bytes().fold(0, |acc, b| acc ^ b);
+        let time = NmeaClock::parse_rmc(&format!("{}*{:02X}", line, checksum));
+        assert_eq!(time, Some(UNIX_EPOCH + Duration::from_millis(1_658_583_906_500)));
+
+        let void = "$GNRMC,134506.50,V,,,,,,,230722,,,N";
+        let checksum = void[1..].bytes().fold(0, |acc, b| acc ^ b);
+        assert_eq!(NmeaClock::parse_rmc(&format!("{}*{:02X}", void, checksum)), None);
+
+        // malformed times with a valid checksum
+        for time in ["1234-1", "1234NaN", "1é3456", "1é345", "254506.50", "136006.50", "134561.00", "134506.", "1345"] {
+            let line = format!("$GNRMC,{},A,3150.7820,N,11711.9250,E,0.0,0.0,230722,,,A", time);
+            let checksum = line[1..].bytes().fold(0, |acc, b| acc ^ b);
+            assert_eq!(NmeaClock::parse_rmc(&format!("{}*{:02X}", line, checksum)), None, "{}", time);
+        }
+        let line = "$GNRMC,235960,A,3150.7820,N,11711.9250,E,0.0,0.0,230722,,,A";
+        let checksum = line[1..].bytes().fold(0, |acc, b| acc ^ b);
+        assert!(NmeaClock::parse_rmc(&format!("{}*{:02X}", line, checksum)).is_some());
+    }
+
+    #[test]
+    fn test_nmea_clock_skips_garbage() {
+        let line = "$GNRMC,134506.50,A,3150.7820,N,11711.9250,E,0.0,0.0,230722,,,A";
+        let checksum = line[1..].bytes().fold(0, |acc, b| acc ^ b);
+        let mut input = b"\xff\xfe noise\n".to_vec();
+        input.extend_from_slice(format!("{}*{:02X}\n", line, checksum).as_bytes());
+
+        let mut clock = NmeaClock::new(std::io::Cursor::new(input));
+        let start = Instant::now();
+        while clock.latest.lock().unwrap().is_none() && start.elapsed() < Duration::from_secs(5) {
+            std::thread::sleep(Duration::from_millis(1));
+        }
+        assert!(clock.now().is_some());
+    }
+
+    #[test]
+    fn test_time_sync_status() {
+        // time_sync_status occupies bits 14..17
+        let status = LiDARStatusCode::read_bytes_default_le(&(3u32 << 14 | 1 << 30).to_le_bytes());
+        assert_eq!(TimeSyncStatus::from(&status), TimeSyncStatus::Pps);
+        assert_eq!(status.system_status, 1);
+        let status = LiDARStatusCode::read_bytes_default_le(&(4u32 << 14).to_le_bytes());
+        assert_eq!(TimeSyncStatus::from(&status), TimeSyncStatus::Abnormal);
+    }
+}
@@ -0,0 +1,364 @@
+use std::error::Error;
+use std::net::{IpAddr, Ipv4Addr};
+use std::path::PathBuf;
+use std::time::{Duration, SystemTime};
+use clap::{Parser, Subcommand, ValueEnum};
+use tokio::select;
+use tokio::time::{interval, Instant};
+use tokio_stream::StreamExt;
+
+use livox_rs::{CoordinateSystem, HandshakeOption, Livox, LivoxClient, LivoxError};
+use livox_rs::extrinsics::{DeviceExtrinsics, Extrinsics};
+use livox_rs::network::IpConfig;
+use livox_rs::time_sync::{NmeaClock, SystemClock};
+use livox_rs::model::data_type::LiDARStatusCode;
+use livox_rs::model::deku_data_type::{general, lidar};
+
+/// Administrate Livox LiDARs on the local network.
+#[derive(Parser, Debug)]
+#[command(version)]
+struct Args {
+    /// Device to talk to, by broadcast code or IP address. Defaults to the first one found.
+    #[arg(short, long, global = true)]
+    target: Option<String>,
+    /// Host IP the device sends data to. Defaults to the interface routing to the device.
+    #[arg(long, global = true)]
+    host_ip: Option<Ipv4Addr>,
+    #[command(subcommand)]
+    command: Command,
+}
+
+#[derive(Subcommand, Debug)]
+enum Command {
+    /// List devices broadcasting on the network.
+    Discover {
+        /// Seconds to listen for broadcasts.
+        #[arg(short, long, default_value_t = 3)]
+        wait: u64,
+    },
+    /// Show device type, firmware version and status.
+    Info,
+    /// Show IP configuration.
+    GetIp,
+    /// Configure IP address, reboot and reconnect. Falls back to DHCP if the device does not come back.
+    SetIp {
+        #[command(subcommand)]
+        mode: IpMode,
+    },
+    /// Reboot the device.
+    Reboot {
+        /// Delay before rebooting, in milliseconds.
+        #[arg(long, default_value_t = 0)]
+        delay: u16,
+    },
+    /// Set working mode.
+    Mode {
+        mode: WorkingMode,
+    },
+    /// Switch point cloud data between Cartesian and spherical coordinates.
+    Coordinate {
+        system: Coordinate,
+    },
+    /// Get or set return mode.
+    ReturnMode {
+        mode: Option<ReturnMode>,
+    },
+    /// Get or set fan state.
+    Fan {
+        state: Option<OnOff>,
+    },
+    /// Turn rain/fog suppression on or off.
+    RainFog {
+        state: OnOff,
+    },
+    /// Read or write extrinsic parameters.
+    Extrinsics {
+        #[command(subcommand)]
+        action: ExtrinsicsAction,
+    },
+    /// Push UTC time to the device every second until interrupted.
+    TimeSync {
+        /// Take time from NMEA sentences (GPS receiver serial port or pty) instead of the system clock.
+        #[arg(long, value_name = "PATH")]
+        nmea: Option<PathBuf>,
+        /// Push period, in milliseconds.
+        #[arg(long, default_value_t = 1000)]
+        period: u64,
+        /// Push system time once and exit.
+        #[arg(long, conflicts_with = "nmea")]
+        once: bool,
+    },
+    /// Start sampling and print point cloud statistics every second.
+    Stream {
+        /// Print per-second statistics.
+        #[arg(long)]
+        stats: bool,
+        /// Stop after this many seconds.
+        #[arg(long)]
+        duration: Option<u64>,
+    },
+}
+
+#[derive(Subcommand, Debug)]
+enum IpMode {
+    /// Get address from DHCP.
+    Dhcp,
+    /// Use a static address.
+    Static {
+        ip: Ipv4Addr,
+        #[arg(long, default_value = "255.255.255.0")]
+        netmask: Ipv4Addr,
+        #[arg(long, default_value = "0.0.0.0")]
+        gateway: Ipv4Addr,
+    },
+}
+
+#[derive(Subcommand, Debug)]
+enum ExtrinsicsAction {
+    Get,
+    /// Angles in degrees, translation in millimetres.
+    Set {
+        #[arg(long, allow_negative_numbers = true)]
+        roll: f32,
+        #[arg(long, allow_negative_numbers = true)]
+        pitch: f32,
+        #[arg(long, allow_negative_numbers = true)]
+        yaw: f32,
+        #[arg(long, allow_negative_numbers = true)]
+        x: i32,
+        #[arg(long, allow_negative_numbers = true)]
+        y: i32,
+        #[arg(long, allow_negative_numbers = true)]
+        z: i32,
+    },
+}
+
+#[derive(ValueEnum, Clone, Copy, Debug)]
+enum WorkingMode {
+    Normal = 1,
+    PowerSaving = 2,
+    Standby = 3,
+}
+
+#[derive(ValueEnum, Clone, Copy, Debug)]
+enum Coordinate {
+    Cartesian,
+    Spherical,
+}
+
+#[derive(ValueEnum, Clone, Copy, Debug)]
+enum ReturnMode {
+    SingleFirst = 0,
+    SingleStrongest = 1,
+    Dual = 2,
+}
+
+#[derive(ValueEnum, Clone, Copy, Debug)]
+enum OnOff {
+    Off = 0,
+    On = 1,
+}
+
+#[tokio::main]
+async fn main() -> Result<(), Box<dyn Error>> {
+    let subscriber = tracing_subscriber::fmt()
+        .with_writer(std::io::stderr)
+        .with_max_level(tracing::Level::WARN)
+        .finish();
+    tracing::subscriber::set_global_default(subscriber)?;
+    let args = Args::parse();
+
+    if let Command::Discover { wait } = args.command {
+        for livox in Livox::discover(Duration::from_secs(wait)).await? {
+            println!("{}\t{}\t{:?}", livox.broadcast_code_str(), livox.lidar_addr.ip(), livox.device_type);
+        }
+        return Ok(());
+    }
+
+    let mut option = HandshakeOption::new();
+    if let Some(ip) = args.host_ip {
+        option = option.user_ip(ip);
+    }
+    let target = args.target.clone();
+    let livox = Livox::wait_for(move |livox| match &target {
+        None => true,
+        Some(target) => match target.parse::<IpAddr>() {
+            Ok(ip) => livox.lidar_addr.ip() == ip,
+            Err(_) => livox.broadcast_code_str() == *target,
+        },
+    }).await?;
+    let client = livox.handshake(option.clone()).await?;
+
+    if let Command::SetIp { mode } = args.command {
+        let config = match mode {
+            IpMode::Dhcp => IpConfig::Dhcp,
+            IpMode::Static { ip, netmask, gateway } => IpConfig::Static { ip, netmask, gateway },
+        };
+        config.validate()?;
+        let client = match client.reconfigure_network(config, option).await {
+            Ok(client) => client,
+            Err(LivoxError::NotReconfigured { client, reason }) => {
+                let _ = client.disconnect().await;
+                return Err(reason as Box<dyn Error>);
+            }
+            Err(err) => return Err(err.into()),
+        };
+        println!("Device is back at {}", client.lidar.lidar_addr.ip());
+        client.disconnect().await.1?;
+        return Ok(());
+    }
+
+    // A rebooting device will not acknowledge the disconnection.
+    let rebooting = matches!(args.command, Command::Reboot { .. });
+    let result = run(&client, args.command).await;
+    if !rebooting {
+        client.disconnect().await.1?;
+    }
+    result
+}
+
+async fn run(client: &LivoxClient, command: Command) -> Result<(), Box<dyn Error>> {
+    match command {
+        Command::Discover { .. } | Command::SetIp { .. } => unreachable!(),
+        Command::Info => {
+            let info = client.request(general::request::QueryDeviceInformation {}).await?;
+            let heartbeat = client.request(general::request::Heartbeat {}).await?;
+            let lidar = &client.lidar;
+            println!("broadcast code: {}", lidar.broadcast_code_str());
+            println!("address:        {}", lidar.lidar_addr.ip());
+            println!("device type:    {:?}", lidar.device_type);
+            println!("firmware:       {}", info.version.map(|v| v.to_string()).join("."));
+            println!("work state:     {}", heartbeat.work_state);
+            println!("status:         {:?}", LiDARStatusCode::from(heartbeat.ack_msg));
+        }
+        Command::GetIp => {
+            match client.ip_config().await? {
+                IpConfig::Dhcp => {
+                    println!("mode:    dhcp");
+                    println!("ip:      {}", client.lidar.lidar_addr.ip());
+                }
+                IpConfig::Static { ip, netmask, gateway } => {
+                    println!("mode:    static");
+                    println!("ip:      {}", ip);
+                    println!("netmask: {}", netmask);
+                    println!("gateway: {}", gateway);
+                }
+            }
+        }
+        Command::Reboot { delay } => {
+            client.request(general::request::RebootDevice { timeout: delay }).await?;
+            println!("Rebooting.");
+        }
+        Command::Mode { mode } => {
+            client.request(lidar::request::SetMode { lidar_mode: mode as u8 }).await?;
+        }
+        Command::Coordinate { system } => {
+            client.set_coordinate_system(match system {
+                Coordinate::Cartesian => CoordinateSystem::Cartesian,
+                Coordinate::Spherical => CoordinateSystem::Spherical,
+            }).await?;
+        }
+        Command::ReturnMode { mode: Some(mode) } => {
+            client.request(lidar::request::SetLiDARReturnMode { mode: mode as u8 }).await?;
+        }
+        Command::ReturnMode { mode: None } => {
+            let ack = client.request(lidar::request::GetLiDARReturnMode {}).await?;
+            match ReturnMode::value_variants().iter().find(|m| **m as u8 == ack.mode) {
+                Some(mode) => println!("{}", mode.to_possible_value().unwrap().get_name()),
+                None => println!("unknown ({})", ack.mode),
+            }
+        }
+        Command::Fan { state: Some(state) } => {
+            client.request(lidar::request::SetTurnOnOffFan { state: state as u8 }).await?;
+        }
+        Command::Fan { state: None } => {
+            let ack = client.request(lidar::request::GetTurnOnOffFanState {}).await?;
+            println!("{}", if ack.state == 0 { "off" } else { "on" });
+        }
+        Command::RainFog { state } => {
+            client.request(lidar::request::TurnOnOffRainFogSuppression { state: state as u8 }).await?;
+        }
+        Command::Extrinsics { action: ExtrinsicsAction::Get } => {
+            let device = client.read_device_extrinsics().await?;
+            let DeviceExtrinsics { roll, pitch, yaw, x, y, z } = device;
+            println!("roll={} pitch={} yaw={} (deg) x={} y={} z={} (mm)", roll, pitch, yaw, x, y, z);
+            println!("{}", Extrinsics::from(device).to_homogeneous());
+        }
+        Command::Extrinsics { action: ExtrinsicsAction::Set { roll, pitch, yaw, x, y, z } } => {
+            client.write_device_extrinsics(DeviceExtrinsics { roll, pitch, yaw, x, y, z }).await?;
+        }
+        Command::TimeSync { once: true, .. } => {
+            let time = lidar::request::UpdateUTCSynchronizeTime::from_system_time(SystemTime::now());
+            client.request(time).await?;
+        }
+        Command::TimeSync { nmea, period, .. } => {
+            let period = Duration::from_millis(period);
+            match nmea {
+                Some(path) => client.start_time_sync(NmeaClock::open(path)?, period),
+                None => client.start_time_sync(SystemClock, period),
+            }
+            let mut ticker = interval(Duration::from_secs(1));
+            loop {
+                select! {
+                    _ = ticker.tick() => {}
+                    _ = tokio::signal::ctrl_c() => break,
+                }
+                let state = client.time_sync_state().unwrap_or_default();
+                println!("status={:?} offset={} failures={}",
+                         client.time_sync_status(),
+                         state.offset_ns.map_or("-".to_string(), |ns| format!("{:.3}ms", ns as f64 / 1e6)),
+                         state.failures);
+            }
+            client.stop_time_sync();
+        }
+        Command::Stream { stats, duration } => {
+            client.set_sampling(true).await?;
+            stream(client, stats, duration.map(Duration::from_secs)).await?;
+        }
+    }
+    Ok(())
+}
+
+async fn stream(client: &LivoxClient, stats: bool, duration: Option<Duration>) -> Result<(), Box<dyn Error>> {
+    let frames = client.frame_stream();
+    tokio::pin!(frames);
+    let mut ticker = interval(Duration::from_secs(1));
+    ticker.tick().await;
+    let end = duration.map(|d| Instant::now() + d);
+    let (mut packets, mut points, mut returns, mut errors) = (0usize, 0usize, 0usize, 0usize);
+    let mut last = None;
+
+    loop {
+        select! {
+            frame = frames.next() => match frame {
+                Some(Ok(frame)) => {
+                    packets += 1;
+                    let frame_points = frame.data.points();
+                    points += frame_points.len();
+                    returns += frame_points.iter().filter(|p| !p.is_zero()).count();
+                    last = Some(frame);
+                }
+                Some(Err(err)) => {
+                    errors += 1;
+                    tracing::warn!("Bad frame: {}", err);
+                }
+                None => break,
+            },
+            _ = ticker.tick() => {
+                if stats {
+                    let (timestamp_type, timestamp, status) = match &last {
+                        Some(f) => (f.timestamp_type, f.timestamp, format!("system={} sync={}",
+                            f.status_code.system_status, f.status_code.time_sync_status)),
+                        None => (0, 0, "-".to_string()),
+                    };
+                    println!("{} packets/s, {} points/s ({} returns), {} errors, ts_type={} ts={} {}",
+                             packets, points, returns, errors, timestamp_type, timestamp, status);
+                }
+                (packets, points, returns, errors) = (0, 0, 0, 0);
+                if end.is_some_and(|end| Instant::now() >= end) { break; }
+            }
+            _ = tokio::signal::ctrl_c() => break,
+        }
+    }
+    Ok(())
+}
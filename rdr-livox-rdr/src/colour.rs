@@ -0,0 +1,85 @@
+use std::io::Cursor;
+use std::sync::Arc;
+use std::time::Duration;
+use bytes::Bytes;
+use tokio::sync::{mpsc, Mutex};
+use tracing::{info, warn};
+
+use rdr_zeromq::client::EncodedImgClient;
+use livox_rs::clock_align::now_ns;
+use livox_rs::cloud::PointCloud;
+use livox_rs::export::PlyWriter;
+use livox_rs::projection::Camera;
+use livox_rs::projection::colour::{Colouriser, RgbFrame};
+
+use rdr_livox::config::ColourConfig;
+use rdr_livox::sink::{Output, Sinks};
+
+/// Receive camera frames, and colour the clouds sent on `clouds`, their points timestamped
+/// by host time. Coloured clouds are sent to the sinks and written to disk as PLY.
+pub async fn run(config: ColourConfig, camera: Camera, mut clouds: mpsc::Receiver<PointCloud>, sinks: Arc<Sinks>) {
+    let colouriser = Colouriser::new(camera)
+        .with_max_offset(Duration::from_millis(config.max_offset_ms))
+        .with_occlusion(config.occlusion);
+    let colouriser = Arc::new(Mutex::new(colouriser));
+    tokio::spawn(receive_frames(config.camera_endpoint.clone(), config.camera_latency_ms, colouriser.clone()));
+
+    if let Some(dir) = &config.ply_dir {
+        if let Err(err) = std::fs::create_dir_all(dir) {
+            warn!("Failed to create {}: {:?}", dir.display(), err);
+        }
+    }
+
+    while let Some(cloud) = clouds.recv().await {
+        let colouriser = colouriser.clone();
+        let ply_dir = config.ply_dir.clone();
+        let total = cloud.len();
+        let timestamp = cloud.time_span().map_or(0, |(first, _)| first);
+        let start_time = tokio::time::Instant::now();
+        let ply = tokio_rayon::spawn(move || {
+            let points = colouriser.blocking_lock().colourise(&cloud);
+            let mut writer = PlyWriter::with_colour(Cursor::new(Vec::new()))?;
+            writer.write_coloured_points(&points)?;
+            let ply = writer.into_inner()?.into_inner();
+            if let (Some(dir), Some((first, _))) = (ply_dir, cloud.time_span()) {
+                let path = dir.join(format!("{}.ply", first));
+                if let Err(err) = std::fs::write(&path, &ply) {
+                    warn!("Failed to write {}: {:?}", path.display(), err);
+                }
+            }
+            livox_rs::LivoxResult::Ok((points.len(), ply))
+        }).await;
+        let (count, ply) = match ply {
+            Ok(ply) => ply,
+            Err(err) => {
+                warn!("Failed to colour point cloud: {}", err);
+                continue;
+            }
+        };
+        info!("Coloured {} of {} points in {}ms", count, total, start_time.elapsed().as_millis());
+        sinks.send(Output::ColouredCloud { timestamp, data: Bytes::from(ply) });
+    }
+}
+
+/// Decode frames as they arrive, timestamped by their estimated capture time.
+async fn receive_frames(endpoint: String, latency_ms: u64, colouriser: Arc<Mutex<Colouriser>>) {
+    let mut client = EncodedImgClient::new(&endpoint).await;
+    loop {
+        let bytes = match client.recv_img().await {
+            Ok(bytes) => bytes,
+            Err(err) => {
+                warn!("Failed to receive camera frame: {:?}", err);
+                continue;
+            }
+        };
+        let timestamp = now_ns().saturating_sub(latency_ms * 1_000_000);
+        let frame = tokio_rayon::spawn(move || {
+            let image = image::load_from_memory(&bytes)?.into_rgb8();
+            Ok::<_, image::ImageError>(RgbFrame::new(image.width(), image.height(), image.into_raw(), timestamp).unwrap())
+        }).await;
+        match frame {
+            Ok(frame) => colouriser.lock().await.push_frame(frame),
+            Err(err) => warn!("Failed to decode camera frame: {}", err),
+        }
+    }
+}
@@ -0,0 +1,24 @@
+use std::error::Error;
+use std::sync::Arc;
+use clap::Parser;
+
+use rdr_livox::{Args, Extensions};
+
+mod colour;
+mod sink;
+
+/// rdr-livox publishing over the rdr ZeroMQ endpoints, and colouring points with camera frames received over rdr.
+#[tokio::main]
+async fn main() -> Result<(), Box<dyn Error>> {
+    let subscriber = tracing_subscriber::FmtSubscriber::new();
+    tracing::subscriber::set_global_default(subscriber)?;
+    let config = Arc::new(Args::parse().config()?);
+    let (colour_config, camera) = (config.colour.clone(), config.camera()?);
+    let extensions = Extensions {
+        sinks: vec![("rdr".into(), Box::new(sink::RdrSink::new(&config).await))],
+        colour: Some(Box::new(move |clouds, sinks| {
+            tokio::spawn(colour::run(colour_config, camera, clouds, sinks));
+        })),
+    };
+    rdr_livox::run(config, extensions).await
+}
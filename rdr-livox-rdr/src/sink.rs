@@ -0,0 +1,157 @@
+use std::io;
+use std::path::PathBuf;
+use async_trait::async_trait;
+use tracing::{info, warn};
+
+use rdr_zeromq::prelude::{Message, Timestamp};
+use rdr_zeromq::prelude::lidar::{DepthPixel, LiDARDepthPixels, LiDARRawPoints, RawPoint};
+use rdr_zeromq::server::{EncodedImgServer, LiDARServer};
+use rdr_zeromq::traits::Server;
+
+use rdr_livox::config::Config;
+use rdr_livox::encoding::DepthEncoding;
+use rdr_livox::message::{self, Messages};
+use rdr_livox::points::PointsFrame;
+use rdr_livox::sink::{Output, OutputKind, Sink};
+
+/// The protobuf messages of rdr, mapped by [`rdr_livox::message`].
+pub struct Rdr;
+
+impl Messages for Rdr {
+    type Timestamp = Timestamp;
+    type RawPoint = RawPoint;
+    type RawPoints = LiDARRawPoints;
+    type DepthPixel = DepthPixel;
+    type DepthPixels = LiDARDepthPixels;
+
+    fn timestamp(seconds: i64, nanos: i32) -> Timestamp {
+        Timestamp { seconds, nanos, ..Timestamp::default() }
+    }
+
+    fn raw_point(x: f32, y: f32, z: f32, reflectivity: u32, tag: u32) -> RawPoint {
+        RawPoint { x, y, z, reflectivity, tag, ..RawPoint::default() }
+    }
+
+    fn raw_points(timestamp: Timestamp, sensor_id: String, points: Vec<RawPoint>) -> LiDARRawPoints {
+        LiDARRawPoints { timestamp: Some(timestamp).into(), sensor_id, points, ..LiDARRawPoints::default() }
+    }
+
+    fn depth_pixel(x: i32, y: i32, z: f32) -> DepthPixel {
+        DepthPixel { x, y, z, ..DepthPixel::default() }
+    }
+
+    fn depth_pixels(timestamp: Timestamp, pixels: Vec<DepthPixel>) -> LiDARDepthPixels {
+        LiDARDepthPixels { timestamp: Some(timestamp).into(), pixels, ..LiDARDepthPixels::default() }
+    }
+}
+
+/// The first seconds of points, written to a file once.
+struct Recording {
+    path: PathBuf,
+    duration_ns: u64,
+    msg: LiDARRawPoints,
+    start: Option<u64>,
+}
+
+/// Publishes over the rdr ZeroMQ servers of `[endpoints]`, and records the first seconds of points.
+pub struct RdrSink {
+    depth_pixels: Option<LiDARServer>,
+    raw_points: Option<LiDARServer>,
+    depth_images: Vec<(EncodedImgServer, DepthEncoding)>,
+    coloured_clouds: Option<EncodedImgServer>,
+    recording: Option<Recording>,
+}
+
+impl RdrSink {
+    pub async fn new(config: &Config) -> Self {
+        let endpoints = &config.endpoints;
+        let depth_pixels = match config.publish.depth_pixels {
+            true => Some(LiDARServer::new(&endpoints.depth_pixels).await),
+            false => None,
+        };
+        let raw_points = match config.publish.raw_points {
+            true => Some(LiDARServer::new(&endpoints.raw_points).await),
+            false => None,
+        };
+        let mut depth_images = Vec::new();
+        for image in &endpoints.depth_images {
+            depth_images.push((EncodedImgServer::new(&image.endpoint).await, image.encoding));
+        }
+        let coloured_clouds = match (config.colour.enabled, &config.colour.endpoint) {
+            (true, Some(endpoint)) => Some(EncodedImgServer::new(endpoint).await),
+            _ => None,
+        };
+        let recording = config.recording.enabled.then(|| Recording {
+            path: config.recording.path.clone(),
+            duration_ns: config.recording.duration_secs * 1_000_000_000,
+            msg: LiDARRawPoints::new(),
+            start: None,
+        });
+        RdrSink { depth_pixels, raw_points, depth_images, coloured_clouds, recording }
+    }
+
+    fn record(&mut self, frame: &PointsFrame) {
+        let Some(recording) = &mut self.recording else { return; };
+        let start = *recording.start.get_or_insert(frame.timestamp);
+        if recording.msg.points.is_empty() {
+            recording.msg = message::raw_points::<Rdr>(frame);
+        } else {
+            recording.msg.points.extend(frame.points.iter().map(message::raw_point::<Rdr>));
+        }
+        if frame.timestamp.saturating_sub(start) < recording.duration_ns { return; }
+
+        let seconds = recording.duration_ns / 1_000_000_000;
+        match std::fs::File::create(&recording.path) {
+            Ok(mut file) => {
+                match recording.msg.write_to_writer(&mut file) {
+                    Ok(_) => info!("Successfully cached {}s of pc.", seconds),
+                    Err(err) => warn!("Cache {}s failed! {:?}", seconds, err),
+                }
+            }
+            Err(err) => warn!("Failed to open file to cache! {:?}", err),
+        }
+        self.recording = None;
+    }
+}
+
+#[async_trait]
+impl Sink for RdrSink {
+    fn accepts(&self, kind: OutputKind) -> bool {
+        match kind {
+            OutputKind::Packets | OutputKind::Batches => false,
+            OutputKind::DepthPixels => self.depth_pixels.is_some(),
+            OutputKind::Points => self.raw_points.is_some() || self.recording.is_some(),
+            OutputKind::DepthImages => !self.depth_images.is_empty(),
+            OutputKind::ColouredClouds => self.coloured_clouds.is_some(),
+        }
+    }
+
+    fn depth_encodings(&self) -> Vec<DepthEncoding> {
+        self.depth_images.iter().map(|(_, encoding)| *encoding).collect()
+    }
+
+    async fn send(&mut self, output: &Output) -> io::Result<()> {
+        match output {
+            Output::Packet(_) | Output::Batch(_) => {}
+            Output::DepthPixels { batch, projections } => if let Some(server) = &mut self.depth_pixels {
+                let msg = message::depth_pixels::<Rdr>(batch, projections);
+                server.send(&msg).await?;
+            }
+            Output::Points(frame) => {
+                self.record(frame);
+                if let Some(server) = &mut self.raw_points {
+                    server.send(&message::raw_points::<Rdr>(frame)).await?;
+                }
+            }
+            Output::DepthImage { encoding, data, .. } => {
+                for (server, _) in self.depth_images.iter_mut().filter(|(_, e)| e == encoding) {
+                    server.send_img(data.clone()).await?;
+                }
+            }
+            Output::ColouredCloud { data, .. } => if let Some(server) = &mut self.coloured_clouds {
+                server.send_img(data.clone()).await?;
+            }
+        }
+        Ok(())
+    }
+}
@@ -0,0 +1,192 @@
+use std::path::Path;
+use nalgebra::{Isometry3, Matrix3, Rotation3, Translation3, UnitQuaternion};
+use serde::Deserialize;
+
+use livox_rs::projection::{Camera, Distortion, Intrinsics};
+
+use crate::config::ConfigError;
+
+/// Matrix as written by OpenCV's `FileStorage`, tagged `!!opencv-matrix`.
+#[derive(Debug, Deserialize)]
+struct OpenCvMatrix {
+    rows: usize,
+    cols: usize,
+    data: Vec<f64>,
+}
+
+/// Camera calibration in OpenCV YAML, with the LiDAR to camera transform (translation in metres).
+///
+/// ```yaml
+/// %YAML:1.0
+/// ---
+/// image_width: 3072
+/// image_height: 2048
+/// camera_matrix: !!opencv-matrix
+///    rows: 3
+///    cols: 3
+///    dt: d
+///    data: [ 2580.7, 0., 1535.9, 0., 2582.8, 1008.7, 0., 0., 1. ]
+/// distortion_model: plumb_bob # or fisheye
+/// distortion_coefficients: !!opencv-matrix
+///    rows: 1
+///    cols: 5
+///    dt: d
+///    data: [ -0.1, 0.05, 0., 0., 0. ]
+/// lidar_to_camera: !!opencv-matrix
+///    rows: 3 # or 4
+///    cols: 4
+///    dt: d
+///    data: [ ... ]
+/// ```
+#[derive(Debug, Deserialize)]
+struct CalibrationFile {
+    image_width: Option<u32>,
+    image_height: Option<u32>,
+    camera_matrix: OpenCvMatrix,
+    distortion_model: Option<String>,
+    distortion_coefficients: Option<OpenCvMatrix>,
+    lidar_to_camera: OpenCvMatrix,
+}
+
+/// Load a calibration file, resizing the camera to `size` where set.
+/// The intrinsics are scaled from the file's image size, so `size` is required when the file has none.
+pub fn load(path: &Path, size: (Option<u32>, Option<u32>)) -> Result<Camera, ConfigError> {
+    let text = std::fs::read_to_string(path).map_err(|source| ConfigError::Io { path: path.to_owned(), source })?;
+    parse(&text, size).map_err(|err| err.in_file(path))
+}
+
+fn parse(text: &str, size: (Option<u32>, Option<u32>)) -> Result<Camera, ConfigError> {
+    // OpenCV's directive and tags are not plain YAML
+    let text = text.strip_prefix("%YAML:1.0").unwrap_or(text).replace("!!opencv-matrix", "");
+    let file: CalibrationFile = crate::config::deserialize(serde_yaml::Deserializer::from_str(&text))?;
+
+    let invalid = |key: &str, reason: String| ConfigError::invalid(key, reason);
+    let matrix = |key: &str, m: &OpenCvMatrix, shapes: &[(usize, usize)]| {
+        if !shapes.contains(&(m.rows, m.cols)) {
+            return Err(invalid(key, format!("expected a {:?} matrix, got {}x{}", shapes, m.rows, m.cols)));
+        }
+        if m.data.len() != m.rows * m.cols {
+            return Err(invalid(key, format!("{}x{} matrix with {} values", m.rows, m.cols, m.data.len())));
+        }
+        Ok(m.data.iter().map(|v| *v as f32).collect::<Vec<_>>())
+    };
+
+    let k = matrix("camera_matrix", &file.camera_matrix, &[(3, 3)])?;
+    if k[0] <= 0.0 || k[4] <= 0.0 {
+        return Err(invalid("camera_matrix", "focal lengths must be positive".into()));
+    }
+
+    let coefficients = match &file.distortion_coefficients {
+        Some(m) => matrix("distortion_coefficients", m, &[(1, m.cols), (m.rows, 1)])?,
+        None => vec![],
+    };
+    let c = |i: usize| coefficients.get(i).copied().unwrap_or(0.0);
+    let distortion = match file.distortion_model.as_deref() {
+        _ if coefficients.iter().all(|c| *c == 0.0) => Distortion::None,
+        None | Some("plumb_bob" | "radtan") if coefficients.len() <= 5 =>
+            Distortion::RadialTangential { k1: c(0), k2: c(1), p1: c(2), p2: c(3), k3: c(4) },
+        None | Some("plumb_bob" | "radtan") =>
+            return Err(invalid("distortion_coefficients", format!("{} coefficients, at most 5 are supported", coefficients.len()))),
+        Some("fisheye" | "equidistant") if coefficients.len() == 4 =>
+            Distortion::Fisheye { k1: c(0), k2: c(1), k3: c(2), k4: c(3) },
+        Some("fisheye" | "equidistant") =>
+            return Err(invalid("distortion_coefficients", format!("{} coefficients, fisheye takes 4", coefficients.len()))),
+        Some(model) => return Err(invalid("distortion_model", format!("unknown model {:?}", model))),
+    };
+
+    let rt = matrix("lidar_to_camera", &file.lidar_to_camera, &[(3, 4), (4, 4)])?;
+    let rotation = Matrix3::new(rt[0], rt[1], rt[2], rt[4], rt[5], rt[6], rt[8], rt[9], rt[10]);
+    if (rotation.transpose() * rotation - Matrix3::identity()).norm() > 1e-3 || rotation.determinant() < 0.0 {
+        return Err(invalid("lidar_to_camera", "rotation part is not a rotation".into()));
+    }
+    let translation = Translation3::new(rt[3], rt[7], rt[11]);
+
+    let intrinsics = |width, height| Intrinsics { fx: k[0], fy: k[4], cx: k[2], cy: k[5], width, height, distortion };
+    let intrinsics = match (file.image_width, file.image_height, size) {
+        (Some(width), Some(height), _) => intrinsics(width, height).resized(size.0.unwrap_or(width), size.1.unwrap_or(height)),
+        // without the calibrated size, the intrinsics are taken as they are
+        (_, _, (Some(width), Some(height))) => intrinsics(width, height),
+        (None, _, _) => return Err(invalid("image_width", "missing, and the configuration does not set the image size".into())),
+        (_, None, _) => return Err(invalid("image_height", "missing, and the configuration does not set the image size".into())),
+    };
+    let rotation = UnitQuaternion::from_rotation_matrix(&Rotation3::from_matrix(&rotation));
+    Ok(Camera::new(intrinsics, Isometry3::from_parts(translation, rotation)))
+}
+
+/// Calibration of our camera, used when none is configured.
+pub fn default_camera() -> Camera {
+    let rotation = Matrix3::new(0.0185759, -0.999824, 0.00251985,
+                                0.0174645, -0.00219543, -0.999675,
+                                0.999675, 0.018617, 0.0174206);
+    let translation = Translation3::new(-0.0904854, -0.132904, -0.421934);
+    let intrinsics = Intrinsics {
+        fx: 2580.738,
+        fy: 2582.884,
+        cx: 1535.983,
+        cy: 1008.7849,
+        width: 3072,
+        height: 2048,
+        distortion: Distortion::None,
+    };
+    Camera::new(intrinsics, Isometry3::from_parts(translation, UnitQuaternion::from_matrix(&rotation)))
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    const CALIBRATION: &str = "%YAML:1.0
+---
+image_width: 3072
+image_height: 2048
+camera_matrix: !!opencv-matrix
+   rows: 3
+   cols: 3
+   dt: d
+   data: [ 2580.7380664637653, 0., 1535.9830165125002, 0.,
+       2582.8839945792183, 1008.784910706948, 0., 0., 1. ]
+distortion_model: plumb_bob
+distortion_coefficients: !!opencv-matrix
+   rows: 1
+   cols: 5
+   dt: d
+   data: [ -0.1, 0.05, 0., 0., 0. ]
+lidar_to_camera: !!opencv-matrix
+   rows: 3
+   cols: 4
+   dt: d
+   data: [ 0.0185759, -0.999824, 0.00251985, -0.0904854,
+       0.0174645, -0.00219543, -0.999675, -0.132904,
+       0.999675, 0.018617, 0.0174206, -0.421934 ]
+";
+
+    #[test]
+    fn test_parse() {
+        let camera = parse(CALIBRATION, (None, None)).unwrap();
+        let default = default_camera();
+        assert_eq!((camera.width(), camera.height()), (3072, 2048));
+        assert_eq!(camera.intrinsics.distortion, Distortion::RadialTangential { k1: -0.1, k2: 0.05, p1: 0.0, p2: 0.0, k3: 0.0 });
+        assert!((camera.projection_matrix() - default.projection_matrix()).norm() < 1e-2);
+
+        let resized = parse(CALIBRATION, (Some(1536), Some(1024))).unwrap();
+        assert_eq!((resized.width(), resized.height()), (1536, 1024));
+        assert!((resized.intrinsics.fx - camera.intrinsics.fx / 2.0).abs() < 1e-3);
+        assert!((resized.intrinsics.cy - camera.intrinsics.cy / 2.0).abs() < 1e-3);
+        let resized = parse(CALIBRATION, (Some(1536), None)).unwrap();
+        assert_eq!((resized.width(), resized.height()), (1536, 2048));
+        assert!((resized.intrinsics.fy - camera.intrinsics.fy).abs() < 1e-3);
+        let no_size = CALIBRATION.replace("image_width: 3072\nimage_height: 2048\n", "");
+        assert_eq!(parse(&no_size, (Some(640), Some(480))).unwrap().intrinsics.fx, camera.intrinsics.fx);
+
+        let err = parse(&CALIBRATION.replace("rows: 1\n   cols: 5", "rows: 1\n   cols: 4"), (None, None)).unwrap_err();
+        assert!(err.to_string().contains("distortion_coefficients"), "{}", err);
+        let err = parse(&CALIBRATION.replace("plumb_bob", "fisheye"), (None, None)).unwrap_err();
+        assert!(err.to_string().contains("distortion_coefficients"), "{}", err);
+        let err = parse(&CALIBRATION.replace("rows: 3\n   cols: 3", "rows: 3\n   cols: 2"), (None, None)).unwrap_err();
+        assert!(err.to_string().contains("camera_matrix"), "{}", err);
+        let err = parse(&CALIBRATION.replace("0.999675, 0.018617", "0.5, 0.018617"), (None, None)).unwrap_err();
+        assert!(err.to_string().contains("lidar_to_camera"), "{}", err);
+        let err = parse(&CALIBRATION.replace("image_width: 3072\n", ""), (Some(640), None)).unwrap_err();
+        assert!(err.to_string().contains("image_width"), "{}", err);
+    }
+}
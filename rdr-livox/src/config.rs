@@ -0,0 +1,517 @@
+use std::fmt::{Display, Formatter};
+use std::net::{IpAddr, Ipv4Addr, SocketAddr};
+use std::path::{Path, PathBuf};
+use std::str::FromStr;
+use std::time::Duration;
+use serde::Deserialize;
+
+use livox_rs::{HandshakeOption, Livox};
+use livox_rs::depth::densify::Densify;
+use livox_rs::filter::crop::CropFilter;
+use livox_rs::filter::PointFilter;
+use livox_rs::projection::Camera;
+use livox_rs::projection::colour::Occlusion;
+
+use crate::calibration;
+use crate::encoding::DepthEncoding;
+use crate::sink::OutputKind;
+
+#[derive(Debug)]
+pub enum ConfigError {
+    Io { path: PathBuf, source: std::io::Error },
+    /// `key` is the dotted path to the offending value.
+    Invalid { path: Option<PathBuf>, key: String, reason: String },
+}
+
+impl ConfigError {
+    pub fn invalid(key: impl Into<String>, reason: impl Into<String>) -> Self {
+        ConfigError::Invalid { path: None, key: key.into(), reason: reason.into() }
+    }
+
+    /// Attach the file the error comes from.
+    pub fn in_file(self, file: &Path) -> Self {
+        match self {
+            ConfigError::Invalid { path: None, key, reason } => ConfigError::Invalid { path: Some(file.to_owned()), key, reason },
+            err => err,
+        }
+    }
+}
+
+impl Display for ConfigError {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        match self {
+            ConfigError::Io { path, source } => write!(f, "{}: {}", path.display(), source),
+            ConfigError::Invalid { path: Some(path), key, reason } => write!(f, "{}: `{}`: {}", path.display(), key, reason),
+            ConfigError::Invalid { path: None, key, reason } => write!(f, "`{}`: {}", key, reason),
+        }
+    }
+}
+
+impl std::error::Error for ConfigError {}
+
+/// Deserialize, reporting the path to the value that failed.
+pub fn deserialize<'de, T: Deserialize<'de>>(deserializer: impl serde::Deserializer<'de>) -> Result<T, ConfigError> {
+    serde_path_to_error::deserialize(deserializer).map_err(|err| {
+        let key = err.path().to_string();
+        ConfigError::invalid(key, err.into_inner().to_string())
+    })
+}
+
+/// Configuration of rdr-livox, loaded from TOML or YAML. Every key is optional.
+#[derive(Debug, Clone, Default, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct Config {
+    pub device: DeviceConfig,
+    pub handshake: HandshakeConfig,
+    pub calibration: CalibrationConfig,
+    pub image: ImageConfig,
+    pub publish: PublishConfig,
+    pub endpoints: EndpointConfig,
+    pub recording: RecordingConfig,
+    pub colour: ColourConfig,
+    pub filter: PointFilter,
+    pub crop: CropFilter,
+    /// Outputs besides rdr, see [`SinkConfig`].
+    pub sinks: Vec<SinkConfig>,
+}
+
+#[derive(Debug, Clone, Default, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct DeviceConfig {
+    /// Broadcast code or IP address of the LiDAR, the first one found if unset.
+    pub target: Option<String>,
+}
+
+#[derive(Debug, Clone, Default, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct HandshakeConfig {
+    pub host_ip: Option<Ipv4Addr>,
+    pub cmd_port: Option<u16>,
+    pub data_port: Option<u16>,
+    pub imu_port: Option<u16>,
+}
+
+#[derive(Debug, Clone, Default, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct CalibrationConfig {
+    /// OpenCV YAML calibration, see [`calibration::load`]. Relative to the configuration file.
+    pub file: Option<PathBuf>,
+}
+
+#[derive(Debug, Clone, Default, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct ImageConfig {
+    /// Resize the images, scaling the intrinsics of the calibration.
+    pub width: Option<u32>,
+    pub height: Option<u32>,
+}
+
+#[derive(Debug, Clone, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct PublishConfig {
+    /// Send the depth pixels of every packet.
+    pub depth_pixels: bool,
+    /// Send the points in frames of `raw_points_period_ms` of sensor time.
+    pub raw_points: bool,
+    pub raw_points_period_ms: u64,
+    /// Returns older than this age out of the depth graph.
+    pub depth_window_ms: u64,
+    pub depth_graph_period_ms: u64,
+    /// Fill the depth graph between the sparse returns, `None` to publish it as is.
+    pub densify: Option<Densify>,
+}
+
+impl Default for PublishConfig {
+    fn default() -> Self {
+        PublishConfig {
+            depth_pixels: true,
+            raw_points: true,
+            raw_points_period_ms: 100,
+            depth_window_ms: 1000,
+            depth_graph_period_ms: 1000,
+            densify: Some(Densify::default()),
+        }
+    }
+}
+
+#[derive(Debug, Clone, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct EndpointConfig {
+    /// Where depth images are published, each with its own encoding.
+    pub depth_images: Vec<DepthImageEndpoint>,
+    pub depth_pixels: String,
+    pub raw_points: String,
+}
+
+impl Default for EndpointConfig {
+    fn default() -> Self {
+        EndpointConfig {
+            depth_images: vec![DepthImageEndpoint { endpoint: "tcp://0.0.0.0:8100".into(), encoding: DepthEncoding::Png16 }],
+            depth_pixels: "tcp://0.0.0.0:8200".into(),
+            raw_points: "tcp://0.0.0.0:8400".into(),
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct DepthImageEndpoint {
+    pub endpoint: String,
+    #[serde(default)]
+    pub encoding: DepthEncoding,
+}
+
+impl FromStr for DepthImageEndpoint {
+    type Err = String;
+
+    /// `ENDPOINT[,ENCODING]`
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let (endpoint, encoding) = match s.split_once(',') {
+            Some((endpoint, encoding)) => (endpoint, encoding.parse()?),
+            None => (s, DepthEncoding::default()),
+        };
+        Ok(DepthImageEndpoint { endpoint: endpoint.into(), encoding })
+    }
+}
+
+#[derive(Debug, Clone, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct RecordingConfig {
+    /// Dump the raw points of the first seconds.
+    pub enabled: bool,
+    pub path: PathBuf,
+    pub duration_secs: u64,
+}
+
+impl Default for RecordingConfig {
+    fn default() -> Self {
+        RecordingConfig { enabled: true, path: "point_cloud.buf".into(), duration_secs: 10 }
+    }
+}
+
+#[derive(Debug, Clone, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct ColourConfig {
+    /// Colour the points with the frames of the calibrated camera.
+    pub enabled: bool,
+    /// Where JPEG or PNG camera frames are received from.
+    pub camera_endpoint: String,
+    /// Time from capture to arrival of a frame, frames are matched to points by capture time.
+    pub camera_latency_ms: u64,
+    /// Points farther in time than this from every frame are left out.
+    pub max_offset_ms: u64,
+    /// Points of every period are coloured together.
+    pub period_ms: u64,
+    /// Leave out points hidden from the camera, `None` to colour them anyway.
+    pub occlusion: Option<Occlusion>,
+    /// Where coloured clouds are published, as PLY.
+    pub endpoint: Option<String>,
+    /// Directory to write every coloured cloud to, as PLY.
+    pub ply_dir: Option<PathBuf>,
+}
+
+impl Default for ColourConfig {
+    fn default() -> Self {
+        ColourConfig {
+            enabled: false,
+            camera_endpoint: "tcp://127.0.0.1:8000".into(),
+            camera_latency_ms: 0,
+            max_offset_ms: 50,
+            period_ms: 1000,
+            occlusion: Some(Occlusion::default()),
+            endpoint: Some("tcp://0.0.0.0:8300".into()),
+            ply_dir: None,
+        }
+    }
+}
+
+/// A sink outputs are sent to, besides the rdr endpoints.
+#[derive(Debug, Clone, PartialEq, Deserialize)]
+#[serde(tag = "type", rename_all = "snake_case", deny_unknown_fields)]
+pub enum SinkConfig {
+    /// Forward LiDAR packets as they are to a UDP address, e.g. another host.
+    Udp { target: SocketAddr },
+    /// Stream outputs to TCP clients, see [`crate::sink::tcp::TcpSink`].
+    Tcp {
+        listen: SocketAddr,
+        #[serde(default = "SinkConfig::default_outputs")]
+        outputs: Vec<OutputKind>,
+        #[serde(default)]
+        depth_encoding: DepthEncoding,
+    },
+    /// Capture LiDAR packets to a pcap file.
+    File { path: PathBuf },
+    /// Write LiDAR packets or filtered point batches to a shared-memory ring buffer,
+    /// `/dev/shm/<name>` unless `name` is a path, or a memfd named `name`.
+    Shm {
+        name: String,
+        #[serde(default = "SinkConfig::default_slots")]
+        slots: u32,
+        #[serde(default)]
+        memfd: bool,
+        #[serde(default = "SinkConfig::default_shm_outputs")]
+        outputs: Vec<OutputKind>,
+    },
+}
+
+impl SinkConfig {
+    fn default_outputs() -> Vec<OutputKind> {
+        vec![OutputKind::Packets]
+    }
+
+    fn default_slots() -> u32 {
+        4096
+    }
+
+    fn default_shm_outputs() -> Vec<OutputKind> {
+        vec![OutputKind::Batches]
+    }
+}
+
+impl Display for SinkConfig {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        match self {
+            SinkConfig::Udp { target } => write!(f, "udp:{}", target),
+            SinkConfig::Tcp { listen, .. } => write!(f, "tcp:{}", listen),
+            SinkConfig::File { path } => write!(f, "file:{}", path.display()),
+            SinkConfig::Shm { name, memfd: true, .. } => write!(f, "shm:memfd:{}", name),
+            SinkConfig::Shm { name, .. } => write!(f, "shm:{}", name),
+        }
+    }
+}
+
+impl FromStr for SinkConfig {
+    type Err = String;
+
+    /// `TYPE:ARG`, e.g. `udp:192.168.1.10:56001`, `tcp:0.0.0.0:8500`, `file:out.pcap`, `shm:livox`
+    /// or `shm:memfd:livox`.
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let (kind, arg) = s.split_once(':').ok_or_else(|| format!("`{}` is not TYPE:ARG", s))?;
+        let addr = |arg: &str| arg.parse::<SocketAddr>().map_err(|err| format!("`{}`: {}", arg, err));
+        match kind {
+            "udp" => Ok(SinkConfig::Udp { target: addr(arg)? }),
+            "tcp" => Ok(SinkConfig::Tcp { listen: addr(arg)?, outputs: Self::default_outputs(), depth_encoding: DepthEncoding::default() }),
+            "file" => Ok(SinkConfig::File { path: arg.into() }),
+            "shm" => {
+                let (name, memfd) = match arg.strip_prefix("memfd:") {
+                    Some(name) => (name, true),
+                    None => (arg, false),
+                };
+                Ok(SinkConfig::Shm { name: name.into(), slots: Self::default_slots(), memfd, outputs: Self::default_shm_outputs() })
+            }
+            _ => Err(format!("unknown sink type `{}`, expected udp, tcp, file or shm", kind)),
+        }
+    }
+}
+
+impl Config {
+    /// Load a TOML or YAML file, by extension.
+    pub fn load(path: &Path) -> Result<Config, ConfigError> {
+        let text = std::fs::read_to_string(path).map_err(|source| ConfigError::Io { path: path.to_owned(), source })?;
+        let mut config: Config = match path.extension().and_then(|e| e.to_str()) {
+            Some("yaml" | "yml") => deserialize(serde_yaml::Deserializer::from_str(&text)),
+            _ => deserialize(&mut toml::Deserializer::new(&text)),
+        }.map_err(|err| err.in_file(path))?;
+
+        if let (Some(file), Some(dir)) = (&config.calibration.file, path.parent()) {
+            config.calibration.file = Some(dir.join(file));
+        }
+        Ok(config)
+    }
+
+    /// Check values serde cannot, e.g. after command line overrides.
+    pub fn validate(&self) -> Result<(), ConfigError> {
+        let check = |ok: bool, key: &str, reason: &str| if ok { Ok(()) } else { Err(ConfigError::invalid(key, reason)) };
+
+        if let Some(target) = &self.device.target {
+            // broadcast codes are 15 characters
+            check(target.parse::<IpAddr>().is_ok() || target.len() == 15,
+                  "device.target", "neither an IP address nor a broadcast code")?;
+        }
+        check(self.image.width != Some(0), "image.width", "must be positive")?;
+        check(self.image.height != Some(0), "image.height", "must be positive")?;
+        check(self.publish.raw_points_period_ms > 0, "publish.raw_points_period_ms", "must be positive")?;
+        check(self.publish.depth_window_ms > 0, "publish.depth_window_ms", "must be positive")?;
+        check(self.publish.depth_graph_period_ms > 0, "publish.depth_graph_period_ms", "must be positive")?;
+        if let Some(densify) = &self.publish.densify {
+            check(densify.radius > 0, "publish.densify.radius", "must be positive")?;
+            check(densify.max_depth_jump >= 0.0, "publish.densify.max_depth_jump", "must not be negative")?;
+        }
+        for (i, image) in self.endpoints.depth_images.iter().enumerate() {
+            if !image.endpoint.contains("://") {
+                return Err(ConfigError::invalid(format!("endpoints.depth_images[{}].endpoint", i), "not a ZeroMQ endpoint"));
+            }
+        }
+        check(self.endpoints.depth_pixels.contains("://"), "endpoints.depth_pixels", "not a ZeroMQ endpoint")?;
+        check(self.endpoints.raw_points.contains("://"), "endpoints.raw_points", "not a ZeroMQ endpoint")?;
+        check(!self.recording.enabled || self.recording.duration_secs > 0, "recording.duration_secs", "must be positive")?;
+        if self.colour.enabled {
+            let colour = &self.colour;
+            check(colour.camera_endpoint.contains("://"), "colour.camera_endpoint", "not a ZeroMQ endpoint")?;
+            check(colour.max_offset_ms > 0, "colour.max_offset_ms", "must be positive")?;
+            check(colour.period_ms > 0, "colour.period_ms", "must be positive")?;
+            check(colour.endpoint.as_ref().is_none_or(|e| e.contains("://")), "colour.endpoint", "not a ZeroMQ endpoint")?;
+            if let Some(occlusion) = &colour.occlusion {
+                check(occlusion.tolerance >= 0.0, "colour.occlusion.tolerance", "must not be negative")?;
+            }
+        }
+        for (i, sink) in self.sinks.iter().enumerate() {
+            match sink {
+                SinkConfig::Tcp { outputs, .. } => {
+                    let streamable = [OutputKind::Packets, OutputKind::DepthImages, OutputKind::ColouredClouds];
+                    check(outputs.iter().all(|kind| streamable.contains(kind)), &format!("sinks[{}].outputs", i),
+                          "only packets, depth_images and coloured_clouds can be streamed")?;
+                }
+                SinkConfig::Shm { name, slots, outputs, .. } => {
+                    check(!name.is_empty(), &format!("sinks[{}].name", i), "must not be empty")?;
+                    check(*slots > 0, &format!("sinks[{}].slots", i), "must be positive")?;
+                    check(outputs.iter().all(|kind| [OutputKind::Packets, OutputKind::Batches].contains(kind)),
+                          &format!("sinks[{}].outputs", i), "only packets and batches can be written")?;
+                }
+                SinkConfig::Udp { .. } | SinkConfig::File { .. } => {}
+            }
+        }
+        if let (Some(min), Some(max)) = (self.filter.min_range, self.filter.max_range) {
+            check(min <= max, "filter.max_range", "smaller than filter.min_range")?;
+        }
+        Ok(())
+    }
+
+    /// Whether a device is the configured one.
+    pub fn matches(&self, livox: &Livox) -> bool {
+        match &self.device.target {
+            None => true,
+            Some(target) => match target.parse::<IpAddr>() {
+                Ok(ip) => livox.lidar_addr.ip() == ip,
+                Err(_) => livox.broadcast_code_str() == *target,
+            },
+        }
+    }
+
+    pub fn handshake_option(&self) -> HandshakeOption {
+        let h = &self.handshake;
+        let mut option = HandshakeOption::new();
+        if let Some(ip) = h.host_ip { option = option.user_ip(ip); }
+        if let Some(port) = h.cmd_port { option = option.cmd_port(port); }
+        if let Some(port) = h.data_port { option = option.data_port(port); }
+        if let Some(port) = h.imu_port { option = option.imu_port(port); }
+        option
+    }
+
+    /// Camera from the calibration file, or the built-in one, resized to the configured image size.
+    pub fn camera(&self) -> Result<Camera, ConfigError> {
+        let size = (self.image.width, self.image.height);
+        match &self.calibration.file {
+            Some(file) => calibration::load(file, size),
+            None => {
+                let mut camera = calibration::default_camera();
+                let intrinsics = camera.intrinsics;
+                camera.intrinsics = intrinsics.resized(size.0.unwrap_or(intrinsics.width), size.1.unwrap_or(intrinsics.height));
+                Ok(camera)
+            }
+        }
+    }
+
+    pub fn depth_window(&self) -> Duration {
+        Duration::from_millis(self.publish.depth_window_ms)
+    }
+
+    pub fn depth_graph_period(&self) -> Duration {
+        Duration::from_millis(self.publish.depth_graph_period_ms)
+    }
+
+    pub fn raw_points_period(&self) -> Duration {
+        Duration::from_millis(self.publish.raw_points_period_ms)
+    }
+
+    pub fn colour_period(&self) -> Duration {
+        Duration::from_millis(self.colour.period_ms)
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    fn parse_toml(text: &str) -> Result<Config, ConfigError> {
+        deserialize(&mut toml::Deserializer::new(text))
+    }
+
+    #[test]
+    fn test_config() {
+        let config = parse_toml(r#"
+            [device]
+            target = "3GGDJ6K00100101"
+
+            [publish]
+            depth_window_ms = 2000
+            densify = { method = "dilation", radius = 4 }
+
+            [[endpoints.depth_images]]
+            endpoint = "tcp://0.0.0.0:8100"
+
+            [[endpoints.depth_images]]
+            endpoint = "ipc:///tmp/depth"
+            encoding = "raw_f32"
+
+            [recording]
+            path = "out.buf"
+
+            [filter]
+            max_range = 60.0
+
+            [[crop]]
+            mode = "remove_inside"
+            region = { box = { min = [-0.5, -0.5, -1.0], max = [0.5, 0.5, 0.5] } }
+
+            [[sinks]]
+            type = "tcp"
+            listen = "0.0.0.0:8500"
+            outputs = ["packets", "depth_images"]
+
+            [[sinks]]
+            type = "shm"
+            name = "livox"
+        "#).unwrap();
+        assert!(config.validate().is_ok());
+        assert_eq!(config.depth_window(), Duration::from_secs(2));
+        assert_eq!(config.publish.densify.unwrap().radius, 4);
+        assert_eq!(config.endpoints.depth_images[0].encoding, DepthEncoding::Png16);
+        assert_eq!(config.endpoints.depth_images[1].encoding, DepthEncoding::RawF32);
+        assert_eq!(config.crop.crops.len(), 1);
+        assert!(config.filter.drop_zero);
+        assert_eq!(config.sinks[1], SinkConfig::Shm { name: "livox".into(), slots: 4096, memfd: false, outputs: vec![OutputKind::Batches] });
+        assert_eq!("shm:livox".parse(), Ok(config.sinks[1].clone()));
+        assert!(matches!("shm:memfd:livox".parse(), Ok(SinkConfig::Shm { memfd: true, .. })));
+        assert_eq!("udp:10.0.0.2:56001".parse(), Ok(SinkConfig::Udp { target: "10.0.0.2:56001".parse().unwrap() }));
+        assert!("pipe:x".parse::<SinkConfig>().is_err());
+
+        let yaml: Config = deserialize(serde_yaml::Deserializer::from_str("publish:\n  depth_graph_period_ms: 500\n")).unwrap();
+        assert_eq!(yaml.depth_graph_period(), Duration::from_millis(500));
+
+        let error = |text: &str| parse_toml(text).and_then(|c| c.validate()).unwrap_err().to_string();
+        assert!(error("[publish]\ndepth_window_ms = -1").contains("publish.depth_window_ms"));
+        assert!(error("[publish]\ndepth_widow_ms = 1").contains("depth_widow_ms"));
+        assert!(error("[handshake]\nhost_ip = \"192.168.1\"").contains("handshake.host_ip"));
+        assert!(error("[publish]\ndepth_graph_period_ms = 0").contains("publish.depth_graph_period_ms"));
+        assert!(error("[device]\ntarget = \"lidar\"").contains("device.target"));
+        assert!(error("[[endpoints.depth_images]]\nendpoint = \"tcp://:1\"\nencoding = \"bmp\"").contains("endpoints.depth_images[0].encoding"));
+        assert!(error("[[endpoints.depth_images]]\nendpoint = \"8100\"").contains("endpoints.depth_images[0].endpoint"));
+        assert_eq!("tcp://:1,raw_u16".parse(), Ok(DepthImageEndpoint { endpoint: "tcp://:1".into(), encoding: DepthEncoding::RawU16 }));
+        assert!(error("[publish]\nraw_points_period_ms = 0").contains("publish.raw_points_period_ms"));
+        assert!(error("[colour]\nenabled = true\nperiod_ms = 0").contains("colour.period_ms"));
+        assert!(error("[colour]\nenabled = true\nocclusion = { radius = 2, tolerance = -1.0 }").contains("colour.occlusion.tolerance"));
+        assert!(error("[[sinks]]\ntype = \"tcp\"\nlisten = \"0.0.0.0:1\"\noutputs = [\"points\"]").contains("sinks[0].outputs"));
+        assert!(error("[[sinks]]\ntype = \"pipe\"").contains("sinks[0]"));
+        assert!(error("[filter]\nmin_range = 10.0\nmax_range = 5.0").contains("filter.max_range"));
+    }
+
+    #[test]
+    fn test_examples() {
+        let dir = Path::new(env!("CARGO_MANIFEST_DIR"));
+        let mut config = Config::load(&dir.join("config.example.toml")).unwrap();
+        config.validate().unwrap();
+        config.calibration.file = Some(dir.join("calibration.example.yaml"));
+        assert_eq!(config.camera().unwrap().width(), 3072);
+    }
+}
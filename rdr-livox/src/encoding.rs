@@ -0,0 +1,139 @@
+use std::io::Cursor;
+use std::str::FromStr;
+use image::{ImageBuffer, ImageOutputFormat, Luma};
+use serde::Deserialize;
+
+use livox_rs::projection::DepthImage;
+
+/// How a depth image is published.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum DepthEncoding {
+    /// 16-bit grayscale PNG, in millimetres.
+    #[default]
+    Png16,
+    /// [`RawHeader`] then little-endian `f32` in metres.
+    RawF32,
+    /// [`RawHeader`] then little-endian `u16` in millimetres.
+    RawU16,
+}
+
+impl FromStr for DepthEncoding {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "png16" => Ok(DepthEncoding::Png16),
+            "raw_f32" => Ok(DepthEncoding::RawF32),
+            "raw_u16" => Ok(DepthEncoding::RawU16),
+            _ => Err(format!("unknown encoding {:?}, expected png16, raw_f32 or raw_u16", s)),
+        }
+    }
+}
+
+/// Header of raw depth buffers, little-endian, followed by the row major pixels:
+///
+/// | offset | type    |                                                       |
+/// |--------|---------|-------------------------------------------------------|
+/// | 0      | [u8; 4] | `LVXD`                                                |
+/// | 4      | u8      | version, `1`                                          |
+/// | 5      | u8      | pixel format, `1` for `f32`, `2` for `u16`            |
+/// | 6      | u16     | reserved                                              |
+/// | 8      | u32     | width                                                 |
+/// | 12     | u32     | height                                                |
+/// | 16     | f32     | scale, metres per unit                                |
+/// | 20     | u64     | timestamp of the newest return, nanoseconds           |
+///
+/// Pixels without depth are `0`.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct RawHeader {
+    pub format: u8,
+    pub width: u32,
+    pub height: u32,
+    pub scale: f32,
+    pub timestamp: u64,
+}
+
+impl RawHeader {
+    pub const MAGIC: &'static [u8; 4] = b"LVXD";
+    pub const VERSION: u8 = 1;
+    pub const LEN: usize = 28;
+
+    fn write(&self, out: &mut Vec<u8>) {
+        out.extend_from_slice(Self::MAGIC);
+        out.extend_from_slice(&[Self::VERSION, self.format, 0, 0]);
+        out.extend_from_slice(&self.width.to_le_bytes());
+        out.extend_from_slice(&self.height.to_le_bytes());
+        out.extend_from_slice(&self.scale.to_le_bytes());
+        out.extend_from_slice(&self.timestamp.to_le_bytes());
+    }
+
+    #[cfg(test)]
+    pub fn parse(bytes: &[u8]) -> Option<RawHeader> {
+        if bytes.len() < Self::LEN || &bytes[0..4] != Self::MAGIC || bytes[4] != Self::VERSION {
+            return None;
+        }
+        Some(RawHeader {
+            format: bytes[5],
+            width: u32::from_le_bytes(bytes[8..12].try_into().unwrap()),
+            height: u32::from_le_bytes(bytes[12..16].try_into().unwrap()),
+            scale: f32::from_le_bytes(bytes[16..20].try_into().unwrap()),
+            timestamp: u64::from_le_bytes(bytes[20..28].try_into().unwrap()),
+        })
+    }
+}
+
+/// Encode a depth image taken at `timestamp`. Blocking, call it off the async runtime.
+pub fn encode(depth: &DepthImage, encoding: DepthEncoding, timestamp: u64) -> Result<Vec<u8>, image::ImageError> {
+    let header = |format: u8, scale: f32| RawHeader { format, width: depth.width, height: depth.height, scale, timestamp };
+    match encoding {
+        DepthEncoding::Png16 => {
+            let img = ImageBuffer::<Luma<u16>, _>::from_raw(depth.width, depth.height, depth.to_millimetres()).unwrap();
+            let mut bytes = Vec::new();
+            img.write_to(&mut Cursor::new(&mut bytes), ImageOutputFormat::Png)?;
+            Ok(bytes)
+        }
+        DepthEncoding::RawF32 => {
+            let mut bytes = Vec::with_capacity(RawHeader::LEN + depth.depth.len() * 4);
+            header(1, 1.0).write(&mut bytes);
+            depth.depth.iter().for_each(|d| bytes.extend_from_slice(&d.to_le_bytes()));
+            Ok(bytes)
+        }
+        DepthEncoding::RawU16 => {
+            let mut bytes = Vec::with_capacity(RawHeader::LEN + depth.depth.len() * 2);
+            header(2, 0.001).write(&mut bytes);
+            depth.to_millimetres().iter().for_each(|d| bytes.extend_from_slice(&d.to_le_bytes()));
+            Ok(bytes)
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use nalgebra::Point2;
+    use livox_rs::projection::Projection;
+    use super::*;
+
+    #[test]
+    fn test_encode() {
+        let mut depth = DepthImage::new(8, 4);
+        depth.insert(&Projection { pixel: Point2::new(3.5, 2.5), depth: 12.345 });
+
+        let png = image::load_from_memory(&encode(&depth, DepthEncoding::Png16, 0).unwrap()).unwrap().into_luma16();
+        assert_eq!(png.dimensions(), (8, 4));
+        assert_eq!(png.get_pixel(3, 2).0, [12345]);
+        assert_eq!(png.get_pixel(0, 0).0, [0]);
+
+        let raw = encode(&depth, DepthEncoding::RawF32, 42).unwrap();
+        let header = RawHeader::parse(&raw).unwrap();
+        assert_eq!(header, RawHeader { format: 1, width: 8, height: 4, scale: 1.0, timestamp: 42 });
+        assert_eq!(raw.len(), RawHeader::LEN + 8 * 4 * 4);
+        let at = RawHeader::LEN + (2 * 8 + 3) * 4;
+        assert_eq!(f32::from_le_bytes(raw[at..at + 4].try_into().unwrap()), 12.345);
+
+        let raw = encode(&depth, DepthEncoding::RawU16, 42).unwrap();
+        assert_eq!(RawHeader::parse(&raw).unwrap().scale, 0.001);
+        let at = RawHeader::LEN + (2 * 8 + 3) * 2;
+        assert_eq!(u16::from_le_bytes(raw[at..at + 2].try_into().unwrap()), 12345);
+    }
+}
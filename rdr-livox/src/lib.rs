@@ -0,0 +1,262 @@
+use std::error::Error;
+use std::net::Ipv4Addr;
+use std::path::PathBuf;
+use std::sync::Arc;
+use async_stream::stream;
+use bytes::Bytes;
+use clap::Parser;
+use tokio::{time};
+use tokio::sync::{mpsc, Mutex};
+use tokio_stream::StreamExt;
+use tracing::{info, warn};
+
+use livox_rs::{Livox, parse_packet_stream};
+use livox_rs::clock_align::now_ns;
+use livox_rs::cloud::PointCloud;
+use livox_rs::depth::DepthAccumulator;
+use livox_rs::point::PointBatch;
+
+use config::{Config, ConfigError, DepthImageEndpoint, SinkConfig};
+use points::PointsAccumulator;
+use sink::{Output, OutputKind, Sink, Sinks};
+
+pub mod calibration;
+pub mod config;
+pub mod encoding;
+pub mod message;
+pub mod points;
+pub mod sink;
+
+/// Project the points of a Livox LiDAR on a camera image into depth maps, and send its data to sinks.
+#[derive(Parser, Debug)]
+#[command(version)]
+pub struct Args {
+    /// TOML or YAML configuration file.
+    #[arg(short, long)]
+    config: Option<PathBuf>,
+    /// Device to use, by broadcast code or IP address. Overrides `device.target`.
+    #[arg(short, long)]
+    target: Option<String>,
+    /// Overrides `handshake.host_ip`.
+    #[arg(long)]
+    host_ip: Option<Ipv4Addr>,
+    /// OpenCV YAML calibration. Overrides `calibration.file`.
+    #[arg(long)]
+    calibration: Option<PathBuf>,
+    /// Overrides `image.width`.
+    #[arg(long)]
+    width: Option<u32>,
+    /// Overrides `image.height`.
+    #[arg(long)]
+    height: Option<u32>,
+    /// Overrides `publish.depth_graph_period_ms`.
+    #[arg(long)]
+    depth_graph_period_ms: Option<u64>,
+    /// Publish depth images to `ENDPOINT[,ENCODING]`, encoding being png16 (default), raw_f32 or raw_u16.
+    /// Can be repeated, overrides `endpoints.depth_images`.
+    #[arg(long = "depth-image")]
+    depth_images: Vec<DepthImageEndpoint>,
+    /// Overrides `publish.raw_points_period_ms`.
+    #[arg(long)]
+    raw_points_period_ms: Option<u64>,
+    /// Overrides `endpoints.depth_pixels`.
+    #[arg(long)]
+    depth_pixels_endpoint: Option<String>,
+    /// Colour points with the camera frames received from this endpoint. Overrides `colour.camera_endpoint`.
+    #[arg(long)]
+    colour_camera: Option<String>,
+    /// Write coloured clouds as PLY to this directory. Overrides `colour.ply_dir`.
+    #[arg(long)]
+    ply_dir: Option<PathBuf>,
+    /// Also send outputs to `TYPE:ARG`, e.g. `udp:192.168.1.10:56001`, `tcp:0.0.0.0:8500`, `file:out.pcap`
+    /// or `shm:livox`. Can be repeated, added to `sinks`.
+    #[arg(long = "sink")]
+    sinks: Vec<SinkConfig>,
+    /// Overrides `recording.path`.
+    #[arg(long)]
+    record: Option<PathBuf>,
+    /// Do not record raw points.
+    #[arg(long, conflicts_with = "record")]
+    no_record: bool,
+}
+
+impl Args {
+    pub fn config(self) -> Result<Config, Box<dyn Error>> {
+        let mut config = match &self.config {
+            Some(path) => Config::load(path)?,
+            None => Config::default(),
+        };
+        if self.target.is_some() { config.device.target = self.target; }
+        if self.host_ip.is_some() { config.handshake.host_ip = self.host_ip; }
+        if self.calibration.is_some() { config.calibration.file = self.calibration; }
+        if self.width.is_some() { config.image.width = self.width; }
+        if self.height.is_some() { config.image.height = self.height; }
+        if let Some(period) = self.depth_graph_period_ms { config.publish.depth_graph_period_ms = period; }
+        if let Some(period) = self.raw_points_period_ms { config.publish.raw_points_period_ms = period; }
+        if !self.depth_images.is_empty() { config.endpoints.depth_images = self.depth_images; }
+        if let Some(endpoint) = self.depth_pixels_endpoint { config.endpoints.depth_pixels = endpoint; }
+        if let Some(endpoint) = self.colour_camera {
+            config.colour.enabled = true;
+            config.colour.camera_endpoint = endpoint;
+        }
+        if self.ply_dir.is_some() { config.colour.ply_dir = self.ply_dir; }
+        if let Some(path) = self.record {
+            config.recording.enabled = true;
+            config.recording.path = path;
+        }
+        if self.no_record { config.recording.enabled = false; }
+        config.sinks.extend(self.sinks);
+        config.validate()?;
+        Ok(config)
+    }
+}
+
+/// Colours the clouds received on its channel, their points timestamped by host time, and sends them to the sinks.
+pub type ColourTask = Box<dyn FnOnce(mpsc::Receiver<PointCloud>, Arc<Sinks>) + Send>;
+
+/// Outputs provided by other crates, like the rdr ones of rdr-livox-rdr.
+#[derive(Default)]
+pub struct Extensions {
+    /// Added to the sinks of the configuration.
+    pub sinks: Vec<(String, Box<dyn Sink>)>,
+    /// Started if `colour.enabled`, which is an error without it.
+    pub colour: Option<ColourTask>,
+}
+
+/// Connect to the LiDAR of the configuration and send its outputs until it goes away.
+#[tracing::instrument(skip(extensions))]
+pub async fn run(config: Arc<Config>, extensions: Extensions) -> Result<(), Box<dyn Error>> {
+    let camera = config.camera()?;
+    if config.colour.enabled && extensions.colour.is_none() {
+        return Err(ConfigError::invalid("colour.enabled", "camera frames are received over rdr, use rdr-livox-rdr").into());
+    }
+
+    let livox = {
+        let config = config.clone();
+        Livox::wait_for(move |livox| config.matches(livox)).await?
+    };
+    let sensor_id = livox.broadcast_code_str();
+    let client = livox.handshake(config.handshake_option()).await?;
+    client.set_sampling(true).await?;
+
+    let mut sinks = Sinks::new(&config, client.data_addr()?, &sensor_id).await?;
+    for (name, sink) in extensions.sinks {
+        sinks.push(name, sink);
+    }
+    let forward_packets = sinks.accepts(OutputKind::Packets);
+    let send_batches = sinks.accepts(OutputKind::Batches);
+    let send_depth_pixels = sinks.accepts(OutputKind::DepthPixels);
+    let mut points = sinks.accepts(OutputKind::Points)
+        .then(|| PointsAccumulator::new(sensor_id, config.raw_points_period()));
+    let encodings = sinks.depth_encodings();
+    let sinks = Arc::new(sinks);
+
+    // Packets go to the sinks as received, before being parsed and filtered
+    let packets = {
+        let sinks = sinks.clone();
+        let packets = client.packet_stream();
+        stream! {
+            tokio::pin!(packets);
+            while let Some(packet) = packets.next().await {
+                if let (true, Ok(packet)) = (forward_packets, &packet) {
+                    sinks.send(Output::Packet(packet.clone()));
+                }
+                yield packet;
+            }
+        }
+    };
+    let batches = parse_packet_stream(packets).map(|frame| frame.map(|frame| PointBatch::from_frame(&frame)));
+    let pc_stream = config.crop.clone().apply_stream(config.filter.clone().apply_stream(batches));
+    tokio::pin!(pc_stream);
+
+    let accumulator = Arc::new(Mutex::new(DepthAccumulator::for_camera(&camera, config.depth_window())));
+
+    // Publish depth images at a fixed rate, from whatever has been accumulated
+    if !encodings.is_empty() {
+        let accumulator = accumulator.clone();
+        let config = config.clone();
+        let sinks = sinks.clone();
+        tokio::spawn(async move {
+            let mut interval = time::interval(config.depth_graph_period());
+            loop {
+                interval.tick().await;
+                let (depth, timestamp) = {
+                    let accumulator = accumulator.lock().await;
+                    // sampled at the current time, so the image ages out when packets stop
+                    (accumulator.sample_at(now_ns()), accumulator.latest())
+                };
+                let densify = config.publish.densify;
+                let encodings = encodings.clone();
+                let start_time = time::Instant::now();
+                let images = tokio_rayon::spawn(move || {
+                    let depth = match densify {
+                        Some(densify) => densify.apply(&depth).depth,
+                        None => depth,
+                    };
+                    encodings.into_iter().map(|encoding| Ok((encoding, encoding::encode(&depth, encoding, timestamp)?))).collect::<Result<Vec<_>, image::ImageError>>()
+                }).await;
+                let images = match images {
+                    Ok(images) => images,
+                    Err(err) => {
+                        warn!("Failed to encode depth image: {}", err);
+                        continue;
+                    }
+                };
+                info!("Image sizes: {:?}kb, encoding used {}ms",
+                    images.iter().map(|(_, img)| img.len() / 1024).collect::<Vec<_>>(), start_time.elapsed().as_millis());
+                for (encoding, img_bytes) in images {
+                    sinks.send(Output::DepthImage { encoding, timestamp, data: Bytes::from(img_bytes) });
+                }
+            }
+        });
+    }
+
+    // Colour the points of every period with the camera frames, points timestamped by host time
+    let mut colouring = extensions.colour.filter(|_| config.colour.enabled).map(|colour| {
+        let (sender, receiver) = mpsc::channel(2);
+        colour(receiver, sinks.clone());
+        (sender, PointCloud::new(), time::Instant::now())
+    });
+
+
+    while let Some(pc) = pc_stream.next().await {
+        match pc {
+            Err(err) => warn!("Error happened when parsing data: {}", err),
+            Ok(batch) => {
+                let batch = Arc::new(batch);
+                if send_batches {
+                    sinks.send(Output::Batch(batch.clone()));
+                }
+
+                if let Some(frame) = points.as_mut().and_then(|accumulator| accumulator.push(&batch)) {
+                    sinks.send(Output::Points(Arc::new(frame)));
+                }
+
+                if let Some((sender, cloud, start)) = &mut colouring {
+                    cloud.points.extend(batch.iter().enumerate().map(|(i, mut p)| {
+                        p.timestamp = batch.host_time(i).unwrap_or(p.timestamp);
+                        p
+                    }));
+                    if start.elapsed() >= config.colour_period() {
+                        *start = time::Instant::now();
+                        if sender.try_send(std::mem::take(cloud)).is_err() {
+                            warn!("Colouring is falling behind, dropping a point cloud");
+                        }
+                    }
+                }
+
+                let projections = camera.project_batch(&batch).collect::<Arc<[_]>>();
+
+                let mut accumulator = accumulator.lock().await;
+                for (i, p) in projections.iter() {
+                    accumulator.insert(p, batch.host_time(*i).unwrap_or(batch.timestamp[*i]));
+                }
+
+                if send_depth_pixels {
+                    sinks.send(Output::DepthPixels { batch, projections });
+                }
+            }
+        }
+    }
+    Ok(())
+}
@@ -0,0 +1,100 @@
+use livox_rs::point::{BatchPoint, PointBatch};
+use livox_rs::projection::Projection;
+
+use crate::points::PointsFrame;
+
+/// Message types of a publisher, e.g. the protobuf messages of rdr, so that the mapping
+/// of outputs to messages is done, and tested, here. Implemented on a marker type, as the
+/// messages usually belong to another crate.
+pub trait Messages {
+    type Timestamp;
+    type RawPoint;
+    type RawPoints;
+    type DepthPixel;
+    type DepthPixels;
+
+    fn timestamp(seconds: i64, nanos: i32) -> Self::Timestamp;
+    /// Coordinates in millimetres.
+    fn raw_point(x: f32, y: f32, z: f32, reflectivity: u32, tag: u32) -> Self::RawPoint;
+    fn raw_points(timestamp: Self::Timestamp, sensor_id: String, points: Vec<Self::RawPoint>) -> Self::RawPoints;
+    /// Pixel indices, depth in millimetres.
+    fn depth_pixel(x: i32, y: i32, z: f32) -> Self::DepthPixel;
+    fn depth_pixels(timestamp: Self::Timestamp, pixels: Vec<Self::DepthPixel>) -> Self::DepthPixels;
+}
+
+/// Timestamp of nanoseconds since UNIX epoch.
+pub fn timestamp<M: Messages>(ns: u64) -> M::Timestamp {
+    M::timestamp((ns / 1_000_000_000) as i64, (ns % 1_000_000_000) as i32)
+}
+
+/// A point in millimetres, with its reflectivity and raw tag byte.
+pub fn raw_point<M: Messages>(p: &BatchPoint) -> M::RawPoint {
+    M::raw_point(p.point.x * 1000.0, p.point.y * 1000.0, p.point.z * 1000.0,
+                 p.point.reflectivity as u32, p.point.tag as u32)
+}
+
+pub fn raw_points<M: Messages>(frame: &PointsFrame) -> M::RawPoints {
+    M::raw_points(timestamp::<M>(frame.timestamp), frame.sensor_id.clone(), frame.points.iter().map(raw_point::<M>).collect())
+}
+
+/// The pixels points of `batch` project to, timestamped by the packet.
+pub fn depth_pixels<M: Messages>(batch: &PointBatch, projections: &[(usize, Projection)]) -> M::DepthPixels {
+    M::depth_pixels(timestamp::<M>(batch.packet.time()), projections.iter()
+        .map(|(_, p)| M::depth_pixel(p.pixel.x as i32, p.pixel.y as i32, p.depth * 1000.0))
+        .collect())
+}
+
+#[cfg(test)]
+mod test {
+    use nalgebra::Point2;
+    use livox_rs::model::data_type::LivoxPoint;
+    use super::*;
+
+    struct Plain;
+
+    impl Messages for Plain {
+        type Timestamp = (i64, i32);
+        type RawPoint = [f32; 5];
+        type RawPoints = ((i64, i32), String, Vec<[f32; 5]>);
+        type DepthPixel = (i32, i32, f32);
+        type DepthPixels = ((i64, i32), Vec<(i32, i32, f32)>);
+
+        fn timestamp(seconds: i64, nanos: i32) -> Self::Timestamp {
+            (seconds, nanos)
+        }
+
+        fn raw_point(x: f32, y: f32, z: f32, reflectivity: u32, tag: u32) -> Self::RawPoint {
+            [x, y, z, reflectivity as f32, tag as f32]
+        }
+
+        fn raw_points(timestamp: Self::Timestamp, sensor_id: String, points: Vec<Self::RawPoint>) -> Self::RawPoints {
+            (timestamp, sensor_id, points)
+        }
+
+        fn depth_pixel(x: i32, y: i32, z: f32) -> Self::DepthPixel {
+            (x, y, z)
+        }
+
+        fn depth_pixels(timestamp: Self::Timestamp, pixels: Vec<Self::DepthPixel>) -> Self::DepthPixels {
+            (timestamp, pixels)
+        }
+    }
+
+    #[test]
+    fn test_messages() {
+        assert_eq!(timestamp::<Plain>(1_658_583_906_500_000_001), (1_658_583_906, 500_000_001));
+
+        let point = BatchPoint {
+            point: LivoxPoint { x: 1.5, y: -0.25, z: 0.125, reflectivity: 42, tag: 0x10 },
+            ..BatchPoint::default()
+        };
+        let frame = PointsFrame { sensor_id: "3GGDJ6K00100101".into(), timestamp: 2_000_000_003, points: vec![point] };
+        assert_eq!(raw_points::<Plain>(&frame), ((2, 3), "3GGDJ6K00100101".into(), vec![[1500.0, -250.0, 125.0, 42.0, 16.0]]));
+
+        let mut batch = PointBatch::default();
+        batch.packet.timestamp = 7;
+        batch.packet.host_timestamp = Some(1_000_000_000);
+        let projections = [(0, Projection { pixel: Point2::new(320.7, 240.2), depth: 2.5 })];
+        assert_eq!(depth_pixels::<Plain>(&batch, &projections), ((1, 0), vec![(320, 240, 2500.0)]));
+    }
+}
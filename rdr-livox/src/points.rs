@@ -0,0 +1,76 @@
+use std::time::Duration;
+
+use livox_rs::cloud::CloudAccumulator;
+use livox_rs::point::{BatchPoint, PointBatch};
+
+/// Points of a period of sensor time.
+#[derive(Debug, Clone, PartialEq, Default)]
+pub struct PointsFrame {
+    /// Broadcast code of the LiDAR.
+    pub sensor_id: String,
+    /// [`PacketInfo::time`](livox_rs::point::PacketInfo::time) of the first batch, in nanoseconds.
+    pub timestamp: u64,
+    pub points: Vec<BatchPoint>,
+}
+
+/// Accumulates batches into [`PointsFrame`]s spanning a period of sensor time.
+#[derive(Debug, Clone)]
+pub struct PointsAccumulator {
+    sensor_id: String,
+    accumulator: CloudAccumulator,
+    /// [`PacketInfo::time`](livox_rs::point::PacketInfo::time) of the first batch of the current frame.
+    timestamp: u64,
+}
+
+impl PointsAccumulator {
+    /// `sensor_id` is the broadcast code of the LiDAR.
+    pub fn new(sensor_id: String, period: Duration) -> Self {
+        PointsAccumulator { sensor_id, accumulator: CloudAccumulator::new(period), timestamp: 0 }
+    }
+
+    /// Add a batch, returning the previous frame when the batch is past its period.
+    pub fn push(&mut self, batch: &PointBatch) -> Option<PointsFrame> {
+        if batch.is_empty() { return None; }
+        let starting = self.accumulator.is_empty();
+        let finished = self.accumulator.push(batch);
+        if !starting && finished.is_none() { return None; }
+        let timestamp = std::mem::replace(&mut self.timestamp, batch.packet.time());
+        finished.map(|cloud| PointsFrame { sensor_id: self.sensor_id.clone(), timestamp, points: cloud.points })
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use livox_rs::model::data_type::LivoxPoint;
+    use super::*;
+
+    #[test]
+    fn test_accumulate() {
+        const MS: u64 = 1_000_000;
+        let batch = |time: u64| {
+            let mut batch = PointBatch::default();
+            batch.packet.timestamp = time;
+            batch.packet.host_timestamp = Some(1_600_000_000_000 * MS + time);
+            batch.push(BatchPoint {
+                point: LivoxPoint { x: 1.0, y: -0.5, z: 0.25, reflectivity: 42, tag: 0x10 },
+                timestamp: time,
+                return_index: 0,
+            });
+            batch
+        };
+
+        let mut accumulator = PointsAccumulator::new("3GGDJ6K00100101".into(), Duration::from_millis(100));
+        assert!(accumulator.push(&batch(0)).is_none());
+        assert!(accumulator.push(&batch(50 * MS)).is_none());
+        let frame = accumulator.push(&batch(120 * MS)).unwrap();
+        assert_eq!(frame.sensor_id, "3GGDJ6K00100101");
+        assert_eq!(frame.points.len(), 2);
+        assert_eq!(frame.timestamp, 1_600_000_000_000 * MS);
+
+        // sensor time going backwards
+        let frame = accumulator.push(&batch(10 * MS)).unwrap();
+        assert_eq!(frame.points.len(), 1);
+        assert_eq!(frame.timestamp, 1_600_000_000_000 * MS + 120 * MS);
+        assert_eq!(frame.sensor_id, "3GGDJ6K00100101");
+    }
+}
@@ -0,0 +1,240 @@
+use std::error::Error;
+use std::io;
+use std::net::SocketAddr;
+use std::sync::Arc;
+use std::sync::atomic::{AtomicU64, Ordering};
+use async_trait::async_trait;
+use bytes::Bytes;
+use serde::Deserialize;
+use tokio::sync::mpsc;
+use tracing::{info, warn};
+
+use livox_rs::RawPacket;
+use livox_rs::point::PointBatch;
+use livox_rs::projection::Projection;
+
+use crate::config::{Config, SinkConfig};
+use crate::encoding::DepthEncoding;
+use crate::points::PointsFrame;
+
+pub mod file;
+#[cfg(feature = "shm")]
+pub mod shm;
+pub mod tcp;
+pub mod udp;
+
+/// Something rdr-livox produces, cheap to clone for every sink accepting it.
+#[derive(Debug, Clone)]
+pub enum Output {
+    /// A point cloud packet as received from the LiDAR.
+    Packet(RawPacket),
+    /// Filtered points of a packet.
+    Batch(Arc<PointBatch>),
+    /// Filtered points of a packet, with those visible in the camera.
+    DepthPixels { batch: Arc<PointBatch>, projections: Arc<[(usize, Projection)]> },
+    /// Filtered points of a period.
+    Points(Arc<PointsFrame>),
+    /// A depth image in one of the [`Sink::depth_encodings`], taken at `timestamp`.
+    DepthImage { encoding: DepthEncoding, timestamp: u64, data: Bytes },
+    /// A coloured point cloud as PLY, starting at `timestamp`.
+    ColouredCloud { timestamp: u64, data: Bytes },
+}
+
+impl Output {
+    pub fn kind(&self) -> OutputKind {
+        match self {
+            Output::Packet(_) => OutputKind::Packets,
+            Output::Batch(_) => OutputKind::Batches,
+            Output::DepthPixels { .. } => OutputKind::DepthPixels,
+            Output::Points(_) => OutputKind::Points,
+            Output::DepthImage { .. } => OutputKind::DepthImages,
+            Output::ColouredCloud { .. } => OutputKind::ColouredClouds,
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum OutputKind {
+    Packets,
+    Batches,
+    DepthPixels,
+    Points,
+    DepthImages,
+    ColouredClouds,
+}
+
+impl OutputKind {
+    const ALL: [OutputKind; 6] = [OutputKind::Packets, OutputKind::Batches, OutputKind::DepthPixels,
+        OutputKind::Points, OutputKind::DepthImages, OutputKind::ColouredClouds];
+}
+
+/// Where outputs go. Outputs no sink accepts are not produced at all.
+#[async_trait]
+pub trait Sink: Send {
+    fn accepts(&self, kind: OutputKind) -> bool;
+
+    /// Encodings depth images are wanted in, the sink only gets depth images in these.
+    fn depth_encodings(&self) -> Vec<DepthEncoding> {
+        Vec::new()
+    }
+
+    /// Only called with the outputs the sink accepts.
+    async fn send(&mut self, output: &Output) -> io::Result<()>;
+}
+
+/// Every configured sink, each running in its own task so a slow one neither holds up
+/// the others nor the packet loop.
+#[derive(Default)]
+pub struct Sinks {
+    sinks: Vec<SinkTask>,
+}
+
+struct SinkTask {
+    name: String,
+    kinds: Vec<OutputKind>,
+    encodings: Vec<DepthEncoding>,
+    sender: mpsc::Sender<Output>,
+    dropped: AtomicU64,
+}
+
+impl Sinks {
+    /// Outputs queued for a sink before new ones are dropped, about a second of packets.
+    pub const QUEUE_LEN: usize = 1024;
+
+    /// Sinks of the configuration, `data_addr` being where the packets of LiDAR `sensor_id` are received.
+    pub async fn new(config: &Config, data_addr: SocketAddr,
+                     #[cfg_attr(not(feature = "shm"), allow(unused_variables))] sensor_id: &str) -> Result<Sinks, Box<dyn Error>> {
+        let mut sinks = Sinks::default();
+        for sink in &config.sinks {
+            let built: Box<dyn Sink> = match sink {
+                SinkConfig::Udp { target } => Box::new(udp::UdpSink::new(*target).await?),
+                SinkConfig::Tcp { listen, outputs, depth_encoding } =>
+                    Box::new(tcp::TcpSink::new(*listen, outputs.clone(), *depth_encoding).await?),
+                SinkConfig::File { path } => Box::new(file::FileSink::create(path, data_addr)?),
+                #[cfg(feature = "shm")]
+                SinkConfig::Shm { name, slots, memfd, outputs } =>
+                    Box::new(shm::ShmSink::create(name, *slots, *memfd, outputs.clone(), sensor_id)?),
+                #[cfg(not(feature = "shm"))]
+                SinkConfig::Shm { .. } => return Err("shm sinks need rdr-livox built with the shm feature".into()),
+            };
+            sinks.push(sink.to_string(), built);
+        }
+        Ok(sinks)
+    }
+
+    /// Start a task sending to `sink` the outputs it accepts.
+    pub fn push(&mut self, name: String, mut sink: Box<dyn Sink>) {
+        info!("Sending to {}", name);
+        let kinds = OutputKind::ALL.into_iter().filter(|kind| sink.accepts(*kind)).collect();
+        let encodings = sink.depth_encodings();
+        let (sender, mut receiver) = mpsc::channel::<Output>(Self::QUEUE_LEN);
+        {
+            let name = name.clone();
+            // ends once the sinks are dropped
+            tokio::spawn(async move {
+                while let Some(output) = receiver.recv().await {
+                    if let Err(err) = sink.send(&output).await {
+                        warn!("Failed to send {:?} to {}: {}", output.kind(), name, err);
+                    }
+                }
+            });
+        }
+        self.sinks.push(SinkTask { name, kinds, encodings, sender, dropped: AtomicU64::new(0) });
+    }
+
+    pub fn accepts(&self, kind: OutputKind) -> bool {
+        self.sinks.iter().any(|sink| sink.kinds.contains(&kind))
+    }
+
+    /// Encodings depth images are wanted in by the sinks accepting them.
+    pub fn depth_encodings(&self) -> Vec<DepthEncoding> {
+        let mut encodings = self.sinks.iter()
+            .filter(|sink| sink.kinds.contains(&OutputKind::DepthImages))
+            .flat_map(|sink| sink.encodings.iter().copied()).collect::<Vec<_>>();
+        encodings.sort_by_key(|encoding| *encoding as u8);
+        encodings.dedup();
+        encodings
+    }
+
+    /// Queue for every sink accepting it without waiting, dropping it for sinks falling behind.
+    /// Depth images only go to the sinks wanting their encoding.
+    pub fn send(&self, output: Output) {
+        let kind = output.kind();
+        for sink in &self.sinks {
+            if !sink.kinds.contains(&kind) { continue; }
+            if let Output::DepthImage { encoding, .. } = &output {
+                if !sink.encodings.contains(encoding) { continue; }
+            }
+            if let Err(err) = sink.sender.try_send(output.clone()) {
+                let dropped = sink.dropped.fetch_add(1, Ordering::Relaxed);
+                if dropped % Self::QUEUE_LEN as u64 == 0 {
+                    warn!("Dropped {} outputs for {}, last one {:?}: {}", dropped + 1, sink.name, kind, err);
+                }
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    struct Collect {
+        kinds: Vec<OutputKind>,
+        encodings: Vec<DepthEncoding>,
+        name: &'static str,
+        sent: Arc<std::sync::Mutex<Vec<(&'static str, OutputKind)>>>,
+    }
+
+    #[async_trait]
+    impl Sink for Collect {
+        fn accepts(&self, kind: OutputKind) -> bool {
+            self.kinds.contains(&kind)
+        }
+
+        fn depth_encodings(&self) -> Vec<DepthEncoding> {
+            self.encodings.clone()
+        }
+
+        async fn send(&mut self, output: &Output) -> io::Result<()> {
+            self.sent.lock().unwrap().push((self.name, output.kind()));
+            Err(io::Error::other("full"))
+        }
+    }
+
+    #[tokio::test]
+    async fn test_sinks() {
+        let sent = Arc::new(std::sync::Mutex::new(Vec::new()));
+        let mut sinks = Sinks::default();
+        sinks.push("a".into(), Box::new(Collect {
+            name: "a",
+            kinds: vec![OutputKind::Packets, OutputKind::DepthImages],
+            encodings: vec![DepthEncoding::RawF32],
+            sent: sent.clone(),
+        }));
+        sinks.push("b".into(), Box::new(Collect {
+            name: "b",
+            kinds: vec![OutputKind::Packets],
+            encodings: vec![DepthEncoding::Png16, DepthEncoding::RawF32],
+            sent: sent.clone(),
+        }));
+        assert!(sinks.accepts(OutputKind::DepthImages));
+        assert!(!sinks.accepts(OutputKind::Points));
+        // b does not accept depth images
+        assert_eq!(sinks.depth_encodings(), [DepthEncoding::RawF32]);
+
+        // a failing sink does not keep the others from getting it, depth images only go in the wanted encoding
+        let data = Bytes::from_static(b"png");
+        sinks.send(Output::DepthImage { encoding: DepthEncoding::Png16, timestamp: 0, data: data.clone() });
+        sinks.send(Output::DepthImage { encoding: DepthEncoding::RawF32, timestamp: 0, data: data.clone() });
+        let packet = RawPacket { arrival: 0, src: "192.168.1.3:65000".parse().unwrap(), payload: data };
+        sinks.send(Output::Packet(packet));
+        while sent.lock().unwrap().len() < 3 {
+            tokio::task::yield_now().await;
+        }
+        let mut sent = sent.lock().unwrap().clone();
+        sent.sort_by_key(|(name, _)| *name);
+        assert_eq!(sent, [("a", OutputKind::DepthImages), ("a", OutputKind::Packets), ("b", OutputKind::Packets)]);
+    }
+}
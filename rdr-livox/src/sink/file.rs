@@ -0,0 +1,42 @@
+use std::io;
+use std::net::{Ipv4Addr, SocketAddr, SocketAddrV4};
+use std::path::Path;
+use async_trait::async_trait;
+use tracing::info;
+
+use livox_rs::capture::CaptureWriter;
+
+use super::{Output, OutputKind, Sink};
+
+/// Records the packets to a pcap file, which `livox-dump --read FILE --data-port PORT`
+/// or [`CaptureReader`](livox_rs::capture::CaptureReader) replay.
+pub struct FileSink {
+    writer: CaptureWriter<io::BufWriter<std::fs::File>>,
+}
+
+impl FileSink {
+    /// `data_addr` is where the packets are received, recorded as their destination.
+    pub fn create(path: &Path, data_addr: SocketAddr) -> io::Result<Self> {
+        let dst = match data_addr {
+            SocketAddr::V4(addr) => addr,
+            SocketAddr::V6(addr) => SocketAddrV4::new(Ipv4Addr::UNSPECIFIED, addr.port()),
+        };
+        let writer = CaptureWriter::create(path, dst).map_err(io::Error::other)?;
+        info!("Recording packets to {}, data port {}", path.display(), dst.port());
+        Ok(FileSink { writer })
+    }
+}
+
+#[async_trait]
+impl Sink for FileSink {
+    fn accepts(&self, kind: OutputKind) -> bool {
+        kind == OutputKind::Packets
+    }
+
+    async fn send(&mut self, output: &Output) -> io::Result<()> {
+        if let Output::Packet(packet) = output {
+            self.writer.write_packet(packet).map_err(io::Error::other)?;
+        }
+        Ok(())
+    }
+}
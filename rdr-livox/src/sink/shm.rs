@@ -0,0 +1,52 @@
+use std::io;
+use async_trait::async_trait;
+use tracing::{info, warn};
+
+use livox_rs::shm::{kind, ring_path, RingWriter};
+
+use super::{Output, OutputKind, Sink};
+
+/// Writes packets and point batches to a shared-memory ring buffer for the other processes
+/// of the host, read with [`RingReader`](livox_rs::shm::RingReader) without serialising them.
+pub struct ShmSink {
+    writer: RingWriter,
+    sensor_id: String,
+    outputs: Vec<OutputKind>,
+}
+
+impl ShmSink {
+    /// Room for a packet, a Mid-70 one is 1362 bytes, or a batch of up to 168 points.
+    const SLOT_SIZE: u32 = 4096;
+
+    /// `name` is a file of `/dev/shm`, or a path, or names the memfd if `memfd`.
+    /// Outputs other than packets and batches are ignored.
+    pub fn create(name: &str, slots: u32, memfd: bool, outputs: Vec<OutputKind>, sensor_id: &str) -> io::Result<Self> {
+        let writer = match memfd {
+            true => RingWriter::create_memfd(name, slots, Self::SLOT_SIZE),
+            false => RingWriter::create(ring_path(name), slots, Self::SLOT_SIZE),
+        }.map_err(io::Error::other)?;
+        info!("Writing {:?} to ring buffer {}", outputs, writer.path().display());
+        Ok(ShmSink { writer, sensor_id: sensor_id.into(), outputs })
+    }
+}
+
+#[async_trait]
+impl Sink for ShmSink {
+    fn accepts(&self, kind: OutputKind) -> bool {
+        self.outputs.contains(&kind)
+    }
+
+    async fn send(&mut self, output: &Output) -> io::Result<()> {
+        let dropped = match output {
+            Output::Packet(packet) => (!self.writer.write(kind::PACKET, packet.arrival, &packet.payload))
+                .then(|| format!("a packet of {} bytes", packet.payload.len())),
+            Output::Batch(batch) => (!self.writer.write_batch(&self.sensor_id, batch))
+                .then(|| format!("a batch of {} points", batch.len())),
+            _ => None,
+        };
+        if let Some(dropped) = dropped {
+            warn!("Dropped {}, larger than the ring slots", dropped);
+        }
+        Ok(())
+    }
+}
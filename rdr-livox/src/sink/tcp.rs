@@ -0,0 +1,111 @@
+use std::io;
+use std::net::SocketAddr;
+use std::sync::Arc;
+use async_trait::async_trait;
+use bytes::{BufMut, Bytes, BytesMut};
+use tokio::io::AsyncWriteExt;
+use tokio::net::TcpListener;
+use tokio::sync::{mpsc, Mutex};
+use tracing::{debug, info, warn};
+
+use crate::encoding::DepthEncoding;
+
+use super::{Output, OutputKind, Sink};
+
+/// Record queues of the connected clients.
+type Clients = Arc<Mutex<Vec<(SocketAddr, mpsc::Sender<Bytes>)>>>;
+
+/// Streams outputs to every connected client, as records of
+///
+/// | offset | type |                                                  |
+/// |--------|------|--------------------------------------------------|
+/// | 0      | u8   | `1` packet, `2` depth image, `3` coloured cloud  |
+/// | 1      | u32  | length of the data, little-endian                |
+/// | 5      | u64  | timestamp, nanoseconds since UNIX epoch, LE      |
+/// | 13     |      | the data                                         |
+///
+/// Packets are as they came from the LiDAR, depth images in the configured encoding and
+/// coloured clouds in PLY. A client too slow to keep up loses records rather than
+/// holding the others back.
+pub struct TcpSink {
+    outputs: Vec<OutputKind>,
+    depth_encoding: DepthEncoding,
+    clients: Clients,
+}
+
+impl TcpSink {
+    /// Records queued for a client before it loses some.
+    const CLIENT_QUEUE: usize = 1024;
+
+    /// Outputs other than packets, depth images and coloured clouds are ignored.
+    pub async fn new(listen: SocketAddr, outputs: Vec<OutputKind>, depth_encoding: DepthEncoding) -> io::Result<Self> {
+        let listener = TcpListener::bind(listen).await?;
+        info!("Serving {:?} on tcp://{}", outputs, listener.local_addr()?);
+        let clients = Arc::new(Mutex::new(Vec::new()));
+        tokio::spawn(Self::accept(listener, clients.clone()));
+        Ok(TcpSink { outputs, depth_encoding, clients })
+    }
+
+    async fn accept(listener: TcpListener, clients: Clients) {
+        loop {
+            let (mut stream, addr) = match listener.accept().await {
+                Ok(client) => client,
+                Err(err) => {
+                    warn!("Failed to accept TCP client: {:?}", err);
+                    continue;
+                }
+            };
+            info!("TCP client {} connected", addr);
+            let (sender, mut receiver) = mpsc::channel::<Bytes>(Self::CLIENT_QUEUE);
+            clients.lock().await.push((addr, sender));
+            tokio::spawn(async move {
+                while let Some(record) = receiver.recv().await {
+                    if let Err(err) = stream.write_all(&record).await {
+                        info!("TCP client {} disconnected: {}", addr, err);
+                        break;
+                    }
+                }
+            });
+        }
+    }
+
+    fn record(kind: u8, timestamp: u64, data: &[u8]) -> Bytes {
+        let mut record = BytesMut::with_capacity(13 + data.len());
+        record.put_u8(kind);
+        record.put_u32_le(data.len() as u32);
+        record.put_u64_le(timestamp);
+        record.put_slice(data);
+        record.freeze()
+    }
+}
+
+#[async_trait]
+impl Sink for TcpSink {
+    fn accepts(&self, kind: OutputKind) -> bool {
+        self.outputs.contains(&kind)
+    }
+
+    fn depth_encodings(&self) -> Vec<DepthEncoding> {
+        if self.accepts(OutputKind::DepthImages) { vec![self.depth_encoding] } else { Vec::new() }
+    }
+
+    async fn send(&mut self, output: &Output) -> io::Result<()> {
+        let record = match output {
+            Output::Packet(packet) => Self::record(1, packet.arrival, &packet.payload),
+            Output::DepthImage { encoding, timestamp, data } if *encoding == self.depth_encoding =>
+                Self::record(2, *timestamp, data),
+            Output::ColouredCloud { timestamp, data } => Self::record(3, *timestamp, data),
+            _ => return Ok(()),
+        };
+        let mut clients = self.clients.lock().await;
+        clients.retain(|(addr, sender)| match sender.try_send(record.clone()) {
+            Ok(()) => true,
+            Err(mpsc::error::TrySendError::Full(_)) => {
+                debug!("TCP client {} is too slow, dropping a record", addr);
+                true
+            }
+            Err(mpsc::error::TrySendError::Closed(_)) => false,
+        });
+        Ok(())
+    }
+}
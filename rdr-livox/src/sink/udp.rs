@@ -0,0 +1,39 @@
+use std::io;
+use std::net::{Ipv4Addr, Ipv6Addr, SocketAddr};
+use async_trait::async_trait;
+use tokio::net::UdpSocket;
+
+use super::{Output, OutputKind, Sink};
+
+/// Forwards the packets as they came from the LiDAR, one datagram each, e.g. to a host
+/// running the Livox SDK or another livox-rs program.
+pub struct UdpSink {
+    socket: UdpSocket,
+    target: SocketAddr,
+}
+
+impl UdpSink {
+    pub async fn new(target: SocketAddr) -> io::Result<Self> {
+        let local: SocketAddr = match target {
+            SocketAddr::V4(_) => (Ipv4Addr::UNSPECIFIED, 0).into(),
+            SocketAddr::V6(_) => (Ipv6Addr::UNSPECIFIED, 0).into(),
+        };
+        let socket = UdpSocket::bind(local).await?;
+        socket.set_broadcast(true)?;
+        Ok(UdpSink { socket, target })
+    }
+}
+
+#[async_trait]
+impl Sink for UdpSink {
+    fn accepts(&self, kind: OutputKind) -> bool {
+        kind == OutputKind::Packets
+    }
+
+    async fn send(&mut self, output: &Output) -> io::Result<()> {
+        if let Output::Packet(packet) = output {
+            self.socket.send_to(&packet.payload, self.target).await?;
+        }
+        Ok(())
+    }
+}